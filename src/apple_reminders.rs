@@ -0,0 +1,156 @@
+use crate::exchange::{group_by_list, parse_due, parse_flag, read_rows};
+use crate::todo_list::{Comment, Priority, TodoItem};
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+/// List of reminders that don't name theirs, the default list of the app
+const DEFAULT_LIST: &str = "Reminders";
+
+/// A reminder as exported by Shortcuts or EventKit based tools
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Reminder {
+    #[serde(alias = "name")]
+    title: String,
+    #[serde(default, alias = "calendar")]
+    list: Option<String>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    due_date: Option<String>,
+    #[serde(default, alias = "completed")]
+    is_completed: bool,
+    #[serde(default)]
+    completion_date: Option<String>,
+    /// 1 to 4 is high, 5 medium and 6 to 9 low, 0 none
+    #[serde(default)]
+    priority: u8,
+}
+
+impl Reminder {
+    /// Reads a CSV row, whose priority may also be a name like `High`
+    fn from_row(row: &HashMap<String, String>) -> Self {
+        let field = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| row.get(*name).filter(|value| !value.is_empty()))
+                .cloned()
+        };
+        let priority = field(&["priority"]).unwrap_or_default();
+        Self {
+            title: field(&["title", "name"]).unwrap_or_default(),
+            list: field(&["list", "calendar"]),
+            notes: field(&["notes"]),
+            due_date: field(&["due date", "due"]),
+            is_completed: field(&["completed", "is completed"])
+                .is_some_and(|value| parse_flag(&value)),
+            completion_date: field(&["completion date", "completed date"]),
+            priority: priority
+                .parse()
+                .unwrap_or_else(|_| match priority.parse::<Priority>() {
+                    Ok(Priority::High) => 1,
+                    Ok(Priority::Medium) => 5,
+                    Ok(Priority::Low) => 9,
+                    Err(_) => 0,
+                }),
+        }
+    }
+
+    /// Returns the name of the list of the reminder and the item for it
+    fn into_item(self) -> (String, TodoItem) {
+        let mut item = TodoItem::new(self.title);
+        item.priority = match self.priority {
+            1..=4 => Some(Priority::High),
+            5 => Some(Priority::Medium),
+            6..=9 => Some(Priority::Low),
+            _ => None,
+        };
+        if let Some(notes) = self.notes.filter(|notes| !notes.trim().is_empty()) {
+            item.comments.push(Comment {
+                at: Local::now().to_string(),
+                text: notes.trim().to_string(),
+            });
+        }
+        if let Some(due) = self.due_date.filter(|due| !due.is_empty()) {
+            match parse_due(&due) {
+                Some(deadline) => item.set_deadline(Some(deadline)),
+                None => eprintln!("Ignoring the due date '{}' of '{}'", due, item.description),
+            }
+        }
+        if self.is_completed {
+            item.complete();
+            if let Some(time) = self.completion_date.as_deref().and_then(parse_due) {
+                item.completed_at = Some(time.to_string());
+            }
+        }
+        let list = self.list.filter(|list| !list.is_empty());
+        (list.unwrap_or(DEFAULT_LIST.to_string()), item)
+    }
+}
+
+/// Imports reminders exported as a JSON array or a CSV file with a row per
+/// reminder, each list becoming a top-level item
+pub fn import(path: &Path) -> Result<Vec<TodoItem>> {
+    let reminders: Vec<Reminder> = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("{} is no Apple Reminders export", path.display()))?
+        }
+        _ => read_rows(path)?.iter().map(Reminder::from_row).collect(),
+    };
+    Ok(group_by_list(
+        reminders
+            .into_iter()
+            .filter(|reminder| !reminder.title.is_empty())
+            .map(Reminder::into_item)
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod apple_reminders_tests {
+    use super::*;
+
+    #[test]
+    fn lists() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("reminders.json");
+        fs::write(
+            &path,
+            r#"[
+                {"title": "Milk", "list": "Groceries", "dueDate": "2024-05-01T09:00:00Z", "priority": 1},
+                {"title": "Call Bob", "notes": "about the trip"},
+                {"title": "Bread", "list": "Groceries", "isCompleted": true,
+                 "completionDate": "2024-04-20T10:00:00+00:00"}
+            ]"#,
+        )?;
+        let items = import(&path)?;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].description, "Groceries");
+        let groceries = &items[0].sub_list.as_ref().unwrap().items;
+        assert_eq!(groceries[0].priority, Some(Priority::High));
+        assert!(groceries[0].deadline.is_some());
+        assert!(groceries[1].is_completed());
+        assert_eq!(items[1].description, "Reminders");
+
+        let csv = path.with_extension("csv");
+        fs::write(
+            &csv,
+            "List,Title,Due Date,Completed,Priority\nWork,Report,2024-05-01,No,Medium\n",
+        )?;
+        let items = import(&csv)?;
+        let report = &items[0].sub_list.as_ref().unwrap().items[0];
+        assert_eq!(report.priority, Some(Priority::Medium));
+        assert!(report
+            .deadline
+            .as_ref()
+            .unwrap()
+            .starts_with("2024-05-01T23:59:59"));
+        assert!(!report.is_completed());
+        Ok(())
+    }
+}
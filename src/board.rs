@@ -0,0 +1,205 @@
+use crate::todo_list::{Status, TodoItem, TodoList};
+use crate::utils::format_path;
+use anyhow::Result;
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{self, ClearType},
+};
+use std::io::{self, IsTerminal, Write};
+
+/// A kanban column, derived from the item's status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Todo,
+    InProgress,
+    Done,
+}
+
+impl Column {
+    const ALL: [Column; 3] = [Column::Todo, Column::InProgress, Column::Done];
+
+    fn of(item: &TodoItem) -> Self {
+        if item.is_completed() {
+            Column::Done
+        } else if item.is_started() {
+            Column::InProgress
+        } else {
+            Column::Todo
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Todo => "TODO",
+            Column::InProgress => "IN PROGRESS",
+            Column::Done => "DONE",
+        }
+    }
+
+    fn index(self) -> usize {
+        Column::ALL.iter().position(|c| *c == self).unwrap_or(0)
+    }
+
+    /// Returns the status of the items in this column
+    fn status(self) -> Status {
+        match self {
+            Column::Todo => Status::Todo,
+            Column::InProgress => Status::Started,
+            Column::Done => Status::Done,
+        }
+    }
+}
+
+/// A card on the board, referencing an item by its ID path
+struct Card {
+    path: String,
+    label: String,
+}
+
+/// Groups every item of the tree into columns
+fn collect_columns(todo_list: &TodoList) -> [Vec<Card>; 3] {
+    let mut columns: [Vec<Card>; 3] = Default::default();
+    todo_list.walk(&mut |path, item| {
+        columns[Column::of(item).index()].push(Card {
+            path: format_path(path),
+            label: format!("{}{}", "  ".repeat(path.len() - 1), item.description),
+        });
+    });
+    columns
+}
+
+/// Prints the board once, used when stdout is not a terminal
+fn print_board(todo_list: &TodoList) {
+    for (column, cards) in Column::ALL.iter().zip(collect_columns(todo_list)) {
+        println!("{} ({})", column.title(), cards.len());
+        for card in cards {
+            println!("  #{} {}", card.path, card.label.trim_start());
+        }
+    }
+}
+
+/// Runs the interactive kanban board
+///
+/// Keys: ←/→ or h/l change column, ↑/↓ or j/k select a card,
+/// H/L or </> move the selected card, q/Esc quit
+pub fn run_board(todo_list: &mut TodoList) -> Result<()> {
+    if !io::stdout().is_terminal() {
+        print_board(todo_list);
+        return Ok(());
+    }
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = board_loop(todo_list, &mut stdout);
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn board_loop(todo_list: &mut TodoList, stdout: &mut io::Stdout) -> Result<()> {
+    let mut focus = 0;
+    let mut selected = [0usize; 3];
+
+    loop {
+        let columns = collect_columns(todo_list);
+        for (column, cards) in columns.iter().enumerate() {
+            selected[column] = selected[column].min(cards.len().saturating_sub(1));
+        }
+        draw(stdout, &columns, focus, &selected)?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let shift = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Left | KeyCode::Char('h') => {
+                focus = focus.saturating_sub(1);
+                continue;
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                focus = (focus + 1).min(Column::ALL.len() - 1);
+                continue;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                selected[focus] = selected[focus].saturating_sub(1);
+                continue;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                selected[focus] += 1;
+                continue;
+            }
+            KeyCode::Char('H') | KeyCode::Char('<') => -1,
+            KeyCode::Char('L') | KeyCode::Char('>') => 1,
+            _ => continue,
+        };
+
+        let Some(card) = columns[focus].get(selected[focus]) else {
+            continue;
+        };
+        let target = focus
+            .saturating_add_signed(shift)
+            .min(Column::ALL.len() - 1);
+        if target != focus {
+            todo_list.set_status(&card.path, Column::ALL[target].status())?;
+            focus = target;
+        }
+    }
+}
+
+fn draw(
+    stdout: &mut io::Stdout,
+    columns: &[Vec<Card>; 3],
+    focus: usize,
+    selected: &[usize; 3],
+) -> Result<()> {
+    let (width, height) = terminal::size()?;
+    let column_width = (width as usize / Column::ALL.len()).max(4);
+    queue!(stdout, terminal::Clear(ClearType::All))?;
+
+    for (index, (column, cards)) in Column::ALL.iter().zip(columns).enumerate() {
+        let x = (index * column_width) as u16;
+        let title = format!("{} ({})", column.title(), cards.len());
+        queue!(stdout, cursor::MoveTo(x, 0), SetAttribute(Attribute::Bold))?;
+        queue!(stdout, Print(truncate(&title, column_width - 1)))?;
+        queue!(stdout, SetAttribute(Attribute::Reset))?;
+
+        for (row, card) in cards
+            .iter()
+            .enumerate()
+            .take((height as usize).saturating_sub(2))
+        {
+            let highlighted = index == focus && row == selected[index];
+            if highlighted {
+                queue!(stdout, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(
+                stdout,
+                cursor::MoveTo(x, row as u16 + 1),
+                Print(truncate(&card.label, column_width - 1))
+            )?;
+            if highlighted {
+                queue!(stdout, SetAttribute(Attribute::Reset))?;
+            }
+        }
+    }
+
+    queue!(
+        stdout,
+        cursor::MoveTo(0, height.saturating_sub(1)),
+        Print("h/l: column  j/k: card  H/L: move card  q: quit")
+    )?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Truncates a string to at most `width` characters
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
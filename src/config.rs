@@ -0,0 +1,320 @@
+use crate::errors;
+use crate::events::Event;
+use crate::utils::{expand_path, glob_match};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// User configuration read from `$XDG_CONFIG_HOME/td/config.toml`
+/// (or `~/.config/td/config.toml`), every setting is optional
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Move items completed longer ago than this (e.g. "14d") to the archive on save
+    pub auto_archive_after: Option<String>,
+    /// Your name on shared lists, used by `list --mine` (defaults to $USER)
+    pub identity: Option<String>,
+    /// Directory attached files are stored relative to
+    pub attachment_dir: Option<String>,
+    pub syntax: SyntaxConfig,
+    pub add: AddConfig,
+    pub ids: IdConfig,
+    pub wip: WipConfig,
+    pub storage: StorageConfig,
+    pub s3: S3Config,
+    pub webhooks: Vec<WebhookConfig>,
+    pub email: EmailConfig,
+    pub remind: RemindConfig,
+    pub dates: DatesConfig,
+    pub serve: ServeConfig,
+    /// Named todo files, picked with `td --list <name>` and shown together by
+    /// `td list --all-lists`
+    pub lists: BTreeMap<String, String>,
+    /// Directory patterns like `~/work/*` mapped to the named list td uses
+    /// inside them
+    pub contexts: BTreeMap<String, String>,
+}
+
+/// Calendar settings used when reading deadlines
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct DatesConfig {
+    /// Days `+3bd` and "next business day" skip besides weekends, given as
+    /// `2024-12-24` or as `12-25` for every year
+    pub holidays: Vec<String>,
+    /// Time like `18:00` or `6pm` a deadline given as a day alone is due at,
+    /// defaults to the end of the day
+    pub due_time: Option<String>,
+    /// Day weeks begin on for "next friday", `monday` unless set
+    pub week_start: Option<String>,
+    /// Show subtasks without a deadline as due with the item they belong to
+    pub inherit_deadlines: bool,
+}
+
+/// Settings of `td serve`
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct ServeConfig {
+    /// Token clients must send as `Authorization: Bearer <token>`, a new one
+    /// is made up for every run unless set. Required to listen beyond loopback
+    pub token: Option<String>,
+}
+
+/// Incoming-webhook urls `td remind --webhook` posts to
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct RemindConfig {
+    pub slack_url: Option<String>,
+    pub discord_url: Option<String>,
+    /// How long before a deadline `td remind --lead` reminds of an item,
+    /// defaults to 15m
+    pub lead: Option<String>,
+    /// Times like "22:00-08:00" during which `td remind --lead` holds its
+    /// reminders back until they end
+    pub quiet_hours: Option<String>,
+    /// Shortest time between two reminders of the same item, like 1h
+    pub throttle: Option<String>,
+}
+
+/// SMTP settings used by `td remind --email`
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct EmailConfig {
+    pub smtp_host: Option<String>,
+    /// Defaults to the standard port of the security mode
+    pub smtp_port: Option<u16>,
+    pub username: Option<String>,
+    /// Falls back to $TD_SMTP_PASSWORD
+    pub password: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub security: SmtpSecurity,
+}
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection, usually on port 587
+    #[default]
+    Starttls,
+    /// Implicit TLS, usually on port 465
+    Tls,
+    /// No encryption, only for local relays
+    None,
+}
+
+/// A url notified with a JSON payload when items are added, completed,
+/// removed or become overdue
+#[derive(Debug, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Events to send, all of them if empty
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+impl WebhookConfig {
+    /// Returns true if the webhook subscribed to `event`
+    pub fn wants(&self, event: Event) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// Location of a list kept in S3, used by `--file s3://` (requires the `s3` feature)
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct S3Config {
+    pub bucket: Option<String>,
+    /// Object key, defaults to "todo.json"
+    pub key: Option<String>,
+    /// Defaults to $AWS_REGION, then us-east-1
+    pub region: Option<String>,
+    /// Endpoint of an S3-compatible service like MinIO, addressed path-style
+    pub endpoint: Option<String>,
+}
+
+/// How the todo file is written
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Write compact instead of pretty-printed JSON
+    pub compact: bool,
+    /// Format of files whose extension doesn't name one
+    pub format: StorageFormat,
+    /// Append changes to `<file>.journal` instead of rewriting the whole file
+    pub journal: bool,
+    /// Fold the journal back into the file once it has this many entries
+    pub compact_journal_after: usize,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            compact: false,
+            format: StorageFormat::default(),
+            journal: false,
+            compact_journal_after: 100,
+        }
+    }
+}
+
+/// Serialization format of a todo file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageFormat {
+    #[default]
+    Json,
+    Msgpack,
+}
+
+impl StorageConfig {
+    /// Returns the format to write `path` in, a `.json`, `.msgpack` or `.mpk`
+    /// extension (before an optional `.gz`) taking precedence over the configured format
+    pub fn format_for(&self, path: &Path) -> StorageFormat {
+        let path = match path.extension() {
+            Some(extension) if extension == "gz" => path.file_stem().map_or(path, Path::new),
+            _ => path,
+        };
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => StorageFormat::Json,
+            Some("msgpack" | "mpk") => StorageFormat::Msgpack,
+            _ => self.format,
+        }
+    }
+}
+
+/// Work-in-progress limit for started items
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WipConfig {
+    /// Maximum number of items in progress at once
+    pub limit: Option<usize>,
+    /// Refuse to start more items than the limit instead of only warning
+    pub strict: bool,
+}
+
+impl Default for WipConfig {
+    fn default() -> Self {
+        Self {
+            limit: None,
+            strict: true,
+        }
+    }
+}
+
+/// How item IDs are allocated
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct IdConfig {
+    /// Never reuse the IDs of removed items, applied to every list on load
+    pub monotonic: bool,
+}
+
+/// Behaviour of `td add`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct AddConfig {
+    /// Detect natural-language deadline phrases like "by tomorrow" in descriptions
+    pub detect_deadline: bool,
+    /// Remove a detected deadline phrase from the stored description
+    pub strip_deadline_phrase: bool,
+}
+
+impl Default for AddConfig {
+    fn default() -> Self {
+        Self {
+            detect_deadline: false,
+            strip_deadline_phrase: true,
+        }
+    }
+}
+
+/// Token prefixes recognised by the inline add syntax
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SyntaxConfig {
+    pub priority: String,
+    pub tag: String,
+    pub context: String,
+    pub project: String,
+    pub deadline: String,
+}
+
+impl Default for SyntaxConfig {
+    fn default() -> Self {
+        Self {
+            priority: "!".to_string(),
+            tag: "#".to_string(),
+            context: "@".to_string(),
+            project: "+".to_string(),
+            deadline: "due:".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Returns the configured identity, falling back to the login name
+    pub fn identity(&self) -> Option<String> {
+        self.identity
+            .clone()
+            .or_else(|| env::var("USER").ok())
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Returns the file of the list called `name` in the `[lists]` table
+    pub fn list_file(&self, name: &str) -> Result<String> {
+        self.lists.get(name).cloned().ok_or_else(|| {
+            errors::not_found(format!(
+                "No list named '{}', add it to [lists] in the config",
+                name
+            ))
+        })
+    }
+
+    /// Returns the list `[contexts]` maps `dir` to, matching its deepest
+    /// ancestor (or itself) that a pattern matches
+    pub fn context_list(&self, dir: &Path) -> Result<Option<String>> {
+        let mut patterns = Vec::new();
+        for (pattern, name) in &self.contexts {
+            let pattern = expand_path(pattern)?.display().to_string();
+            patterns.push((pattern.trim_end_matches('/').to_string(), name));
+        }
+        for ancestor in dir.ancestors() {
+            let ancestor = ancestor.display().to_string();
+            if let Some((_, name)) = patterns
+                .iter()
+                .find(|(pattern, _)| glob_match(pattern, &ancestor))
+            {
+                return Ok(Some(name.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the directory holding td's configuration files
+    pub fn dir() -> Result<PathBuf> {
+        match env::var("XDG_CONFIG_HOME") {
+            Ok(dir) if !dir.is_empty() => Ok(PathBuf::from(dir).join("td")),
+            _ => {
+                let home = env::var("HOME").context("HOME environment variable not set")?;
+                Ok(PathBuf::from(home).join(".config").join("td"))
+            }
+        }
+    }
+
+    /// Loads the configuration file, or the defaults if it doesn't exist
+    pub fn load() -> Result<Self> {
+        let path = Self::dir()?.join("config.toml");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+
+/// Exit codes td ends with, so scripts can tell outcomes apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// A listing or query found nothing
+    NothingFound = 1,
+    /// The item, habit, milestone or list named doesn't exist
+    NotFound = 2,
+    /// An argument or value is malformed
+    Invalid = 3,
+    /// The change was refused, like a duplicate item or a reached WIP limit
+    Refused = 4,
+    /// Reading or writing the todo file or another file failed
+    Storage = 5,
+    /// The command line couldn't be parsed
+    Usage = 6,
+    /// Any other failure
+    Failure = 7,
+}
+
+/// An error ending td with a particular exit code
+#[derive(Debug)]
+pub struct CodedError {
+    pub code: ExitCode,
+    /// Printed on stderr unless empty
+    pub message: String,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for CodedError {}
+
+pub fn error(code: ExitCode, message: impl Into<String>) -> anyhow::Error {
+    CodedError {
+        code,
+        message: message.into(),
+    }
+    .into()
+}
+
+pub fn not_found(message: impl Into<String>) -> anyhow::Error {
+    error(ExitCode::NotFound, message)
+}
+
+pub fn invalid(message: impl Into<String>) -> anyhow::Error {
+    error(ExitCode::Invalid, message)
+}
+
+pub fn refused(message: impl Into<String>) -> anyhow::Error {
+    error(ExitCode::Refused, message)
+}
+
+/// Ends td with `NothingFound` after the command printed that it found nothing
+pub fn nothing_found() -> anyhow::Error {
+    error(ExitCode::NothingFound, "")
+}
+
+/// Returns the exit code of an error, the one of the outermost coded error in
+/// its chain, `Storage` for I/O errors and `Failure` for anything else
+pub fn exit_code(err: &anyhow::Error) -> ExitCode {
+    if let Some(coded) = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<CodedError>())
+    {
+        return coded.code;
+    }
+    if err.chain().any(|cause| cause.is::<std::io::Error>()) {
+        ExitCode::Storage
+    } else {
+        ExitCode::Failure
+    }
+}
+
+/// Returns true if the error has something to print, a `nothing_found` one doesn't
+pub fn has_message(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<CodedError>()
+        .is_none_or(|coded| !coded.message.is_empty())
+}
+
+#[cfg(test)]
+mod errors_tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn codes() {
+        let err = Err::<(), _>(not_found("No todo item matches 'x'"))
+            .context("Invalid patch 1")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), ExitCode::NotFound);
+        assert!(has_message(&err));
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        assert_eq!(exit_code(&anyhow::Error::from(io)), ExitCode::Storage);
+        assert_eq!(exit_code(&anyhow::anyhow!("other")), ExitCode::Failure);
+        assert!(!has_message(&nothing_found()));
+    }
+}
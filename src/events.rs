@@ -0,0 +1,131 @@
+use crate::todo_list::{TodoItem, TodoList};
+use crate::utils::format_path;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Something that happened to an item, reported to webhooks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    Add,
+    Complete,
+    Remove,
+    Overdue,
+}
+
+/// An event together with the item it happened to and the item's ID path
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemEvent {
+    pub event: Event,
+    pub path: String,
+    pub item: TodoItem,
+}
+
+/// Collects every item of a list by uuid, with its ID path
+fn items_by_uuid(list: &TodoList) -> HashMap<&str, (String, &TodoItem)> {
+    list.iter()
+        .map(|(path, item)| (item.uuid.as_str(), (format_path(&path), item)))
+        .collect()
+}
+
+/// Returns the items added, completed and removed between two versions of a list
+///
+/// Items are matched by uuid, so moving or renumbering an item is no event
+pub fn changes(before: &TodoList, after: &TodoList) -> Vec<ItemEvent> {
+    let old = items_by_uuid(before);
+    let new = items_by_uuid(after);
+    let mut events = Vec::new();
+    after.walk(&mut |path, item| {
+        let event = match old.get(item.uuid.as_str()) {
+            None => Event::Add,
+            Some((_, old_item)) if item.is_completed() && !old_item.is_completed() => {
+                Event::Complete
+            }
+            Some(_) => return,
+        };
+        events.push(ItemEvent {
+            event,
+            path: format_path(path),
+            item: item.clone(),
+        });
+    });
+    before.walk(&mut |_, item| {
+        if !new.contains_key(item.uuid.as_str()) {
+            let (path, item) = &old[item.uuid.as_str()];
+            events.push(ItemEvent {
+                event: Event::Remove,
+                path: path.clone(),
+                item: (*item).clone(),
+            });
+        }
+    });
+    events
+}
+
+/// Returns pending items whose deadline passed after `since` and before `now`
+pub fn newly_overdue(
+    list: &TodoList,
+    since: DateTime<Local>,
+    now: DateTime<Local>,
+) -> Vec<ItemEvent> {
+    let mut events = Vec::new();
+    list.walk(&mut |path, item| {
+        if item.is_overdue(now)
+            && item
+                .deadline_time()
+                .is_some_and(|deadline| deadline >= since)
+        {
+            events.push(ItemEvent {
+                event: Event::Overdue,
+                path: format_path(path),
+                item: item.clone(),
+            });
+        }
+    });
+    events
+}
+
+#[cfg(test)]
+mod events_tests {
+    use super::*;
+    use anyhow::Result;
+    use chrono::Duration;
+
+    #[test]
+    fn list_changes() -> Result<()> {
+        let mut before = TodoList::new();
+        before.add_item("a".to_string(), None, None)?;
+        before.add_item("b".to_string(), None, None)?;
+        before.add_item("c".to_string(), None, None)?;
+        let mut after = before.clone();
+        after.complete_item(&"0".to_string())?;
+        after.remove_item("1")?;
+        after.move_item(&"2".to_string(), Some(&"0".to_string()))?;
+        after.add_item("d".to_string(), None, None)?;
+
+        let events = changes(&before, &after);
+        let events: Vec<(Event, &str)> = events
+            .iter()
+            .map(|event| (event.event, event.item.description.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (Event::Complete, "a"),
+                (Event::Add, "d"),
+                (Event::Remove, "b")
+            ]
+        );
+
+        let now = Local::now();
+        let mut list = TodoList::new();
+        list.add_item("late".to_string(), Some(now - Duration::hours(1)), None)?;
+        list.add_item("old".to_string(), Some(now - Duration::days(3)), None)?;
+        list.add_item("later".to_string(), Some(now + Duration::hours(1)), None)?;
+        let overdue = newly_overdue(&list, now - Duration::days(1), now);
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].item.description, "late");
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+use crate::apple_reminders;
+use crate::config::DatesConfig;
+use crate::ics;
+use crate::markdown;
+use crate::microsoft_todo;
+use crate::org;
+use crate::spreadsheet;
+use crate::todo_list::{new_uuid, TodoItem, TodoList};
+use crate::todoist;
+use crate::utils::migrate;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Formats of other tools `td import` and `td export` understand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Todoist backups: a CSV file per project, or a JSON backup of all of them
+    Todoist,
+    /// Emacs org-mode headings with TODO keywords
+    Org,
+    /// One row per item with the columns of `spreadsheet::COLUMNS`
+    Csv,
+    /// Upcoming events and open todos of an iCalendar file, import only
+    Ics,
+    /// Apple Reminders exported as JSON or CSV, import only
+    AppleReminders,
+    /// Microsoft To Do lists exported as JSON or CSV, import only
+    MicrosoftTodo,
+    /// Items as td stores them, to move a subtree to another list
+    Json,
+    /// Markdown checklists, sub items indented below their parent
+    Md,
+}
+
+/// Counts of the items an import added and updated
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub added: usize,
+    pub updated: usize,
+}
+
+impl ImportReport {
+    fn add(&mut self, other: ImportReport) {
+        self.added += other.added;
+        self.updated += other.updated;
+    }
+}
+
+/// An item with the items nested below it, before IDs are assigned
+pub struct Node {
+    pub item: TodoItem,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn new(item: TodoItem) -> Self {
+        Self {
+            item,
+            children: Vec::new(),
+        }
+    }
+
+    /// Adds `node` `depth` levels below this one, as far down as the last
+    /// children reach
+    pub fn insert(&mut self, depth: usize, node: Node) {
+        match self.children.last_mut() {
+            Some(last) if depth > 0 => last.insert(depth - 1, node),
+            _ => self.children.push(node),
+        }
+    }
+
+    pub fn into_item(self) -> TodoItem {
+        let mut item = self.item;
+        if !self.children.is_empty() {
+            let mut sub_list = TodoList::new();
+            for child in self.children {
+                sub_list.push_item(child.into_item());
+            }
+            item.sub_list = Some(sub_list);
+        }
+        item
+    }
+}
+
+/// Parses a due date of another tool, which is a date, a floating date-time or
+/// one with an offset, a date alone meaning the end of that day
+pub fn parse_due(date: &str) -> Option<DateTime<Local>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(date) {
+        return Some(time.with_timezone(&Local));
+    }
+    let time = NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M"))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map(|date| date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap()))
+        })
+        .ok()?;
+    time.and_local_timezone(Local).earliest()
+}
+
+/// Reads the rows of a CSV file as maps from lowercase column names to values
+pub fn read_rows(path: &Path) -> Result<Vec<HashMap<String, String>>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let headers: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|header| header.trim().to_lowercase())
+        .collect();
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        rows.push(
+            headers
+                .iter()
+                .cloned()
+                .zip(record.iter().map(|field| field.trim().to_string()))
+                .collect(),
+        );
+    }
+    Ok(rows)
+}
+
+/// Returns whether a CSV field like `Yes`, `true`, `1` or `x` is set
+pub fn parse_flag(value: &str) -> bool {
+    matches!(
+        value.to_lowercase().as_str(),
+        "yes" | "true" | "1" | "x" | "completed" | "done"
+    )
+}
+
+/// Collects items by the name of their list into top-level items named after
+/// the lists, in the order the lists first appear
+pub fn group_by_list(items: Vec<(String, TodoItem)>) -> Vec<TodoItem> {
+    let mut lists: Vec<(String, TodoList)> = Vec::new();
+    for (name, item) in items {
+        let index = match lists.iter().position(|(list, _)| *list == name) {
+            Some(index) => index,
+            None => {
+                lists.push((name, TodoList::new()));
+                lists.len() - 1
+            }
+        };
+        lists[index].1.push_item(item);
+    }
+    lists
+        .into_iter()
+        .map(|(name, sub_list)| TodoItem {
+            sub_list: Some(sub_list),
+            ..TodoItem::new(name)
+        })
+        .collect()
+}
+
+/// Reads the items of a JSON file, giving those whose uuid the list already
+/// has a new one so a subtree can be grafted more than once
+///
+/// The file may hold an exported array, a single item or a whole todo file, and
+/// is migrated from the format version it was written in first
+fn import_json(todo_list: &TodoList, path: &Path) -> Result<Vec<TodoItem>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("{} holds no td items", path.display()))?;
+    // exported items carry no version, migrating leaves current ones unchanged
+    let mut root = match value {
+        Value::Array(_) => json!({ "items": value }),
+        Value::Object(ref object) if !object.contains_key("items") => json!({ "items": [value] }),
+        _ => value,
+    };
+    migrate(&mut root)?;
+    let items: Vec<TodoItem> = serde_json::from_value(root["items"].take())
+        .with_context(|| format!("{} holds no td items", path.display()))?;
+    let mut list = TodoList::new();
+    for item in items {
+        list.push_item(item);
+    }
+    list.walk_mut(&mut |item| {
+        if todo_list.find_by_uuid(&item.uuid).is_some() {
+            item.uuid = new_uuid();
+        }
+    });
+    Ok(list.items)
+}
+
+/// Adds the items of files of another tool to the list, below `under` if given
+///
+/// CSV rows and calendar events of items the list already has update them instead
+pub fn import(
+    format: Format,
+    todo_list: &mut TodoList,
+    paths: &[PathBuf],
+    under: Option<&String>,
+    dates: &DatesConfig,
+) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    for path in paths {
+        let items = match format {
+            Format::Todoist => todoist::import(path, dates)?,
+            Format::Org => org::import(path)?,
+            Format::AppleReminders => apple_reminders::import(path)?,
+            Format::MicrosoftTodo => microsoft_todo::import(path)?,
+            Format::Json => import_json(todo_list, path)?,
+            Format::Md => markdown::import(path)?,
+            Format::Csv => {
+                report.add(spreadsheet::import(todo_list, path, under)?);
+                continue;
+            }
+            Format::Ics => {
+                report.add(ics::import(todo_list, path, under)?);
+                continue;
+            }
+        };
+        let list = todo_list.list_at(under)?;
+        for item in items {
+            let item = list.push_item(item);
+            report.added += 1 + item
+                .sub_list
+                .as_ref()
+                .map_or(0, |sub_list| sub_list.count_where(|_| true));
+        }
+    }
+    Ok(report)
+}
+
+/// Writes text to the output file, or stdout if there is none
+fn write_text(output: Option<&Path>, text: &str) -> Result<()> {
+    match output {
+        Some(path) => Ok(std::fs::write(path, text)?),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/// Writes the list for another tool, to stdout if no output is given
+pub fn export(format: Format, todo_list: &TodoList, output: Option<&Path>) -> Result<()> {
+    match format {
+        Format::Todoist => match output {
+            Some(dir) if dir.extension().is_none_or(|extension| extension != "json") => {
+                todoist::export_csv(todo_list, dir)
+            }
+            Some(path) => Ok(std::fs::write(
+                path,
+                serde_json::to_string_pretty(&todoist::export_json(todo_list))?,
+            )?),
+            None => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&todoist::export_json(todo_list))?
+                );
+                Ok(())
+            }
+        },
+        Format::Org => write_text(output, &org::export(todo_list)),
+        Format::Json => write_text(
+            output,
+            &format!("{}\n", serde_json::to_string_pretty(&todo_list.items)?),
+        ),
+        Format::Md => write_text(output, &markdown::export(todo_list)),
+        Format::Csv => match output {
+            Some(path) => spreadsheet::export(todo_list, std::fs::File::create(path)?),
+            None => spreadsheet::export(todo_list, std::io::stdout()),
+        },
+        Format::Ics | Format::AppleReminders | Format::MicrosoftTodo => {
+            let name = clap::ValueEnum::to_possible_value(&format)
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default();
+            Err(anyhow::anyhow!("Exporting to {} is not supported", name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod exchange_tests {
+    use super::*;
+
+    #[test]
+    fn import_old_json() -> Result<()> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join("v0.json");
+        let items = import_json(&TodoList::new(), &path)?;
+        assert!(items[0].is_started());
+        assert!(items[0].sub_list.as_ref().unwrap().items[0].is_completed());
+        assert!(items[0].deadline.as_ref().unwrap().contains('T'));
+
+        // exported items of the current format come through as they are
+        let dir = tempfile::tempdir()?;
+        let exported = dir.path().join("export.json");
+        std::fs::write(&exported, serde_json::to_string(&items)?)?;
+        let again = import_json(&TodoList::new(), &exported)?;
+        assert_eq!(serde_json::to_value(&again)?, serde_json::to_value(&items)?);
+        Ok(())
+    }
+}
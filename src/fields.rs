@@ -0,0 +1,420 @@
+use crate::config::DatesConfig;
+use crate::errors;
+use crate::recurrence::Recurrence;
+use crate::remind::parse_reminder;
+use crate::todo_list::{Priority, Status, TodoItem, TodoList};
+use crate::utils::{format_deadline, parse_deadline};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// How an assignment changes a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// `field=value` replaces the value, an empty one clears it
+    Set,
+    /// `field+=value` adds to a list field
+    Add,
+    /// `field-=value` removes from a list field
+    Remove,
+}
+
+/// A `field=value`, `field+=value` or `field-=value` argument of `td set`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignment {
+    pub field: String,
+    pub op: Op,
+    pub value: String,
+}
+
+impl FromStr for Assignment {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, value) = s.split_once('=').ok_or_else(|| {
+            errors::invalid(format!("Invalid assignment '{}', expected field=value", s))
+        })?;
+        let (field, op) = match name.chars().last() {
+            Some('+') => (&name[..name.len() - 1], Op::Add),
+            Some('-') => (&name[..name.len() - 1], Op::Remove),
+            _ => (name, Op::Set),
+        };
+        Ok(Self {
+            field: field.trim().to_lowercase(),
+            op,
+            value: value.to_string(),
+        })
+    }
+}
+
+/// Fields `td set` can change, lists being the ones taking `+=` and `-=`
+pub const FIELDS: [&str; 15] = [
+    "description",
+    "deadline",
+    "priority",
+    "status",
+    "tags",
+    "context",
+    "project",
+    "url",
+    "assignee",
+    "waiting",
+    "starred",
+    "someday",
+    "attachments",
+    "repeat",
+    "remind",
+];
+
+/// Returns None for an empty value, which clears an optional field
+fn optional(value: &str) -> Option<String> {
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "1" => Ok(true),
+        "false" | "no" | "0" | "" => Ok(false),
+        _ => Err(errors::invalid(format!(
+            "Invalid flag '{}', expected true or false",
+            value
+        ))),
+    }
+}
+
+/// Changes a list field, values being separated by any of `separators`
+fn apply_list(list: &mut Vec<String>, op: Op, value: &str, separators: &[char]) {
+    let values = value
+        .split(separators)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(String::from);
+    match op {
+        Op::Set => *list = values.collect(),
+        Op::Add => {
+            for value in values {
+                if !list.contains(&value) {
+                    list.push(value);
+                }
+            }
+        }
+        Op::Remove => {
+            let values: Vec<String> = values.collect();
+            list.retain(|existing| !values.contains(existing));
+        }
+    }
+}
+
+fn parse_status(value: &str) -> Result<Status> {
+    match value.to_lowercase().as_str() {
+        "todo" => Ok(Status::Todo),
+        "started" => Ok(Status::Started),
+        "done" => Ok(Status::Done),
+        _ => Err(errors::invalid(format!(
+            "Invalid status '{}', expected todo, started or done",
+            value
+        ))),
+    }
+}
+
+impl Assignment {
+    /// Applies the assignment to the item at `path`, touching it
+    ///
+    /// Statuses are set through the list, so completing a repeating item
+    /// brings back its next occurrence
+    pub fn apply_at(
+        &self,
+        todo_list: &mut TodoList,
+        path: &String,
+        dates: &DatesConfig,
+    ) -> Result<()> {
+        if let ("status", Op::Set) = (self.field.as_str(), self.op) {
+            todo_list.set_status(path, parse_status(&self.value)?)?;
+            todo_list.parse_path(path)?.touch();
+            return Ok(());
+        }
+        self.apply(todo_list.parse_path(path)?, dates)
+    }
+
+    /// Applies the assignment to a single item, touching it
+    fn apply(&self, item: &mut TodoItem, dates: &DatesConfig) -> Result<()> {
+        let value = self.value.as_str();
+        match (self.field.as_str(), self.op) {
+            ("tags", op) => apply_list(
+                &mut item.tags,
+                op,
+                value.trim_start_matches('#'),
+                &[',', ' '],
+            ),
+            ("attachments", op) => apply_list(&mut item.attachments, op, value, &[',', ' ']),
+            // reminders like "1d 12h" hold spaces themselves
+            ("remind", op) => {
+                apply_list(&mut item.remind, op, value, &[',']);
+                for reminder in &item.remind {
+                    parse_reminder(reminder)?;
+                }
+            }
+            (field, Op::Add | Op::Remove) if FIELDS.contains(&field) => {
+                return Err(errors::invalid(format!(
+                    "{} is no list, use {}=value instead",
+                    field, field
+                )))
+            }
+            ("description", _) => {
+                item.description =
+                    optional(value).ok_or_else(|| errors::invalid("Description cannot be empty"))?
+            }
+            ("deadline", _) => {
+                item.deadline = match optional(value) {
+                    Some(deadline) => Some(format_deadline(parse_deadline(Some(deadline), dates)?)),
+                    None => None,
+                }
+            }
+            ("priority", _) => {
+                item.priority = optional(value)
+                    .map(|priority| Priority::from_str(&priority))
+                    .transpose()?
+            }
+            ("status", _) => match parse_status(value)? {
+                Status::Todo => item.reopen(),
+                Status::Started => item.start(),
+                Status::Done => item.complete(),
+            },
+            ("context", _) => item.context = optional(value),
+            ("project", _) => item.project = optional(value),
+            ("url", _) => item.url = optional(value),
+            ("assignee", _) => item.assignee = optional(value),
+            ("waiting", _) => item.waiting_on = optional(value),
+            ("starred", _) => item.starred = parse_bool(value)?,
+            ("someday", _) => item.someday = parse_bool(value)?,
+            ("repeat", _) => {
+                item.repeat = optional(value);
+                if let Some(repeat) = &item.repeat {
+                    repeat.parse::<Recurrence>()?;
+                }
+            }
+            (field, _) => {
+                return Err(errors::invalid(format!(
+                    "Unknown field '{}', expected one of {}",
+                    field,
+                    FIELDS.join(", ")
+                )))
+            }
+        }
+        item.touch();
+        Ok(())
+    }
+}
+
+/// Returns the value of a field of the item at `path` as `td get` prints it,
+/// None if it isn't set
+///
+/// Besides the fields `td set` changes these are `path`, `id`, `uuid`,
+/// `created`, `modified` and `completed`, lists are separated by spaces
+pub fn get(item: &TodoItem, path: &str, field: &str) -> Result<Option<String>> {
+    let flag = |set: bool| Some(set.to_string());
+    let list = |values: &[String]| Some(values.join(" ")).filter(|values| !values.is_empty());
+    Ok(match field.to_lowercase().as_str() {
+        "path" => Some(path.to_string()),
+        "id" => Some(item.id.to_string()),
+        "uuid" => Some(item.uuid.clone()),
+        "description" => Some(item.description.clone()),
+        "deadline" => item.deadline.clone(),
+        "priority" => item.priority.map(|priority| priority.to_string()),
+        "status" => Some(
+            match item.status {
+                Status::Todo => "todo",
+                Status::Started => "started",
+                Status::Done => "done",
+            }
+            .to_string(),
+        ),
+        "tags" => list(&item.tags),
+        "attachments" => list(&item.attachments),
+        "remind" => list(&item.remind),
+        "context" => item.context.clone(),
+        "project" => item.project.clone(),
+        "url" => item.url.clone(),
+        "assignee" => item.assignee.clone(),
+        "waiting" => item.waiting_on.clone(),
+        "starred" => flag(item.starred),
+        "someday" => flag(item.someday),
+        "repeat" => item.repeat.clone(),
+        "created" => item.created_at.clone(),
+        "modified" => item.modified_at.clone(),
+        "completed" => item.completed_at.clone().filter(|_| item.is_completed()),
+        field => {
+            return Err(errors::invalid(format!(
+                "Unknown field '{}', expected path, id, uuid, created, modified, completed or one of {}",
+                field,
+                FIELDS.join(", ")
+            )))
+        }
+    })
+}
+
+/// Turns a JSON value of a patch into the text of an assignment, null clearing
+/// the field and arrays listing values
+fn patch_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(values) => values.iter().map(patch_value).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+/// Applies patch objects like `{"path": "2:1", "deadline": "+1d", "completed": true}`
+/// to the items they name by `path` or `uuid`, returning how many were applied
+///
+/// Keys are the fields of `td set`, `tags+` and `tags-` adding and removing, and
+/// `completed` sets the status. Nothing is changed unless every patch applies
+pub fn apply_patches(
+    todo_list: &mut TodoList,
+    patches: &Value,
+    dates: &DatesConfig,
+) -> Result<usize> {
+    let patches = patches
+        .as_array()
+        .ok_or_else(|| errors::invalid("Expected an array of patch objects"))?;
+    let mut patched = todo_list.clone();
+    for (index, patch) in patches.iter().enumerate() {
+        let context = || format!("Invalid patch {}", index + 1);
+        let patch = patch
+            .as_object()
+            .ok_or_else(|| errors::invalid("Expected an object"))
+            .with_context(context)?;
+        let path = match (patch.get("path"), patch.get("uuid")) {
+            (Some(Value::String(path)), _) => path.clone(),
+            (None, Some(Value::String(uuid))) => patched
+                .find_by_uuid(uuid)
+                .map(|(path, _)| path)
+                .ok_or_else(|| errors::not_found(format!("No todo item has uuid {}", uuid)))
+                .with_context(context)?,
+            _ => return Err(errors::invalid("A path or uuid is required")).with_context(context),
+        };
+        patched.parse_path(&path).with_context(context)?;
+        for (key, value) in patch {
+            let assignment = match (key.as_str(), value) {
+                ("path" | "uuid", _) => continue,
+                ("completed", Value::Bool(done)) => Assignment {
+                    field: "status".to_string(),
+                    op: Op::Set,
+                    value: if *done { "done" } else { "todo" }.to_string(),
+                },
+                _ => format!("{}={}", key, patch_value(value))
+                    .parse()
+                    .with_context(context)?,
+            };
+            assignment
+                .apply_at(&mut patched, &path, dates)
+                .with_context(context)?;
+        }
+    }
+    *todo_list = patched;
+    Ok(patches.len())
+}
+
+#[cfg(test)]
+mod fields_tests {
+    use super::*;
+
+    #[test]
+    fn assignments() -> Result<()> {
+        let dates = DatesConfig::default();
+        let mut item = TodoItem::new("Report".to_string());
+        item.tags = vec!["later".to_string(), "work".to_string()];
+        for assignment in [
+            "priority=high",
+            "tags+=urgent,work",
+            "tags-=later",
+            "starred=yes",
+        ] {
+            assignment.parse::<Assignment>()?.apply(&mut item, &dates)?;
+        }
+        assert_eq!(item.priority, Some(Priority::High));
+        assert_eq!(item.tags, ["work", "urgent"]);
+        assert!(item.starred);
+
+        "priority="
+            .parse::<Assignment>()?
+            .apply(&mut item, &dates)?;
+        assert_eq!(item.priority, None);
+        assert!("priority+=high"
+            .parse::<Assignment>()?
+            .apply(&mut item, &dates)
+            .is_err());
+        assert!("colour=red"
+            .parse::<Assignment>()?
+            .apply(&mut item, &dates)
+            .is_err());
+        assert!("priority".parse::<Assignment>().is_err());
+
+        // reminders are separated by commas only, a single one may hold spaces
+        "remind=1d 12h, 2h"
+            .parse::<Assignment>()?
+            .apply(&mut item, &dates)?;
+        assert_eq!(item.remind, ["1d 12h", "2h"]);
+
+        assert_eq!(get(&item, "0:1", "path")?.as_deref(), Some("0:1"));
+        assert_eq!(get(&item, "0:1", "tags")?.as_deref(), Some("work urgent"));
+        assert_eq!(get(&item, "0:1", "deadline")?, None);
+        assert!(get(&item, "0:1", "colour").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn patches() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("Report".to_string(), None, None)?;
+        let uuid = list
+            .add_item("Draft".to_string(), None, Some(&"0".to_string()))?
+            .uuid
+            .clone();
+        let patches = serde_json::json!([
+            {"path": "0", "priority": "high", "tags+": ["work", "q3"]},
+            {"uuid": uuid, "completed": true, "deadline": null}
+        ]);
+        assert_eq!(
+            apply_patches(&mut list, &patches, &DatesConfig::default())?,
+            2
+        );
+        assert_eq!(list.items[0].tags, ["work", "q3"]);
+        assert!(list.parse_path(&"0:0".to_string())?.is_completed());
+
+        // a failing patch leaves the list as it was
+        let patches = serde_json::json!([
+            {"path": "0", "priority": "low"},
+            {"path": "7", "priority": "low"}
+        ]);
+        assert!(apply_patches(&mut list, &patches, &DatesConfig::default()).is_err());
+        assert_eq!(list.items[0].priority, Some(Priority::High));
+        Ok(())
+    }
+
+    #[test]
+    fn completing_repeats() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("Water plants".to_string(), None, None)?
+            .repeat = Some("1w".to_string());
+        "status=done".parse::<Assignment>()?.apply_at(
+            &mut list,
+            &"0".to_string(),
+            &DatesConfig::default(),
+        )?;
+        assert_eq!(list.items.len(), 2);
+        assert!(!list.items[1].is_completed());
+        // completing it again brings no second occurrence
+        "status=done".parse::<Assignment>()?.apply_at(
+            &mut list,
+            &"0".to_string(),
+            &DatesConfig::default(),
+        )?;
+        assert_eq!(list.items.len(), 2);
+
+        let patches = serde_json::json!([{"path": "1", "completed": true}]);
+        apply_patches(&mut list, &patches, &DatesConfig::default())?;
+        assert_eq!(list.items.len(), 3);
+        Ok(())
+    }
+}
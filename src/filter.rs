@@ -0,0 +1,69 @@
+use crate::config::DatesConfig;
+use crate::todo_list::TodoItem;
+use crate::utils::parse_deadline;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+
+/// A simple filter expression over todo items
+///
+/// Terms are separated by whitespace and must all match:
+/// - `done` / `pending` - Completion status
+/// - `overdue` - Pending items whose deadline has passed
+/// - `due:<deadline>` - Items due before the given deadline (e.g. `due:today`)
+/// - anything else - Case-insensitive substring of the description
+#[derive(Debug, Default)]
+pub struct Filter {
+    completed: Option<bool>,
+    overdue: bool,
+    due_before: Option<DateTime<Local>>,
+    words: Vec<String>,
+}
+
+impl Filter {
+    /// Parses a filter expression, an empty expression matches every item
+    pub fn parse(expr: &str, dates: &DatesConfig) -> Result<Self> {
+        let mut filter = Filter::default();
+        for term in expr.split_whitespace() {
+            match term.to_lowercase().as_str() {
+                "done" | "completed" => filter.completed = Some(true),
+                "pending" | "todo" => filter.completed = Some(false),
+                "overdue" => filter.overdue = true,
+                lower => {
+                    if let Some(deadline) = lower.strip_prefix("due:") {
+                        filter.due_before =
+                            Some(parse_deadline(Some(deadline.to_string()), dates)?);
+                    } else {
+                        filter.words.push(lower.to_string());
+                    }
+                }
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Returns true if the filter constrains completion status explicitly
+    pub fn has_status(&self) -> bool {
+        self.completed.is_some()
+    }
+
+    /// Checks whether a single item matches all terms of the filter
+    pub fn matches(&self, item: &TodoItem) -> bool {
+        if self
+            .completed
+            .is_some_and(|completed| item.is_completed() != completed)
+        {
+            return false;
+        }
+        if self.overdue && !item.is_overdue(Local::now()) {
+            return false;
+        }
+        if let Some(before) = self.due_before {
+            match item.deadline_time() {
+                Some(deadline) if deadline <= before => {}
+                _ => return false,
+            }
+        }
+        let description = item.description.to_lowercase();
+        self.words.iter().all(|word| description.contains(word))
+    }
+}
@@ -0,0 +1,280 @@
+use crate::todo_list::{TodoItem, TodoList};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    time::Duration,
+};
+
+/// Tag marking the items kept in step with GitHub issues
+pub const TAG: &str = "gh";
+
+/// The parts of a GitHub issue the sync looks at
+#[derive(Debug, Deserialize)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub state: String,
+    pub updated_at: DateTime<Local>,
+    /// Set on pull requests, which the issues API lists too
+    #[serde(default)]
+    pull_request: Option<Value>,
+}
+
+impl Issue {
+    fn is_open(&self) -> bool {
+        self.state == "open"
+    }
+}
+
+/// Which side of an item and its issue is out of date
+#[derive(Debug, PartialEq, Eq)]
+enum Stale {
+    Neither,
+    Item,
+    Issue,
+}
+
+/// Compares an item with its issue, the more recently modified one winning
+fn stale_side(item: &TodoItem, issue: &Issue) -> Stale {
+    if item.description == issue.title && item.is_completed() != issue.is_open() {
+        return Stale::Neither;
+    }
+    match item.modified_time() {
+        Some(modified) if modified > issue.updated_at => Stale::Issue,
+        _ => Stale::Item,
+    }
+}
+
+/// Counts of what a sync changed on either side
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub issues_created: usize,
+    pub issues_updated: usize,
+    pub items_added: usize,
+    pub items_updated: usize,
+}
+
+/// The issues of one repository, reached through the REST API
+///
+/// The token is read from `$GITHUB_TOKEN` or `$GH_TOKEN`, and `$GITHUB_API_URL`
+/// points at a GitHub Enterprise server instead of github.com
+pub struct Client {
+    agent: ureq::Agent,
+    api: String,
+    repo: String,
+    token: String,
+}
+
+impl Client {
+    pub fn new(repo: &str) -> Result<Self> {
+        if repo.split('/').filter(|part| !part.is_empty()).count() != 2 {
+            return Err(anyhow::anyhow!(
+                "Invalid repository {}, expected owner/name",
+                repo
+            ));
+        }
+        let token = env::var("GITHUB_TOKEN")
+            .or_else(|_| env::var("GH_TOKEN"))
+            .context("Set GITHUB_TOKEN to a token allowed to read and write issues")?;
+        let api = env::var("GITHUB_API_URL").unwrap_or("https://api.github.com".to_string());
+        Ok(Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+            api: api.trim_end_matches('/').to_string(),
+            repo: repo.to_string(),
+            token,
+        })
+    }
+
+    /// Sends a request to `path` below the repository, returning the response body
+    fn request(&self, method: &str, path: &str, body: Option<Value>) -> Result<String> {
+        let request = self
+            .agent
+            .request(method, &format!("{}/repos/{}{}", self.api, self.repo, path))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .set("Accept", "application/vnd.github+json")
+            .set("X-GitHub-Api-Version", "2022-11-28");
+        let response = match body {
+            Some(body) => request
+                .set("Content-Type", "application/json")
+                .send_string(&body.to_string()),
+            None => request.call(),
+        };
+        let response = response.map_err(|error| match error {
+            ureq::Error::Status(status, response) => anyhow::anyhow!(
+                "GitHub answered {} to {} {}: {}",
+                status,
+                method,
+                path,
+                response.into_string().unwrap_or_default()
+            ),
+            error => anyhow::anyhow!("Failed to reach GitHub: {}", error),
+        })?;
+        Ok(response.into_string()?)
+    }
+
+    /// Returns every issue of the repository, open and closed, without pull requests
+    pub fn issues(&self) -> Result<Vec<Issue>> {
+        let mut issues = Vec::new();
+        for page in 1.. {
+            let body = self.request(
+                "GET",
+                &format!("/issues?state=all&per_page=100&page={}", page),
+                None,
+            )?;
+            let batch: Vec<Issue> = serde_json::from_str(&body)?;
+            let done = batch.len() < 100;
+            issues.extend(
+                batch
+                    .into_iter()
+                    .filter(|issue| issue.pull_request.is_none()),
+            );
+            if done {
+                break;
+            }
+        }
+        Ok(issues)
+    }
+
+    /// Opens an issue with the given title
+    pub fn create(&self, title: &str) -> Result<Issue> {
+        let body = self.request("POST", "/issues", Some(json!({ "title": title })))?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Sets the title and state of an issue
+    pub fn update(&self, number: u64, title: &str, open: bool) -> Result<()> {
+        let state = if open { "open" } else { "closed" };
+        self.request(
+            "PATCH",
+            &format!("/issues/{}", number),
+            Some(json!({ "title": title, "state": state })),
+        )?;
+        Ok(())
+    }
+}
+
+/// Brings the items tagged `gh` and the issues of the repository in step
+///
+/// Pending tagged items without an issue get one, and open issues no item is
+/// linked to become tagged items. For linked pairs, the title and open or
+/// closed state of whichever side was modified last are copied to the other
+pub fn sync(todo_list: &mut TodoList, client: &Client) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    let issues: HashMap<u64, Issue> = client
+        .issues()?
+        .into_iter()
+        .map(|issue| (issue.number, issue))
+        .collect();
+
+    let mut tagged = Vec::new();
+    let mut linked = HashSet::new();
+    todo_list.walk(&mut |_, item| {
+        linked.extend(item.github_issue);
+        if item.tags.iter().any(|tag| tag == TAG) {
+            tagged.push(item.clone());
+        }
+    });
+
+    let mut updated = HashMap::new();
+    for mut item in tagged {
+        match item.github_issue {
+            None if !item.is_completed() => {
+                let issue = client.create(&item.description)?;
+                item.github_issue = Some(issue.number);
+                report.issues_created += 1;
+            }
+            None => continue,
+            // an issue of another repository, or one that was deleted
+            Some(number) if !issues.contains_key(&number) => continue,
+            Some(number) => {
+                let issue = &issues[&number];
+                match stale_side(&item, issue) {
+                    Stale::Neither => continue,
+                    Stale::Issue => {
+                        client.update(number, &item.description, !item.is_completed())?;
+                        report.issues_updated += 1;
+                        continue;
+                    }
+                    Stale::Item => {
+                        item.description = issue.title.clone();
+                        match (issue.is_open(), item.is_completed()) {
+                            (true, true) => item.reopen(),
+                            (false, false) => item.complete(),
+                            _ => item.touch(),
+                        }
+                        report.items_updated += 1;
+                    }
+                }
+            }
+        }
+        updated.insert(item.uuid.clone(), item);
+    }
+    todo_list.walk_mut(&mut |item| {
+        if let Some(new) = updated.remove(&item.uuid) {
+            item.description = new.description;
+            item.status = new.status;
+            item.completed_at = new.completed_at;
+            item.modified_at = new.modified_at;
+            item.github_issue = new.github_issue;
+        }
+    });
+
+    let mut unlinked: Vec<&Issue> = issues
+        .values()
+        .filter(|issue| issue.is_open() && !linked.contains(&issue.number))
+        .collect();
+    unlinked.sort_by_key(|issue| issue.number);
+    for issue in unlinked {
+        let item = todo_list.add_item(issue.title.clone(), None, None)?;
+        item.tags.push(TAG.to_string());
+        item.github_issue = Some(issue.number);
+        report.items_added += 1;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod github_tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn newer_side_wins() -> Result<()> {
+        let mut list = TodoList::new();
+        let item = list.add_item("Fix login".to_string(), None, None)?.clone();
+        let modified = item.modified_time().unwrap();
+        let issue = |title: &str, state: &str, updated_at| Issue {
+            number: 1,
+            title: title.to_string(),
+            state: state.to_string(),
+            updated_at,
+            pull_request: None,
+        };
+
+        let older = modified - Duration::minutes(5);
+        let newer = modified + Duration::minutes(5);
+        assert_eq!(
+            stale_side(&item, &issue("Fix login", "open", newer)),
+            Stale::Neither
+        );
+        assert_eq!(
+            stale_side(&item, &issue("Fix the login", "open", newer)),
+            Stale::Item
+        );
+        assert_eq!(
+            stale_side(&item, &issue("Fix the login", "open", older)),
+            Stale::Issue
+        );
+        assert_eq!(
+            stale_side(&item, &issue("Fix login", "closed", newer)),
+            Stale::Item
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use crate::errors;
+use crate::todo_list::IdPool;
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A recurring habit, tracked by the days it was done rather than a completed flag
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Habit {
+    pub id: usize,
+    pub description: String,
+    /// Recurrence interval in days, 1 means daily
+    #[serde(default = "default_every")]
+    pub every: u32,
+    /// Days the habit was done, sorted ascending
+    #[serde(default)]
+    pub done: Vec<NaiveDate>,
+}
+
+fn default_every() -> u32 {
+    1
+}
+
+/// The collection of habits stored alongside the root todo list
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+pub struct Habits {
+    #[serde(default)]
+    pub items: Vec<Habit>,
+    #[serde(default)]
+    id_pool: IdPool,
+}
+
+impl Habits {
+    /// Returns true if no habits are tracked
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Creates a new habit recurring every `every` days
+    pub fn add(&mut self, description: String, every: u32) -> &Habit {
+        let id = self.id_pool.acquire_id();
+        self.items.push(Habit {
+            id,
+            description,
+            every: every.max(1),
+            done: Vec::new(),
+        });
+        &self.items[self.items.len() - 1]
+    }
+
+    /// Finds a habit by its ID
+    pub fn get_mut(&mut self, id: usize) -> Result<&mut Habit> {
+        self.items
+            .iter_mut()
+            .find(|habit| habit.id == id)
+            .ok_or_else(|| errors::not_found(format!("Habit with ID {} not found", id)))
+    }
+
+    /// Removes a habit by its ID and releases the ID
+    pub fn remove(&mut self, id: usize) -> Result<Habit> {
+        let index = self
+            .items
+            .iter()
+            .position(|habit| habit.id == id)
+            .ok_or_else(|| errors::not_found(format!("Habit with ID {} not found", id)))?;
+        self.id_pool.release_id(id)?;
+        Ok(self.items.remove(index))
+    }
+}
+
+impl Habit {
+    /// Records the habit as done on `day`, returns false if already recorded
+    pub fn mark_done(&mut self, day: NaiveDate) -> bool {
+        match self.done.binary_search(&day) {
+            Ok(_) => false,
+            Err(index) => {
+                self.done.insert(index, day);
+                true
+            }
+        }
+    }
+
+    /// Splits the done days into streaks, where consecutive entries are at most `every` days apart
+    fn streaks(&self) -> Vec<(NaiveDate, usize)> {
+        let mut streaks: Vec<(NaiveDate, usize)> = Vec::new();
+        for &day in &self.done {
+            match streaks.last_mut() {
+                Some((last, length)) if (day - *last).num_days() <= self.every as i64 => {
+                    *last = day;
+                    *length += 1;
+                }
+                _ => streaks.push((day, 1)),
+            }
+        }
+        streaks
+    }
+
+    /// Returns the current streak as of `today`
+    ///
+    /// The streak is still alive while the next occurrence is not yet overdue
+    pub fn current_streak(&self, today: NaiveDate) -> usize {
+        match self.streaks().last() {
+            Some(&(last, length)) if (today - last).num_days() <= self.every as i64 => length,
+            _ => 0,
+        }
+    }
+
+    /// Returns the longest streak ever recorded
+    pub fn best_streak(&self) -> usize {
+        self.streaks()
+            .iter()
+            .map(|&(_, length)| length)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Displays the habit with its streaks
+    pub fn display(&self) {
+        let today = Local::now().date_naive();
+        let every = match self.every {
+            1 => "every day".to_string(),
+            days => format!("every {} days", days),
+        };
+        let done_today = if self.done.last() == Some(&today) {
+            " | ✓ today"
+        } else {
+            ""
+        };
+        println!(
+            "#{}: {} ({}) | streak {}, best {}{}",
+            self.id,
+            self.description,
+            every,
+            self.current_streak(today),
+            self.best_streak(),
+            done_today
+        );
+    }
+}
+
+#[cfg(test)]
+mod habit_tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, d).unwrap()
+    }
+
+    #[test]
+    fn streaks() {
+        let mut habit = Habit {
+            id: 0,
+            description: "read".to_string(),
+            every: 1,
+            done: Vec::new(),
+        };
+        for d in [1, 2, 3, 5, 6] {
+            habit.mark_done(day(d));
+        }
+        assert!(!habit.mark_done(day(6)));
+        assert_eq!(habit.best_streak(), 3);
+        assert_eq!(habit.current_streak(day(7)), 2);
+        assert_eq!(habit.current_streak(day(8)), 0);
+
+        habit.every = 2;
+        assert_eq!(habit.best_streak(), 5);
+        assert_eq!(habit.current_streak(day(8)), 5);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::config::Config;
+use crate::events::{self, Event};
+use crate::todo_list::{TodoItem, TodoList};
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Executable scripts in `~/.config/td/hooks/` run around changes, Taskwarrior-style
+///
+/// `on-add`, `on-complete` and `on-remove` receive the item as JSON on stdin,
+/// `pre-save` the whole list. A hook vetoes the command by exiting non-zero, and
+/// `on-add`, `on-complete` and `pre-save` may print a modified version to use instead
+pub struct Hooks {
+    dir: PathBuf,
+}
+
+/// Returns true if `path` is a file the hook runner may execute
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+impl Hooks {
+    /// Returns the hooks directory if it exists
+    pub fn load() -> Result<Option<Self>> {
+        let dir = Config::dir()?.join("hooks");
+        Ok(dir.is_dir().then_some(Self { dir }))
+    }
+
+    /// Runs the hook `name` with `input` as JSON on stdin
+    ///
+    /// Returns what the hook printed parsed as the same type, or None if it didn't
+    /// exist or printed nothing
+    fn run<T: Serialize + DeserializeOwned>(
+        &self,
+        name: &str,
+        input: &T,
+        file_path: &Path,
+        path: Option<&str>,
+    ) -> Result<Option<T>> {
+        let script = self.dir.join(name);
+        if !is_executable(&script) {
+            return Ok(None);
+        }
+        let mut child = Command::new(&script)
+            .env("TD_FILE", file_path)
+            .env("TD_HOOK", name)
+            .env("TD_PATH", path.unwrap_or_default())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run hook {}", script.display()))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        serde_json::to_writer(&mut stdin, input)?;
+        stdin.write_all(b"\n")?;
+        drop(stdin);
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("The {} hook rejected the change", name));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() {
+            return Ok(None);
+        }
+        let modified = serde_json::from_str(&stdout)
+            .with_context(|| format!("The {} hook printed invalid JSON", name))?;
+        Ok(Some(modified))
+    }
+
+    /// Runs the item hooks for the changes between the loaded and the current list,
+    /// replacing items with the versions the hooks print
+    pub fn run_item_hooks(
+        &self,
+        loaded: &TodoList,
+        todo_list: &mut TodoList,
+        file_path: &Path,
+    ) -> Result<()> {
+        for event in events::changes(loaded, todo_list) {
+            let name = match event.event {
+                Event::Add => "on-add",
+                Event::Complete => "on-complete",
+                Event::Remove => "on-remove",
+                Event::Overdue => continue,
+            };
+            let modified = self.run(name, &event.item, file_path, Some(&event.path))?;
+            if let (Some(modified), false) = (modified, event.event == Event::Remove) {
+                replace_item(todo_list, modified);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the pre-save hook on the whole list
+    pub fn run_pre_save(&self, todo_list: &mut TodoList, file_path: &Path) -> Result<()> {
+        if let Some(mut modified) = self.run("pre-save", &*todo_list, file_path, None)? {
+            // keep what the file doesn't store, like the version loaded
+            modified.version = todo_list.version;
+            *todo_list = modified;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the item with the same uuid, keeping its ID and sub items
+fn replace_item(todo_list: &mut TodoList, mut modified: TodoItem) {
+    todo_list.walk_mut(&mut |item| {
+        if item.uuid == modified.uuid {
+            modified.id = item.id;
+            modified.sub_list = item.sub_list.take();
+            *item = modified.clone();
+        }
+    });
+}
+
+#[cfg(all(test, unix))]
+pub(crate) mod hooks_tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    /// Returns hooks run from `dir`
+    pub(crate) fn hooks_in(dir: &Path) -> Hooks {
+        Hooks {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Writes an executable hook script into `dir`
+    pub(crate) fn write_hook(dir: &Path, name: &str, script: &str) -> Result<()> {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", script))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        Ok(())
+    }
+
+    #[test]
+    fn pre_save() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        let hooks = hooks_in(dir);
+        let file_path = dir.join("todo.json");
+        let mut list = TodoList::new();
+        list.add_item("a".to_string(), None, None)?;
+
+        write_hook(dir, "pre-save", "exit 1")?;
+        assert!(hooks.run_pre_save(&mut list, &file_path).is_err());
+        assert_eq!(list.items[0].description, "a");
+
+        write_hook(
+            dir,
+            "pre-save",
+            r#"sed 's/"description":"a"/"description":"b"/'"#,
+        )?;
+        hooks.run_pre_save(&mut list, &file_path)?;
+        assert_eq!(list.items[0].description, "b");
+        Ok(())
+    }
+
+    #[test]
+    fn item_hooks() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        let hooks = hooks_in(dir);
+        let file_path = dir.join("todo.json");
+        let loaded = TodoList::new();
+        let mut list = loaded.clone();
+        list.add_item("draft".to_string(), None, None)?;
+
+        write_hook(
+            dir,
+            "on-add",
+            r#"sed 's/"description":"draft"/"description":"final"/'"#,
+        )?;
+        hooks.run_item_hooks(&loaded, &mut list, &file_path)?;
+        assert_eq!(list.items[0].description, "final");
+
+        write_hook(dir, "on-add", "exit 1")?;
+        assert!(hooks
+            .run_item_hooks(&loaded, &mut list, &file_path)
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use crate::exchange::ImportReport;
+use crate::todo_list::TodoList;
+use crate::utils::format_deadline;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use std::{fs, path::Path};
+
+/// A VEVENT or VTODO of a calendar
+#[derive(Debug, Default)]
+struct Component {
+    kind: String,
+    uid: Option<String>,
+    summary: Option<String>,
+    start: Option<DateTime<Local>>,
+    due: Option<DateTime<Local>>,
+    status: Option<String>,
+}
+
+impl Component {
+    /// Returns the deadline of the item for this component, or None if it
+    /// shouldn't be imported
+    ///
+    /// Events are imported if they start in the future, todos unless they are
+    /// completed or cancelled, with their due date or else their start
+    fn deadline(&self, now: DateTime<Local>) -> Option<Option<DateTime<Local>>> {
+        match self.kind.as_str() {
+            "VEVENT" => self.start.filter(|start| *start >= now).map(Some),
+            "VTODO" => match self.status.as_deref() {
+                Some("COMPLETED" | "CANCELLED") => None,
+                _ => Some(self.due.or(self.start)),
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Undoes the escaping of text values
+fn unescape(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n' | 'N') => text.push('\n'),
+                Some(other) => text.push(other),
+                None => {}
+            },
+            (c, false) => text.push(c),
+        }
+    }
+    text
+}
+
+/// Parses a DATE or DATE-TIME value, UTC if it ends in Z and local time
+/// otherwise, a date alone meaning the end of that day
+fn parse_date_time(value: &str) -> Option<DateTime<Local>> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(time.and_utc().with_timezone(&Local));
+    }
+    let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y%m%d")
+                .map(|date| date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap()))
+        })
+        .ok()?;
+    time.and_local_timezone(Local).earliest()
+}
+
+/// Reads the VEVENT and VTODO components of a calendar
+fn parse_components(content: &str) -> Vec<Component> {
+    // long lines are folded onto lines starting with a space or tab
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut components = Vec::new();
+    let mut current: Option<Component> = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // parameters like ;TZID=Europe/Berlin or ;VALUE=DATE are dropped
+        let name = name.split(';').next().unwrap_or_default().to_uppercase();
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value == "VEVENT" || value == "VTODO" => {
+                current = Some(Component {
+                    kind: value.to_string(),
+                    ..Component::default()
+                });
+            }
+            ("END", Some(component)) if value == component.kind => {
+                components.extend(current.take());
+            }
+            ("UID", Some(component)) => component.uid = Some(value.to_string()),
+            ("SUMMARY", Some(component)) => component.summary = Some(unescape(value)),
+            ("DTSTART", Some(component)) => component.start = parse_date_time(value),
+            ("DUE", Some(component)) => component.due = parse_date_time(value),
+            ("STATUS", Some(component)) => component.status = Some(value.to_uppercase()),
+            _ => {}
+        }
+    }
+    components
+}
+
+/// Imports the upcoming events and open todos of a calendar as items with
+/// deadlines, below `under` if given
+///
+/// Items remember the UID of their event, importing it again only updates the
+/// description and deadline. Recurring events count with their first occurrence
+pub fn import(
+    todo_list: &mut TodoList,
+    path: &Path,
+    under: Option<&String>,
+) -> Result<ImportReport> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut report = ImportReport::default();
+    let now = Local::now();
+    for component in parse_components(&content) {
+        let Some(deadline) = component.deadline(now) else {
+            continue;
+        };
+        let description = component
+            .summary
+            .clone()
+            .filter(|summary| !summary.is_empty())
+            .unwrap_or("(no title)".to_string());
+        let deadline = deadline.map(format_deadline);
+
+        let mut updated = None;
+        if let Some(uid) = &component.uid {
+            todo_list.walk_mut(&mut |item| {
+                if item.calendar_uid.as_ref() == Some(uid) {
+                    let changed = item.description != description || item.deadline != deadline;
+                    if changed {
+                        item.description = description.clone();
+                        item.deadline = deadline.clone();
+                        item.touch();
+                    }
+                    updated = Some(changed);
+                }
+            });
+        }
+        match updated {
+            Some(true) => report.updated += 1,
+            Some(false) => {}
+            None => {
+                let item = todo_list.add_item(description, None, under)?;
+                item.deadline = deadline;
+                item.calendar_uid = component.uid;
+                report.added += 1;
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod ics_tests {
+    use super::*;
+
+    #[test]
+    fn calendar() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("calendar.ics");
+        let write = |summary: &str| {
+            fs::write(
+                &path,
+                format!(
+                    "BEGIN:VCALENDAR\r\n\
+                     BEGIN:VEVENT\r\n\
+                     UID:a@example.com\r\n\
+                     SUMMARY:{}\r\n\
+                     DTSTART;TZID=Europe/Berlin:29990501T090000\r\n\
+                     END:VEVENT\r\n\
+                     BEGIN:VEVENT\r\n\
+                     UID:past@example.com\r\n\
+                     SUMMARY:Past\r\n\
+                     DTSTART;VALUE=DATE:20000101\r\n\
+                     END:VEVENT\r\n\
+                     BEGIN:VTODO\r\n\
+                     UID:b@example.com\r\n\
+                     SUMMARY:File taxes\\, finally\r\n\
+                     DUE;VALUE=DATE:29990430\r\n\
+                     END:VTODO\r\n\
+                     BEGIN:VTODO\r\n\
+                     UID:c@example.com\r\n\
+                     SUMMARY:Done\r\n\
+                     STATUS:COMPLETED\r\n\
+                     END:VTODO\r\n\
+                     END:VCALENDAR\r\n",
+                    summary
+                ),
+            )
+        };
+
+        write("Dentist\r\n  appointment")?;
+        let mut list = TodoList::new();
+        list.add_item("Calendar".to_string(), None, None)?;
+        let report = import(&mut list, &path, Some(&"0".to_string()))?;
+        assert_eq!((report.added, report.updated), (2, 0));
+        let items = &list.items[0].sub_list.as_ref().unwrap().items;
+        assert_eq!(items[0].description, "Dentist appointment");
+        assert!(items[0]
+            .deadline
+            .as_ref()
+            .unwrap()
+            .starts_with("2999-05-01T09:00:00"));
+        assert_eq!(items[1].description, "File taxes, finally");
+        assert!(items[1]
+            .deadline
+            .as_ref()
+            .unwrap()
+            .starts_with("2999-04-30T23:59:59"));
+
+        write("Dentist")?;
+        let report = import(&mut list, &path, None)?;
+        assert_eq!((report.added, report.updated), (0, 1));
+        assert_eq!(list.items.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,365 @@
+use crate::config::StorageConfig;
+use crate::todo_list::TodoList;
+use crate::utils::{migrate, read_value, save_todo_list, sibling_path};
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Key marking a patch of an array whose elements are identified by their uuid
+const BY_UUID: &str = "~uuid";
+
+/// One line of the journal, the change a single command made to the list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub at: String,
+    pub command: String,
+    pub patch: Value,
+}
+
+/// Returns the path of the journal kept next to a todo file
+pub fn journal_path(file_path: &Path) -> PathBuf {
+    sibling_path(file_path, ".journal")
+}
+
+/// The entries of a journal that could be read, and where an unreadable tail
+/// begins as its line number and byte offset
+struct Journal {
+    entries: Vec<JournalEntry>,
+    bad_tail: Option<(usize, u64)>,
+}
+
+/// Reads a journal up to its first unreadable line, which an interrupted append
+/// leaves behind
+fn read_entries(path: &Path) -> Result<Journal> {
+    let mut journal = Journal {
+        entries: Vec::new(),
+        bad_tail: None,
+    };
+    if !path.exists() {
+        return Ok(journal);
+    }
+    let content = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut offset = 0;
+    for (number, line) in content.split_inclusive(|byte| *byte == b'\n').enumerate() {
+        if !line.trim_ascii().is_empty() {
+            match serde_json::from_slice(line) {
+                Ok(entry) => journal.entries.push(entry),
+                Err(_) => {
+                    journal.bad_tail = Some((number + 1, offset));
+                    break;
+                }
+            }
+        }
+        offset += line.len() as u64;
+    }
+    Ok(journal)
+}
+
+/// Reads the entries of the journal next to `file_path`, oldest first, warning
+/// about and leaving out an unreadable tail
+pub fn read_journal(file_path: &Path) -> Result<Vec<JournalEntry>> {
+    let path = journal_path(file_path);
+    let journal = read_entries(&path)?;
+    if let Some((line, _)) = journal.bad_tail {
+        eprintln!(
+            "Warning: ignoring {} from line {} on, it can't be read",
+            path.display(),
+            line
+        );
+    }
+    Ok(journal.entries)
+}
+
+/// Applies the journal next to `file_path` to the file's content, returning the number of entries
+///
+/// An unreadable tail is cut off the journal, after keeping a copy of the whole
+/// journal as `<file>.journal.corrupt-<time>`
+pub fn replay(file_path: &Path, value: &mut Value) -> Result<usize> {
+    let path = journal_path(file_path);
+    let journal = read_entries(&path)?;
+    if let Some((line, offset)) = journal.bad_tail {
+        let aside = sibling_path(
+            &path,
+            &format!(".corrupt-{}", Local::now().format("%Y%m%d%H%M%S")),
+        );
+        fs::copy(&path, &aside)?;
+        OpenOptions::new()
+            .write(true)
+            .open(&path)?
+            .set_len(offset)?;
+        eprintln!(
+            "Warning: dropped the unreadable end of {} from line {} on, kept a copy at {}",
+            path.display(),
+            line,
+            aside.display()
+        );
+    }
+    for entry in &journal.entries {
+        apply(value, &entry.patch);
+    }
+    Ok(journal.entries.len())
+}
+
+/// Records the changes made to a list by appending one line to its journal
+///
+/// The file itself is only rewritten when it doesn't exist yet or the journal has
+/// grown to `storage.compact_journal_after` entries
+pub fn append(
+    file_path: &PathBuf,
+    todo_list: &TodoList,
+    storage: &StorageConfig,
+    command: &str,
+) -> Result<()> {
+    if !file_path.exists() {
+        return save_todo_list(file_path, todo_list, storage);
+    }
+    let mut old = read_value(file_path)?;
+    migrate(&mut old)?;
+    let entries = replay(file_path, &mut old)?;
+    let Some(patch) = diff(&old, &serde_json::to_value(todo_list)?) else {
+        return Ok(());
+    };
+    if entries + 1 >= storage.compact_journal_after {
+        return save_todo_list(file_path, todo_list, storage);
+    }
+
+    let entry = JournalEntry {
+        at: Local::now().to_string(),
+        command: command.to_string(),
+        patch,
+    };
+    let mut line = serde_json::to_string(&entry)?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal_path(file_path))?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Prints the commands recorded in the journal since the file was last compacted
+pub fn print_history(file_path: &Path, limit: Option<usize>) -> Result<()> {
+    let entries = read_journal(file_path)?;
+    if entries.is_empty() {
+        println!("The journal is empty");
+        return Ok(());
+    }
+    let skip = limit.map_or(0, |limit| entries.len().saturating_sub(limit));
+    for entry in &entries[skip..] {
+        println!("{}  td {}", entry.at, entry.command);
+    }
+    Ok(())
+}
+
+/// Returns the uuids of an array's elements if every element is an object with a unique uuid
+fn uuids(values: &[Value]) -> Option<Vec<&str>> {
+    let uuids: Vec<&str> = values
+        .iter()
+        .map(|value| value.get("uuid").and_then(Value::as_str))
+        .collect::<Option<_>>()?;
+    let mut unique = uuids.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    (unique.len() == uuids.len()).then_some(uuids)
+}
+
+/// Returns a merge patch turning `old` into `new`, or None if they are equivalent
+///
+/// Objects are patched key by key with null removing a key, arrays of items are
+/// patched by uuid so changing one item doesn't record its siblings
+pub fn diff(old: &Value, new: &Value) -> Option<Value> {
+    if old == new {
+        return None;
+    }
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut patch = Map::new();
+            for (key, new_value) in new {
+                match old.get(key) {
+                    Some(old_value) => {
+                        if let Some(value) = diff(old_value, new_value) {
+                            patch.insert(key.clone(), value);
+                        }
+                    }
+                    None if new_value.is_null() => {}
+                    None => {
+                        patch.insert(key.clone(), new_value.clone());
+                    }
+                }
+            }
+            for key in old.keys().filter(|key| !new.contains_key(*key)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            // keys that are null on one side and missing on the other aren't changes
+            (!patch.is_empty()).then_some(Value::Object(patch))
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            let (Some(old_uuids), Some(new_uuids)) = (uuids(old), uuids(new)) else {
+                return Some(Value::Array(new.clone()));
+            };
+            let old_by_uuid: HashMap<&str, &Value> = old_uuids.iter().copied().zip(old).collect();
+            let mut changes = Map::new();
+            for (uuid, new_value) in new_uuids.iter().zip(new) {
+                let change = match old_by_uuid.get(uuid) {
+                    Some(old_value) => diff(old_value, new_value),
+                    None => Some(new_value.clone()),
+                };
+                if let Some(change) = change {
+                    changes.insert(uuid.to_string(), change);
+                }
+            }
+            if changes.is_empty() && old_uuids == new_uuids {
+                return None;
+            }
+            let mut patch = Map::new();
+            patch.insert("changes".to_string(), Value::Object(changes));
+            if old_uuids != new_uuids {
+                patch.insert("order".to_string(), new_uuids.into());
+            }
+            let mut wrapper = Map::new();
+            wrapper.insert(BY_UUID.to_string(), Value::Object(patch));
+            Some(Value::Object(wrapper))
+        }
+        _ => Some(new.clone()),
+    }
+}
+
+/// Applies a patch produced by `diff` to `target`
+pub fn apply(target: &mut Value, patch: &Value) {
+    let Value::Object(fields) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if let Some(by_uuid) = fields.get(BY_UUID) {
+        apply_by_uuid(target, by_uuid);
+        return;
+    }
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        unreachable!()
+    };
+    for (key, value) in fields {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            apply(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Applies the changes and new order of a uuid-keyed array patch
+fn apply_by_uuid(target: &mut Value, patch: &Value) {
+    let elements = match target.take() {
+        Value::Array(elements) => elements,
+        _ => Vec::new(),
+    };
+    let mut order: Vec<String> = Vec::new();
+    let mut by_uuid: HashMap<String, Value> = HashMap::new();
+    for element in elements {
+        let uuid = element
+            .get("uuid")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        order.push(uuid.clone());
+        by_uuid.insert(uuid, element);
+    }
+    if let Some(Value::Object(changes)) = patch.get("changes") {
+        for (uuid, change) in changes {
+            apply(by_uuid.entry(uuid.clone()).or_insert(Value::Null), change);
+        }
+    }
+    if let Some(Value::Array(new_order)) = patch.get("order") {
+        order = new_order
+            .iter()
+            .filter_map(Value::as_str)
+            .map(String::from)
+            .collect();
+    }
+    *target = Value::Array(
+        order
+            .iter()
+            .filter_map(|uuid| by_uuid.remove(uuid))
+            .collect(),
+    );
+}
+
+#[cfg(test)]
+mod journal_tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn diff_apply_roundtrip() {
+        let old = json!({
+            "items": [
+                {"uuid": "a", "id": 0, "description": "a", "deadline": "2024-01-01"},
+                {"uuid": "b", "id": 1, "description": "b", "tags": ["x"]},
+            ],
+            "id_pool": {"next": 2},
+        });
+        let new = json!({
+            "items": [
+                {"uuid": "b", "id": 1, "description": "b", "tags": ["x", "y"]},
+                {"uuid": "c", "id": 2, "description": "c", "deadline": null},
+                {"uuid": "a", "id": 0, "description": "a", "deadline": null},
+            ],
+            "id_pool": {"next": 3},
+        });
+        let patch = diff(&old, &new).unwrap();
+        assert!(patch["items"][BY_UUID]["changes"].get("a").is_some());
+        assert_eq!(
+            patch["items"][BY_UUID]["changes"]["b"],
+            json!({"tags": ["x", "y"]})
+        );
+
+        let mut value = old.clone();
+        apply(&mut value, &patch);
+        let expected = json!({
+            "items": [
+                {"uuid": "b", "id": 1, "description": "b", "tags": ["x", "y"]},
+                {"uuid": "c", "id": 2, "description": "c"},
+                {"uuid": "a", "id": 0, "description": "a"},
+            ],
+            "id_pool": {"next": 3},
+        });
+        assert_eq!(value, expected);
+        assert_eq!(diff(&new, &new), None);
+    }
+
+    #[test]
+    fn bad_tail() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        let file_path = dir.join("todo.json");
+        fs::write(&file_path, r#"{"items": []}"#)?;
+        let entry = JournalEntry {
+            at: Local::now().to_string(),
+            command: "add a".to_string(),
+            patch: json!({"next": 1}),
+        };
+        let line = format!("{}\n", serde_json::to_string(&entry)?);
+        // an append cut short, and a line written after it that can't be trusted
+        fs::write(
+            journal_path(&file_path),
+            format!("{}{{\"at\": \"2024\n{}", line, line),
+        )?;
+
+        let mut value = json!({});
+        assert_eq!(replay(&file_path, &mut value)?, 1);
+        assert_eq!(value, json!({"next": 1}));
+        assert_eq!(fs::read_to_string(journal_path(&file_path))?, line);
+        assert_eq!(fs::read_dir(dir)?.count(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use crate::todo_list::{TodoItem, TodoList};
+use chrono::{DateTime, Duration, Local};
+
+/// Single-child levels below an item from which `td lint` suggests flattening
+const CHAIN_DEPTH: usize = 3;
+
+/// A smell `td lint` found, with a command that would fix it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Path of the item, empty for the root list
+    pub path: String,
+    pub message: String,
+    pub fix: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path.as_str() {
+            "" => write!(f, "root list: {}", self.message)?,
+            path => write!(f, "#{}: {}", path, self.message)?,
+        }
+        write!(f, "\n    fix: {}", self.fix)
+    }
+}
+
+/// Thresholds of `td lint`
+#[derive(Debug, Clone, Copy)]
+pub struct LintOptions {
+    /// Days a pending item may go without a deadline
+    pub days: i64,
+    /// Pending items a single list may hold before it should be split
+    pub max_items: usize,
+}
+
+/// Returns the number of single-child levels below an item
+fn chain_depth(item: &TodoItem) -> usize {
+    match &item.sub_list {
+        Some(sub_list) if sub_list.items.len() == 1 => 1 + chain_depth(&sub_list.items[0]),
+        _ => 0,
+    }
+}
+
+/// Returns the path of the item at the bottom of a single-child chain
+fn chain_bottom(item: &TodoItem, path: String) -> String {
+    match &item.sub_list {
+        Some(sub_list) if sub_list.items.len() == 1 => {
+            let child = &sub_list.items[0];
+            chain_bottom(child, format!("{}:{}", path, child.id))
+        }
+        _ => path,
+    }
+}
+
+/// Looks through the list for items without deadlines for too long, empty
+/// descriptions, completed parents of pending items, lists too long to scan
+/// and chains of single subtasks
+pub fn lint(todo_list: &TodoList, now: DateTime<Local>, options: LintOptions) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    lint_list(todo_list, "", false, now, options, &mut findings);
+    findings
+}
+
+fn lint_list(
+    list: &TodoList,
+    prefix: &str,
+    only_child: bool,
+    now: DateTime<Local>,
+    options: LintOptions,
+    findings: &mut Vec<Finding>,
+) {
+    let pending = list.todo_len();
+    if pending > options.max_items {
+        findings.push(Finding {
+            path: prefix.to_string(),
+            message: format!("{} pending items in a single list", pending),
+            fix: match prefix {
+                "" => "td add \"<group>\" && td demote <id> --under <group id>".to_string(),
+                prefix => format!(
+                    "td add \"<group>\" -p {0} && td demote {0}:<id> --under <group id>",
+                    prefix
+                ),
+            },
+        });
+    }
+    let undated_before = now - Duration::days(options.days);
+    for item in &list.items {
+        let path = match prefix {
+            "" => item.id.to_string(),
+            prefix => format!("{}:{}", prefix, item.id),
+        };
+        if item.description.trim().is_empty() {
+            findings.push(Finding {
+                path: path.clone(),
+                message: "empty description".to_string(),
+                fix: format!("td edit {} \"<description>\"", path),
+            });
+        }
+        let undated = item.deadline.is_none()
+            && item.is_actionable()
+            && item
+                .created_time()
+                .is_some_and(|time| time < undated_before);
+        if undated {
+            findings.push(Finding {
+                path: path.clone(),
+                message: format!("no deadline for over {} days", options.days),
+                fix: format!("td set {} deadline=<when>", path),
+            });
+        }
+        let Some(sub_list) = &item.sub_list else {
+            continue;
+        };
+        if item.is_completed() && sub_list.count_where(|item| !item.is_completed()) > 0 {
+            findings.push(Finding {
+                path: path.clone(),
+                message: "completed with pending subtasks".to_string(),
+                fix: format!("td set {} status=todo", path),
+            });
+        }
+        // a chain is reported once, at its top
+        let depth = chain_depth(item);
+        if depth >= CHAIN_DEPTH && !only_child {
+            findings.push(Finding {
+                path: path.clone(),
+                message: format!("{} levels of single subtasks", depth),
+                fix: format!("td promote {}", chain_bottom(item, path.clone())),
+            });
+        }
+        let only_child = sub_list.items.len() == 1;
+        lint_list(sub_list, &path, only_child, now, options, findings);
+    }
+}
+
+#[cfg(test)]
+mod lint_tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn smells() -> Result<()> {
+        let now = Local::now();
+        let options = LintOptions {
+            days: 30,
+            max_items: 3,
+        };
+        let mut list = TodoList::new();
+        list.add_item("old".to_string(), None, None)?.created_at =
+            Some((now - Duration::days(40)).to_string());
+        list.add_item(" ".to_string(), Some(now), None)?;
+        list.add_item("a".to_string(), Some(now), None)?;
+        for parent in ["2", "2:0", "2:0:0"] {
+            list.add_item("b".to_string(), Some(now), Some(&parent.to_string()))?;
+        }
+        list.complete_item(&"2".to_string())?;
+        list.parse_path(&"2".to_string())?
+            .sub_list
+            .as_mut()
+            .unwrap()
+            .items[0]
+            .reopen();
+        list.add_item("c".to_string(), Some(now), None)?;
+        list.add_item("d".to_string(), Some(now), None)?;
+
+        let findings = lint(&list, now, options);
+        let found: Vec<(&str, &str)> = findings
+            .iter()
+            .map(|finding| (finding.path.as_str(), finding.fix.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "",
+                    "td add \"<group>\" && td demote <id> --under <group id>"
+                ),
+                ("0", "td set 0 deadline=<when>"),
+                ("1", "td edit 1 \"<description>\""),
+                ("2", "td set 2 status=todo"),
+                ("2", "td promote 2:0:0:0"),
+            ]
+        );
+
+        // groups of a nested list are added inside it
+        let mut list = TodoList::new();
+        list.add_item("trip".to_string(), Some(now), None)?;
+        for step in ["a", "b", "c", "d"] {
+            list.add_item(step.to_string(), Some(now), Some(&"0".to_string()))?;
+        }
+        assert_eq!(
+            lint(&list, now, options)[0].fix,
+            "td add \"<group>\" -p 0 && td demote 0:<id> --under <group id>"
+        );
+        Ok(())
+    }
+}
@@ -1,14 +1,62 @@
+mod apple_reminders;
+mod board;
+mod config;
+mod errors;
+mod events;
+mod exchange;
+mod fields;
+mod filter;
+mod github;
+mod habit;
+mod hooks;
+mod ics;
+mod journal;
+mod lint;
+mod markdown;
+mod mcp;
+mod microsoft_todo;
+mod milestone;
+mod org;
+mod plugin;
+mod recurrence;
+mod remind;
+mod remote;
+mod review;
+#[cfg(feature = "s3")]
+mod s3;
+mod scan;
+mod server;
+mod session;
+mod spreadsheet;
+mod sync;
+mod systemd;
 mod todo_list;
+mod todoist;
+mod trash;
 mod utils;
+mod views;
+mod webhook;
 
-use crate::utils::{expand_path, load_todo_list, parse_deadline, save_todo_list};
-use anyhow::Result;
-use clap::{CommandFactory, Parser, Subcommand};
+use crate::config::{Config, StorageConfig, StorageFormat, WipConfig};
+use crate::errors::ExitCode;
+use crate::filter::Filter;
+use crate::todo_list::{ListOptions, SortKey, TodoList};
+use crate::utils::{
+    attachment_reference, detect_deadline, expand_path, find_local_file, format_path,
+    fuzzy_select_path, is_gzip_path, load_todo_list, open_url, parse_deadline, parse_duration,
+    parse_inline, random_unit, resolve_attachment, resolve_path, save_todo_list, select_paths,
+    sibling_path, similar_descriptions,
+};
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::aot::{Bash, Elvish, Fish, PowerShell, Zsh};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::CompleteEnv;
 use clap_complete::Shell;
 use clap_complete::{generate, Generator};
-use std::io;
-use std::sync::atomic::{AtomicBool, Ordering};
+use dialoguer::Confirm;
+use std::io::{self, IsTerminal};
 
 #[derive(Parser)]
 #[command(name = "td")]
@@ -17,49 +65,611 @@ struct Cli {
     #[command(subcommand)]
     command: Commands,
 
-    #[arg(short, long, default_value = "~/.todo")]
-    file: String,
+    /// Todo file, defaults to the nearest .todo or todo.json in the current
+    /// directory or its parents, else ~/.todo
+    #[arg(short, long, env = "TD_FILE")]
+    file: Option<String>,
+
+    /// Use ~/.todo even in a directory with a list of its own
+    #[arg(short, long)]
+    global: bool,
+
+    /// Use the file of this list from the [lists] table of the config instead
+    #[arg(long = "list", env = "TD_LIST")]
+    list_name: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Add a new todo item
     Add {
-        /// Description of the todo item
+        /// Description of the todo item, may contain inline tokens like
+        /// "!high #tag @context due:tomorrow"
         description: String,
         /// Deadline of todo item
         #[arg(short, long)]
         deadline: Option<String>,
-        /// Sub todo list of parent id
-        #[arg(short, long)]
+        /// Path of the parent item at any depth, like 2:1 or 2.1
+        #[arg(short, long, add = ArgValueCandidates::new(item_candidates))]
         parent_path: Option<String>,
+        /// Detect a deadline phrase like "by tomorrow" in the description
+        #[arg(long)]
+        detect: bool,
+        /// Confirm a detected deadline before adding
+        #[arg(short, long)]
+        interactive: bool,
+        /// Link the todo item to a url, opened by `open`
+        #[arg(short, long)]
+        url: Option<String>,
+        /// Add the item even if a similar pending item exists
+        #[arg(long)]
+        force: bool,
+        /// Bring the item back when completed, after an interval like 1d or at
+        /// the next time of a cron expression like "0 9 * * MON-FRI"
+        #[arg(long)]
+        repeat: Option<String>,
+        /// Remind of the item this long before its deadline, like 30m or 1d,
+        /// may be given more than once
+        #[arg(long, value_name = "DURATION")]
+        remind: Vec<String>,
     },
     /// Edit todo item with id
     Edit {
-        /// Sub todolist of parent id
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
         path: String,
-        /// Description of the todo item
-        description: String,
+        /// New description of the todo item, keeps the current one if omitted
+        description: Option<String>,
         /// Deadline of todo item
         #[arg(short, long)]
         deadline: Option<String>,
+        /// Remove the deadline of the todo item
+        #[arg(long, conflicts_with = "deadline")]
+        no_deadline: bool,
+        /// Link the todo item to a url, opened by `open`
+        #[arg(short, long)]
+        url: Option<String>,
+    },
+    /// Change fields of a todo item, like `priority=high tags+=urgent tags-=later`
+    Set {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// `field=value`, an empty value clearing the field, or `field+=value` and
+        /// `field-=value` to add to or remove from tags and attachments
+        #[arg(required = true)]
+        assignments: Vec<fields::Assignment>,
+    },
+    /// Print one field of a todo item without decoration, failing if it isn't set
+    Get {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Field to print: path, id, uuid, description, deadline, status, priority,
+        /// tags, created, modified, completed or another field `set` changes
+        field: String,
+    },
+    /// Apply an array of patch objects from stdin, all of them or none
+    Apply {
+        /// Read the patches as JSON, like `[{"path": "2:1", "deadline": "+1d", "completed": true}]`
+        #[arg(long, required = true)]
+        json: bool,
+    },
+    /// Append text to the description of a todo item
+    Append {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Text to append
+        text: String,
+    },
+    /// Prepend text to the description of a todo item
+    Prepend {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Text to prepend
+        text: String,
+    },
+    /// Copy a todo item as a new pending item
+    Duplicate {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Path of the parent to add the copy under, defaults to the original's parent
+        #[arg(short, long, add = ArgValueCandidates::new(item_candidates))]
+        to: Option<String>,
+        /// Also copy all subtasks
+        #[arg(short, long)]
+        with_subtasks: bool,
+    },
+    /// Add several subtasks to a todo item at once
+    Split {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Descriptions of the subtasks
+        #[arg(required = true)]
+        steps: Vec<String>,
+    },
+    /// Move a subtask up one level, next to its parent
+    Promote {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+    },
+    /// Nest a todo item under one of its siblings
+    Demote {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// ID of the sibling to nest the item under
+        #[arg(short, long)]
+        under: usize,
+    },
+    /// Choose whether IDs of removed items are reused in a list and its sub lists
+    IdMode {
+        /// Allocation mode
+        #[arg(value_enum)]
+        mode: IdMode,
+        /// Path of the item whose sub list to change, defaults to the whole file
+        #[arg(short, long, add = ArgValueCandidates::new(item_candidates))]
+        path: Option<String>,
+    },
+    /// Reassign sequential IDs to the items of the list
+    Renumber {
+        /// Also renumber the items of every sub list
+        #[arg(short, long)]
+        recursive: bool,
     },
     /// List all todo items
+    ///
+    /// The flags choosing what to list, like --due or --waiting, can't be combined
+    #[command(group(ArgGroup::new("view").args([
+        "all_lists", "due", "habits", "waiting", "someday", "project", "assignee", "mine",
+        "completed",
+    ])))]
     List {
-        #[arg(short, long)]
+        /// Include completed items
+        #[arg(short, long, conflicts_with_all = ["due", "habits", "waiting", "someday", "completed"])]
         all: bool,
+        /// Show at most this many top level items
+        #[arg(short = 'n', long, conflicts_with = "view")]
+        limit: Option<usize>,
+        /// Skip this many top level items before listing
+        #[arg(long, default_value_t = 0, conflicts_with = "view")]
+        offset: usize,
+        /// Order items within each list by this key
+        #[arg(short, long, value_enum, conflicts_with = "view")]
+        sort: Option<SortKey>,
+        /// Reverse the sort order
+        #[arg(short, long, conflicts_with = "view")]
+        reverse: bool,
+        /// Show a completion bar on items with subtasks
+        #[arg(long, conflicts_with = "view")]
+        progress: bool,
+        /// Show only this many levels of subtasks, 0 for the top level alone
+        #[arg(long, conflicts_with = "view")]
+        depth: Option<usize>,
+        /// Show only items matching a filter expression like "overdue" or
+        /// "due:friday report", with the items they belong to
+        #[arg(long, conflicts_with = "view")]
+        filter: Option<String>,
+        /// List habits and their streaks instead of todo items
+        #[arg(long)]
+        habits: bool,
+        /// List only items waiting on someone else
+        #[arg(long)]
+        waiting: bool,
+        /// List only items in the someday bucket
+        #[arg(long)]
+        someday: bool,
+        /// List only items belonging to this project
+        #[arg(short, long)]
+        project: Option<String>,
+        /// List only items assigned to this person
+        #[arg(long)]
+        assignee: Option<String>,
+        /// List only items assigned to you, see `identity` in the config
+        #[arg(long)]
+        mine: bool,
+        /// List only completed items, oldest completion first
+        #[arg(long)]
+        completed: bool,
+        /// With --completed, only items completed on or after this date (YYYY-MM-DD)
+        #[arg(long, requires = "completed")]
+        since: Option<NaiveDate>,
+        /// With --completed, only items completed before this date (YYYY-MM-DD)
+        #[arg(long, requires = "completed")]
+        until: Option<NaiveDate>,
+        /// Show the items of every list in the [lists] table of the config
+        /// together, sorted by deadline
+        #[arg(long)]
+        all_lists: bool,
+        /// List only pending items due by this deadline, like today, friday or +3d,
+        /// overdue ones included
+        #[arg(long)]
+        due: Option<String>,
+    },
+    /// Move a todo item into the someday bucket
+    Someday {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+    },
+    /// Move a todo item out of the someday bucket
+    Revive {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+    },
+    /// Show the most urgent actionable todo item
+    Next {
+        /// Only consider items belonging to this project
+        #[arg(short, long)]
+        project: Option<String>,
+    },
+    /// List projects with their open and total item counts
+    Projects,
+    /// Mark a todo item as waiting on someone else
+    Delegate {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Who the item is waiting on, omit to clear
+        who: Option<String>,
+    },
+    /// Track recurring habits
+    #[command(subcommand)]
+    Habit(HabitCommands),
+    /// Show a day-by-day schedule of items with deadlines
+    Agenda {
+        /// Number of days to show, starting today
+        #[arg(short, long, default_value_t = 7)]
+        days: i64,
+    },
+    /// Summarize completed, added and pending items
+    Report {
+        /// Cover the last 7 days instead of only today
+        #[arg(short, long)]
+        week: bool,
+        /// Output format of the report
+        #[arg(long, value_enum, default_value_t = views::ReportFormat::Text)]
+        format: views::ReportFormat,
+    },
+    /// Print a digest of overdue items, items due today and items completed
+    /// yesterday, meant for cron
+    Remind {
+        /// Send the digest by email with the [email] settings instead
+        #[arg(long)]
+        email: bool,
+        /// Post the digest to the Slack or Discord webhook of the [remind] settings instead
+        #[arg(long, value_enum)]
+        webhook: Option<remind::ChatService>,
+        /// Remind once of each item due within this lead time instead of the
+        /// daily digest, like 15m (the `lead` of the [remind] settings by default)
+        #[arg(long, value_name = "DURATION", num_args = 0..=1)]
+        lead: Option<Option<String>>,
+    },
+    /// Install a systemd timer running `td remind --lead` for this list
+    InstallTimer {
+        /// Install a user unit into ~/.config/systemd/user instead of a system one
+        #[arg(long)]
+        user: bool,
+        /// How often the timer runs, like 15m or 1h
+        #[arg(long, default_value = "15m")]
+        interval: String,
+        /// Send the reminders by email with the [email] settings
+        #[arg(long)]
+        email: bool,
+        /// Post the reminders to the Slack or Discord webhook of the [remind] settings
+        #[arg(long, value_enum)]
+        webhook: Option<remind::ChatService>,
+        /// Print the units instead of installing them
+        #[arg(long)]
+        print: bool,
+    },
+    /// Sync items with an external tracker
+    #[command(subcommand)]
+    Sync(SyncCommands),
+    /// Rewrite the todo file in its format, JSON pretty-printed unless --compact is given
+    Fmt {
+        /// Write compact JSON instead
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Write the todo list to a file in another storage format
+    Convert {
+        /// Format to write
+        #[arg(long, value_enum)]
+        to: StorageFormat,
+        /// Output file, defaults to the todo file with the format's extension
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Add the items of files written by another tool to the list
+    Import {
+        /// Format of the files
+        #[arg(long, value_enum)]
+        format: exchange::Format,
+        /// Files to import
+        #[arg(required = true)]
+        files: Vec<String>,
+        /// Add the items below this item instead of at the top level
+        #[arg(long, add = ArgValueCandidates::new(item_candidates))]
+        under: Option<String>,
+    },
+    /// Import the TODO: and FIXME: comments of a codebase as items
+    Scan {
+        /// Directory to scan, only the files git tracks inside a repository
+        #[arg(default_value = ".")]
+        dir: String,
+        /// Complete imported items whose comment is gone
+        #[arg(long)]
+        complete: bool,
+    },
+    /// Write the list in the format of another tool
+    Export {
+        /// Format to write
+        #[arg(long, value_enum)]
+        format: exchange::Format,
+        /// Output file or directory, defaults to stdout where the format allows
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Only export this item and its sub items
+        #[arg(long, add = ArgValueCandidates::new(item_candidates))]
+        path: Option<String>,
+    },
+    /// Print the JSON Schema of the todo file format
+    Schema,
+    /// Show the commands recorded in the journal (`storage.journal = true`)
+    History {
+        /// Only show the last N commands
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+    },
+    /// Check the todo file for inconsistencies
+    Doctor {
+        /// Repair the problems found
+        #[arg(long)]
+        fix: bool,
+    },
+    /// List pending items not modified for a while, the stalest first
+    Stale {
+        /// Items not modified for this many days are stale
+        #[arg(short, long, default_value_t = 30)]
+        days: i64,
+        /// Tag the stale items #stale, without counting that as a change
+        #[arg(long)]
+        tag: bool,
+    },
+    /// Point out smells of the list, each with a command that fixes it
+    Lint {
+        /// Pending items without a deadline created more than this many days ago are flagged
+        #[arg(short, long, default_value_t = 30)]
+        days: i64,
+        /// Lists with more pending items than this should be split
+        #[arg(long, default_value_t = 20)]
+        max_items: usize,
+    },
+    /// Print completed items grouped by day
+    Log {
+        /// Number of days to look back, including today
+        #[arg(short, long, default_value_t = 7)]
+        days: i64,
+    },
+    /// Plot item counts over time as a sparkline
+    Chart {
+        /// Kind of chart to plot
+        #[arg(value_enum)]
+        kind: views::ChartKind,
+        /// Number of days to plot, ending today
+        #[arg(short, long, default_value_t = 30)]
+        days: i64,
+    },
+    /// Interactively review overdue and stale items
+    Review {
+        /// Items not modified for this many days are considered stale
+        #[arg(short, long, default_value_t = 14)]
+        days: i64,
+    },
+    /// Show pending items in an urgent/important (Eisenhower) matrix
+    Matrix {
+        /// Items due within this duration count as urgent
+        #[arg(short, long, default_value = "2d")]
+        urgent_within: String,
+        /// Print the quadrants as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show a kanban board of todo, in-progress and done items
+    Board,
+    /// Serve the list over http as a JSON API
+    Serve {
+        /// Address to listen on, use 0.0.0.0:7878 to reach it from other machines
+        /// (requires `token` in the `[serve]` config)
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        addr: String,
+        /// Also serve a web interface at /
+        #[arg(long)]
+        web: bool,
+    },
+    /// Run a Model Context Protocol server on stdio for AI assistants
+    Mcp,
+    /// Any other command runs `td-<command>` from PATH with the remaining arguments
+    #[command(external_subcommand)]
+    External(Vec<String>),
+    /// Print the number of matching todo items
+    Count {
+        /// Filter expression, e.g. "overdue", "done", "due:today milk"
+        #[arg(short, long, default_value = "")]
+        filter: String,
     },
     /// Complete a todo item
     Complete {
-        /// Sub todolist of parent id
+        /// Path, path prefix or description substring of the item
+        #[arg(
+            add = ArgValueCandidates::new(item_candidates),
+            required_unless_present = "interactive"
+        )]
+        path: Option<String>,
+        /// Choose several pending items from a checkbox list
+        #[arg(short, long, conflicts_with = "path")]
+        interactive: bool,
+        /// Complete the pending subtasks too
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Show all details of a todo item
+    Show {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
         path: String,
     },
-    /// Remove a todo item
-    Remove {
-        /// Sub todo list of parent id
+    /// Mark a todo item as in progress
+    Start {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Start the item even if the WIP limit is reached
+        #[arg(long)]
+        force: bool,
+    },
+    /// Assign a todo item to someone
+    Assign {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Who the item is assigned to, clears the assignee if omitted
+        who: Option<String>,
+    },
+    /// Add a timestamped comment to a todo item
+    Comment {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Text of the comment
+        text: String,
+    },
+    /// Mark two todo items as related to each other
+    Link {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Path, path prefix or description substring of the related item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        other: String,
+        /// Remove the relation instead
+        #[arg(short, long)]
+        remove: bool,
+    },
+    /// Attach a file or url to a todo item
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+    Attach {
+        #[command(subcommand)]
+        command: Option<AttachCommands>,
+        /// Path, path prefix or description substring of the item
+        #[arg(required = true, add = ArgValueCandidates::new(item_candidates))]
+        path: Option<String>,
+        /// File path or url to attach
+        #[arg(required = true)]
+        target: Option<String>,
+    },
+    /// Open the url of a todo item in the browser
+    Open {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+    },
+    /// Toggle a checklist entry of a todo item, or append a new one
+    Check {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Index of the checklist entry to toggle, as shown by `show`
+        #[arg(required_unless_present = "add")]
+        index: Option<usize>,
+        /// Append an entry with this text instead of toggling
+        #[arg(short, long, conflicts_with = "index")]
+        add: Option<String>,
+    },
+    /// Star a todo item so it is listed first
+    Star {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
         path: String,
     },
+    /// Remove the star from a todo item
+    Unstar {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+    },
+    /// Pick a random pending todo item
+    Pick {
+        /// Filter expression restricting the candidates
+        #[arg(short, long, default_value = "")]
+        filter: String,
+        /// Weight the choice by urgency instead of picking uniformly
+        #[arg(short, long)]
+        weighted: bool,
+        /// Mark the picked item as in progress
+        #[arg(short, long)]
+        start: bool,
+    },
+    /// Print pending items for dmenu/rofi, or act on a chosen line read from stdin
+    ///
+    /// e.g. `td menu | dmenu | td menu --action complete`
+    Menu {
+        /// Read a chosen line from stdin and apply this action to it
+        #[arg(short, long, value_enum)]
+        action: Option<MenuAction>,
+    },
+    /// Fuzzy find an item and print its path, e.g. `td complete $(td pick-id)`
+    PickId,
+    /// Manage removed items
+    #[command(subcommand)]
+    Trash(TrashCommands),
+    /// Manage milestones and their progress
+    #[command(subcommand)]
+    Milestone(MilestoneCommands),
+    /// Manage tags across all todo items
+    #[command(subcommand)]
+    Tag(TagCommands),
+    /// Remove a todo item, moving it to the trash
+    Remove {
+        /// Path, path prefix or description substring of the item
+        #[arg(
+            add = ArgValueCandidates::new(item_candidates),
+            required_unless_present = "interactive"
+        )]
+        path: Option<String>,
+        /// Choose several pending items from a checkbox list
+        #[arg(short, long, conflicts_with = "path")]
+        interactive: bool,
+    },
+    /// Print a one-line summary of due and overdue items
+    Status {
+        /// Output format of the summary
+        #[arg(long, value_enum, default_value_t = StatusFormat::Prompt)]
+        format: StatusFormat,
+        /// Print compact glyph segment, exit with 1 if anything is overdue
+        #[arg(short, long)]
+        short: bool,
+        /// Items due before this deadline count as due
+        #[arg(long, default_value = "today")]
+        due_within: String,
+        /// Glyph shown before the due count in short mode
+        #[arg(long, default_value = "⚑")]
+        due_glyph: String,
+        /// Glyph shown before the overdue count in short mode
+        #[arg(long, default_value = "⚠")]
+        overdue_glyph: String,
+    },
     /// Generate shell completion scripts
+    ///
+    /// For completion of item paths, register dynamic completion instead,
+    /// e.g. `source <(COMPLETE=bash td)`
     Completion {
         /// Shell type to generate completion for
         #[arg(value_enum)]
@@ -67,6 +677,156 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum HabitCommands {
+    /// Add a new habit
+    Add {
+        /// Description of the habit
+        description: String,
+        /// Recurrence interval in days
+        #[arg(short, long, default_value_t = 1)]
+        every: u32,
+    },
+    /// Record a habit as done today
+    Done {
+        /// ID of the habit
+        id: usize,
+    },
+    /// Remove a habit
+    Remove {
+        /// ID of the habit
+        id: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum SyncCommands {
+    /// Keep items tagged #gh in step with the issues of a GitHub repository
+    Github {
+        /// Repository as owner/name
+        #[arg(long)]
+        repo: String,
+    },
+    /// Keep the `- [ ]` tasks of a directory of Markdown notes in step with the list
+    Markdown {
+        /// Directory of notes, searched recursively
+        #[arg(long)]
+        dir: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AttachCommands {
+    /// Open an attachment of a todo item
+    Open {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Index of the attachment, as shown by `show`
+        #[arg(default_value_t = 0)]
+        index: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum MilestoneCommands {
+    /// Create a new milestone
+    Create {
+        /// Unique name of the milestone
+        name: String,
+        /// Optional deadline of the milestone
+        #[arg(short, long)]
+        deadline: Option<String>,
+    },
+    /// Assign a todo item to a milestone
+    Assign {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+        /// Name of the milestone
+        name: String,
+    },
+    /// Remove a todo item from its milestone
+    Unassign {
+        /// Path, path prefix or description substring of the item
+        #[arg(add = ArgValueCandidates::new(item_candidates))]
+        path: String,
+    },
+    /// Show the progress of milestones
+    Status {
+        /// Only show this milestone
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagCommands {
+    /// List tags with the count of items carrying them
+    List,
+    /// Rename a tag on every item
+    Rename {
+        /// Current tag name
+        old: String,
+        /// New tag name, must not be in use yet
+        new: String,
+    },
+    /// Strip a tag from every item
+    Rm {
+        /// Tag to remove
+        tag: String,
+    },
+    /// Merge a tag into another one
+    Merge {
+        /// Tag to merge away
+        from: String,
+        /// Tag to keep
+        into: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashCommands {
+    /// List removed items
+    List,
+    /// Restore a removed item to its original place
+    Restore {
+        /// ID of the trash entry
+        id: usize,
+    },
+    /// Permanently delete removed items
+    Empty {
+        /// Only delete items removed longer ago than this, e.g. "30d"
+        #[arg(long)]
+        older_than: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum IdMode {
+    /// Reuse the IDs of removed items
+    Reuse,
+    /// Always allocate new, increasing IDs
+    Monotonic,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MenuAction {
+    /// Complete the chosen item
+    Complete,
+    /// Mark the chosen item as in progress
+    Start,
+    /// Show the details of the chosen item
+    Show,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StatusFormat {
+    /// Plain text suitable for shell prompts
+    Prompt,
+    /// JSON for waybar/polybar custom modules
+    Waybar,
+}
+
 fn print_completion<G: Generator>(generator: G, cmd: &mut clap::Command) {
     generate(
         generator,
@@ -76,63 +836,1448 @@ fn print_completion<G: Generator>(generator: G, cmd: &mut clap::Command) {
     );
 }
 
-static SHOW_COMPLETE: AtomicBool = AtomicBool::new(false);
+/// Returns the number of items currently in progress
+fn wip_count(todo_list: &TodoList) -> usize {
+    todo_list.count_where(|item| item.is_started() && !item.is_completed())
+}
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let file_path = expand_path(&cli.file)?;
-    let mut todo_list = load_todo_list(&file_path)?;
+/// Checks the WIP limit before starting the item at `path`
+///
+/// Refuses in strict mode unless forced, otherwise only prints a warning
+fn check_wip(todo_list: &mut TodoList, wip: &WipConfig, path: &String, force: bool) -> Result<()> {
+    let Some(limit) = wip.limit else {
+        return Ok(());
+    };
+    let count = wip_count(todo_list);
+    if todo_list.parse_path(path)?.is_started() || count < limit {
+        return Ok(());
+    }
+    if wip.strict && !force {
+        return Err(errors::refused(format!(
+            "WIP limit reached ({}/{}), finish something first or use --force",
+            count, limit
+        )));
+    }
+    eprintln!("Warning: WIP limit exceeded ({}/{})", count + 1, limit);
+    Ok(())
+}
 
-    match cli.command {
+/// Warns about the pending items at or below `path` that are due after an
+/// item they belong to
+fn warn_late_subtasks(todo_list: &TodoList, path: &str) {
+    let below = format!("{}:", path);
+    for (late, ancestor) in todo_list.late_subtasks() {
+        if late == path || late.starts_with(&below) {
+            eprintln!(
+                "Warning: #{} is due after #{} it belongs to",
+                late, ancestor
+            );
+        }
+    }
+}
+
+/// The todo file used without `-f`, `--list` or a local list
+const DEFAULT_FILE: &str = "~/.todo";
+
+/// Returns the todo file to use and the name of its list if it is a named one
+///
+/// A named list wins over a file given, then come the nearest local list of
+/// the current directory other than `~/.todo`, the list `[contexts]` maps the
+/// directory to and `~/.todo`, the last alone with `global`
+fn resolve_file(
+    list_name: Option<&String>,
+    file: Option<&String>,
+    global: bool,
+    config: &Config,
+) -> Result<(String, Option<String>)> {
+    if let Some(name) = list_name {
+        return Ok((config.list_file(name)?, Some(name.clone())));
+    }
+    if let Some(file) = file {
+        return Ok((file.clone(), None));
+    }
+    let dir = std::env::current_dir()?;
+    if !global {
+        let home_file = expand_path(&DEFAULT_FILE.to_string())?;
+        if let Some(local) = find_local_file(&dir)?.filter(|local| *local != home_file) {
+            return Ok((local.display().to_string(), None));
+        }
+        if let Some(name) = config.context_list(&dir)? {
+            return Ok((config.list_file(&name)?, Some(name)));
+        }
+    }
+    Ok((DEFAULT_FILE.to_string(), None))
+}
+
+/// Offers the paths of pending items for dynamic shell completion
+///
+/// The file is taken from `TD_FILE`, falling back to the one td would use here
+fn item_candidates() -> Vec<CompletionCandidate> {
+    let file = std::env::var("TD_FILE").ok();
+    let config = Config::load().unwrap_or_default();
+    let Ok((file, _)) = resolve_file(None, file.as_ref(), false, &config) else {
+        return Vec::new();
+    };
+    let Ok(todo_list) = expand_path(&file).and_then(|path| load_todo_list(&path)) else {
+        return Vec::new();
+    };
+    let mut candidates = Vec::new();
+    todo_list.walk(&mut |path, item| {
+        if !item.is_completed() {
+            candidates.push(
+                CompletionCandidate::new(format_path(path))
+                    .help(Some(item.description.clone().into())),
+            );
+        }
+    });
+    candidates
+}
+
+/// Looks for a pending item similar to a new description and asks whether to bump it
+///
+/// Returns the path of the item to bump, or None to add the new item. Without a
+/// terminal to ask on, a similar item is an error unless `force` is set
+fn similar_to_bump(todo_list: &TodoList, description: &str, force: bool) -> Result<Option<String>> {
+    let mut similar = None;
+    todo_list.walk(&mut |path, item| {
+        if similar.is_none()
+            && !item.is_completed()
+            && similar_descriptions(&item.description, description)
+        {
+            similar = Some((format_path(path), item.description.clone()));
+        }
+    });
+    let Some((path, existing)) = similar.filter(|_| !force) else {
+        return Ok(None);
+    };
+    if !io::stdin().is_terminal() {
+        return Err(errors::refused(format!(
+            "Similar pending item #{} exists: {}, use --force to add anyway",
+            path, existing
+        )));
+    }
+    let bump = Confirm::new()
+        .with_prompt(format!(
+            "Similar pending item #{} exists: {}. Bump it instead?",
+            path, existing
+        ))
+        .default(true)
+        .interact()?;
+    Ok(bump.then_some(path))
+}
+
+/// Completes an item, along with its subtasks if `recursive`, telling about
+/// the next occurrence of a repeating one
+fn complete_item(todo_list: &mut TodoList, path: &String, recursive: bool) -> Result<()> {
+    let (subtasks, next) = if recursive {
+        todo_list.complete_item_recursive(path)?
+    } else {
+        (0, todo_list.complete_repeating(path)?)
+    };
+    let item = todo_list.parse_path(path)?;
+    match subtasks {
+        0 => println!("Completed todo item #{}: {}", path, item.description),
+        count => println!(
+            "Completed todo item #{}: {} and {} subtasks",
+            path, item.description, count
+        ),
+    }
+    if let Some(next) = next {
+        let item = todo_list.parse_path(&next)?;
+        println!(
+            "Repeats as #{}, due {}",
+            next,
+            item.deadline_label().unwrap_or("-".to_string())
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        if errors::has_message(&err) {
+            eprintln!("Error: {:?}", err);
+        }
+        std::process::exit(errors::exit_code(&err) as i32);
+    }
+}
+
+fn run() -> Result<()> {
+    CompleteEnv::with_factory(Cli::command).complete();
+    let command_line = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) => {
+            // --help and --version end up here too and aren't failures
+            let _ = err.print();
+            let code = if err.use_stderr() {
+                ExitCode::Usage as i32
+            } else {
+                0
+            };
+            std::process::exit(code);
+        }
+    };
+    let config = Config::load()?;
+    let (file, active_list) = resolve_file(
+        cli.list_name.as_ref(),
+        cli.file.as_ref(),
+        cli.global,
+        &config,
+    )?;
+    if let Commands::External(args) = &cli.command {
+        // remote urls are passed on as given, td resolves them when the plugin calls it
+        let file = if file.contains("://") {
+            file
+        } else {
+            expand_path(&file)?.display().to_string()
+        };
+        std::process::exit(plugin::run(args, &file)?);
+    }
+    let command = match cli.command {
+        // status runs from shell prompts, so it only reads the file and never
+        // fetches, repairs or writes anything
+        Commands::Status {
+            format,
+            short,
+            due_within,
+            due_glyph,
+            overdue_glyph,
+        } => {
+            let todo_list = session::peek(&file, &config)?;
+            let now = Local::now();
+            let horizon = parse_deadline(Some(due_within), &config.dates)?;
+            let overdue = todo_list.count_where(|item| item.is_overdue(now));
+            let due = todo_list.count_where(|item| {
+                !item.is_completed()
+                    && item
+                        .deadline_time()
+                        .is_some_and(|deadline| deadline >= now && deadline <= horizon)
+            });
+            if short {
+                let segments: Vec<String> = [(due_glyph, due), (overdue_glyph, overdue)]
+                    .into_iter()
+                    .filter(|(_, count)| *count > 0)
+                    .map(|(glyph, count)| format!("{}{}", glyph, count))
+                    .collect();
+                println!("{}", segments.join(" "));
+                std::process::exit(if overdue > 0 { 1 } else { 0 });
+            }
+            let text = format!("{} due, {} overdue", due, overdue);
+            match format {
+                StatusFormat::Prompt => println!("{}", text),
+                StatusFormat::Waybar => {
+                    let class = if overdue > 0 {
+                        "overdue"
+                    } else if due > 0 {
+                        "due"
+                    } else {
+                        "clear"
+                    };
+                    let output = serde_json::json!({
+                        "text": text,
+                        "tooltip": format!("{} pending", todo_list.count_where(|item| !item.is_completed())),
+                        "class": class,
+                    });
+                    println!("{}", output);
+                }
+            }
+            return Ok(());
+        }
+        command => command,
+    };
+    let mut session = session::Session::open(&file, &config)?;
+    let file_path = session.file_path.clone();
+    let mut todo_list = session.load()?;
+
+    match command {
         Commands::Add {
             description,
             deadline,
             parent_path,
+            detect,
+            interactive,
+            url,
+            force,
+            repeat,
+            remind,
         } => {
-            let deadline = parse_deadline(deadline).ok();
-            let item = todo_list.add_item(description, deadline, parent_path.as_ref())?;
-            println!(
-                "Added todo item #{}{}: {}",
-                parent_path.map_or(String::new(), |path| format!("{}:", path)),
-                item.id,
-                item.description
-            );
+            let parent_path = parent_path
+                .map(|path| resolve_path(&mut todo_list, &path))
+                .transpose()?;
+            let recurrence = repeat
+                .as_deref()
+                .map(str::parse::<recurrence::Recurrence>)
+                .transpose()?;
+            for reminder in &remind {
+                remind::parse_reminder(reminder)?;
+            }
+            let mut inline = parse_inline(&description, &config.syntax, &config.dates)?;
+            if let Some(path) = similar_to_bump(&todo_list, &inline.description, force)? {
+                let item = todo_list.bump_item(&path)?;
+                println!("Bumped todo item #{}: {}", path, item.description);
+            } else {
+                // an invalid -d fails rather than silently adding without a deadline
+                let mut deadline = match deadline {
+                    Some(_) => Some(parse_deadline(deadline, &config.dates)?),
+                    None => inline.deadline,
+                };
+                let detected = (deadline.is_none() && (detect || config.add.detect_deadline))
+                    .then(|| detect_deadline(&inline.description, &config.dates))
+                    .flatten();
+                if let Some(detected) = detected {
+                    let accepted = !interactive
+                        || Confirm::new()
+                            .with_prompt(format!(
+                                "Use '{}' as deadline {}?",
+                                detected.phrase,
+                                detected.deadline.format("%Y-%m-%d %H:%M")
+                            ))
+                            .default(true)
+                            .interact()?;
+                    if accepted {
+                        deadline = Some(detected.deadline);
+                        if config.add.strip_deadline_phrase {
+                            inline.description = detected.stripped;
+                        }
+                    }
+                }
+                if let (None, Some(recurrence)) = (deadline, &recurrence) {
+                    deadline = Some(recurrence.after(Local::now())?);
+                }
+                let parent_project = match &parent_path {
+                    Some(path) => todo_list.parse_path(path)?.project.clone(),
+                    None => None,
+                };
+                let item =
+                    todo_list.add_item(inline.description, deadline, parent_path.as_ref())?;
+                item.priority = inline.priority;
+                item.tags = inline.tags;
+                item.context = inline.context;
+                item.project = inline.project.or(parent_project);
+                item.url = url;
+                item.repeat = repeat;
+                item.remind = remind;
+                let path = match &parent_path {
+                    Some(parent) => format!("{}:{}", parent, item.id),
+                    None => item.id.to_string(),
+                };
+                println!("Added todo item #{}: {}", path, item.description);
+                warn_late_subtasks(&todo_list, &path);
+            }
         }
         Commands::Edit {
             path,
             description,
             deadline,
+            no_deadline,
+            url,
         } => {
-            let deadline = parse_deadline(deadline).ok();
+            let path = resolve_path(&mut todo_list, &path)?;
+            let deadline = if no_deadline {
+                Some(None)
+            } else {
+                deadline
+                    .map(|deadline| parse_deadline(Some(deadline), &config.dates))
+                    .transpose()?
+                    .map(Some)
+            };
+            if let Some(url) = url {
+                todo_list.parse_path(&path)?.url = Some(url).filter(|url| !url.is_empty());
+            }
             let item = todo_list.edit_item(&path, description, deadline)?;
             println!(
                 "Edit todo item #{}: {} {}",
                 path,
                 item.description,
-                match deadline {
+                match item.deadline_label() {
                     Some(time) => format!("| deadline: {}", time),
                     None => String::new(),
                 }
             );
+            warn_late_subtasks(&todo_list, &path);
+        }
+        Commands::Append { path, text } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.amend_item(&path, &text, false)?;
+            println!("Edit todo item #{}: {}", path, item.description);
+        }
+        Commands::Prepend { path, text } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.amend_item(&path, &text, true)?;
+            println!("Edit todo item #{}: {}", path, item.description);
         }
-        Commands::List { all } => {
-            SHOW_COMPLETE.store(all, Ordering::SeqCst);
-            let items = todo_list.list_items();
+        Commands::Duplicate {
+            path,
+            to,
+            with_subtasks,
+        } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let to = to.map(|to| resolve_path(&mut todo_list, &to)).transpose()?;
+            let new_path = todo_list.duplicate_item(&path, to.as_ref(), with_subtasks)?;
+            let item = todo_list.parse_path(&new_path)?;
+            println!(
+                "Duplicated todo item #{} as #{}: {}",
+                path, new_path, item.description
+            );
+        }
+        Commands::Split { path, steps } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            for step in steps {
+                let item = todo_list.add_item(step, None, Some(&path))?;
+                println!(
+                    "Added todo item #{}:{}: {}",
+                    path, item.id, item.description
+                );
+            }
+        }
+        Commands::Promote { path } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let (parent, _) = path.rsplit_once(':').ok_or_else(|| {
+                errors::refused(format!("Todo item #{} is already at the top level", path))
+            })?;
+            let grandparent = parent
+                .rsplit_once(':')
+                .map(|(grandparent, _)| grandparent.to_string());
+            let new_path = todo_list.move_item(&path, grandparent.as_ref())?;
+            println!("Promoted todo item #{} to #{}", path, new_path);
+        }
+        Commands::Demote { path, under } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let sibling = match path.rsplit_once(':') {
+                Some((parent, _)) => format!("{}:{}", parent, under),
+                None => under.to_string(),
+            };
+            let new_path = todo_list.move_item(&path, Some(&sibling))?;
+            println!("Demoted todo item #{} to #{}", path, new_path);
+        }
+        Commands::IdMode { mode, path } => {
+            let list = match &path {
+                Some(path) => {
+                    let path = resolve_path(&mut todo_list, path)?;
+                    todo_list
+                        .parse_path(&path)?
+                        .sub_list
+                        .as_mut()
+                        .ok_or_else(|| {
+                            errors::not_found(format!("Todo item #{} has no subitems", path))
+                        })?
+                }
+                None => &mut todo_list,
+            };
+            list.set_monotonic_ids(mode == IdMode::Monotonic);
+            println!(
+                "IDs are now {}",
+                match mode {
+                    IdMode::Reuse => "reused after removal",
+                    IdMode::Monotonic => "never reused",
+                }
+            );
+        }
+        Commands::Renumber { recursive } => {
+            let mapping = todo_list.renumber(recursive);
+            if mapping.is_empty() {
+                println!("IDs are already sequential.");
+            }
+            for (old, new) in mapping {
+                println!("#{} -> #{}", old, new);
+            }
+        }
+        Commands::List {
+            all_lists: true,
+            all,
+            ..
+        } => {
+            if config.lists.is_empty() {
+                return Err(errors::invalid(
+                    "No lists configured, add them to [lists] in the config",
+                ));
+            }
+            let mut lists = Vec::new();
+            for (name, file) in &config.lists {
+                let path = match remote::Remote::parse(file, &config.s3)? {
+                    Some(mut remote) => {
+                        remote.fetch()?;
+                        remote.cache_path().clone()
+                    }
+                    None => expand_path(file)?,
+                };
+                let list = load_todo_list(&path)
+                    .with_context(|| format!("Failed to load list '{}'", name))?;
+                lists.push((name.clone(), list));
+            }
+            if !views::print_all_lists(&lists, all) {
+                return Err(errors::nothing_found());
+            }
+            return Ok(());
+        }
+        Commands::Set { path, assignments } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            for assignment in &assignments {
+                assignment.apply_at(&mut todo_list, &path, &config.dates)?;
+            }
+            let item = todo_list.parse_path(&path)?;
+            let mut fields: Vec<&str> = Vec::new();
+            for assignment in &assignments {
+                if !fields.contains(&assignment.field.as_str()) {
+                    fields.push(&assignment.field);
+                }
+            }
+            println!(
+                "Set {} of todo item #{}: {}",
+                fields.join(", "),
+                path,
+                item.description
+            );
+        }
+        Commands::Get { path, field } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.parse_path(&path)?;
+            match fields::get(item, &path, &field)? {
+                Some(value) => println!("{}", value),
+                None => {
+                    return Err(errors::error(
+                        ExitCode::NothingFound,
+                        format!("Todo item #{} has no {}", path, field.to_lowercase()),
+                    ))
+                }
+            }
+            return Ok(());
+        }
+        Commands::Apply { .. } => {
+            let patches: serde_json::Value = serde_json::from_reader(io::stdin().lock())
+                .context("Failed to read patches from stdin")?;
+            let count = fields::apply_patches(&mut todo_list, &patches, &config.dates)?;
+            println!("Applied {} patches", count);
+        }
+        Commands::List { due: Some(due), .. } => {
+            let by = parse_deadline(Some(due), &config.dates)?;
+            let mut found = false;
+            todo_list.walk(&mut |path, item| {
+                if !item.is_completed() && item.deadline_time().is_some_and(|time| time <= by) {
+                    println!("{}", item.format_info_at(&format_path(path)));
+                    found = true;
+                }
+            });
+            if !found {
+                println!("No todo items due by {}.", by.format("%Y-%m-%d %H:%M"));
+                return Err(errors::nothing_found());
+            }
+            return Ok(());
+        }
+        Commands::List { habits: true, .. } => {
+            if todo_list.habits.is_empty() {
+                println!("No habits found.");
+                return Err(errors::nothing_found());
+            } else {
+                println!("Habits({}):", todo_list.habits.items.len());
+                todo_list.habits.items.iter().for_each(|h| h.display());
+            }
+            return Ok(());
+        }
+        Commands::List { waiting: true, .. } => {
+            let mut found = false;
+            todo_list.walk(&mut |path, item| {
+                if !item.is_completed() && item.waiting_on.is_some() {
+                    println!("{}", item.format_info_at(&format_path(path)));
+                    found = true;
+                }
+            });
+            if !found {
+                println!("No waiting todo items found.");
+                return Err(errors::nothing_found());
+            }
+            return Ok(());
+        }
+        Commands::List { someday: true, .. } => {
+            let mut found = false;
+            todo_list.walk(&mut |path, item| {
+                if !item.is_completed() && item.someday {
+                    println!("{}", item.format_info_at(&format_path(path)));
+                    found = true;
+                }
+            });
+            if !found {
+                println!("No someday todo items found.");
+                return Err(errors::nothing_found());
+            }
+            return Ok(());
+        }
+        Commands::List {
+            project: Some(project),
+            all,
+            ..
+        } => {
+            let mut found = false;
+            todo_list.walk(&mut |path, item| {
+                if (all || !item.is_completed()) && item.project.as_ref() == Some(&project) {
+                    println!("{}", item.format_info_at(&format_path(path)));
+                    found = true;
+                }
+            });
+            if !found {
+                println!("No todo items found in project {}.", project);
+                return Err(errors::nothing_found());
+            }
+            return Ok(());
+        }
+        Commands::List {
+            assignee,
+            mine,
+            all,
+            ..
+        } if assignee.is_some() || mine => {
+            let who = match assignee {
+                Some(who) => who,
+                None => config.identity().ok_or_else(|| {
+                    errors::invalid("No identity configured, set `identity` in the config")
+                })?,
+            };
+            let mut found = false;
+            todo_list.walk(&mut |path, item| {
+                if (all || !item.is_completed()) && item.assignee.as_ref() == Some(&who) {
+                    println!("{}", item.format_info_at(&format_path(path)));
+                    found = true;
+                }
+            });
+            if !found {
+                println!("No todo items assigned to {}.", who);
+                return Err(errors::nothing_found());
+            }
+            return Ok(());
+        }
+        Commands::Projects => {
+            views::print_projects(&todo_list);
+            return Ok(());
+        }
+        Commands::List {
+            completed: true,
+            since,
+            until,
+            ..
+        } => {
+            let mut done = Vec::new();
+            todo_list.walk(&mut |path, item| {
+                let Some(time) = item.completed_time().filter(|_| item.is_completed()) else {
+                    return;
+                };
+                let day = time.date_naive();
+                if since.is_none_or(|since| day >= since) && until.is_none_or(|until| day < until) {
+                    done.push((time, format_path(path), item));
+                }
+            });
+            if done.is_empty() {
+                println!("No completed todo items found.");
+                return Err(errors::nothing_found());
+            } else {
+                done.sort_by_key(|(time, ..)| *time);
+                println!("Completed({}):", done.len());
+                for (time, path, item) in done {
+                    println!(
+                        "{} {}",
+                        time.format("%Y-%m-%d %H:%M"),
+                        item.format_info_at(&path)
+                    );
+                }
+            }
+            return Ok(());
+        }
+        Commands::List {
+            all,
+            limit,
+            offset,
+            sort,
+            reverse,
+            progress,
+            depth,
+            filter,
+            ..
+        } => {
+            let options = ListOptions {
+                include_completed: all,
+                filter: filter
+                    .as_deref()
+                    .map(|filter| Filter::parse(filter, &config.dates))
+                    .transpose()?,
+                sort,
+                reverse,
+                depth,
+                progress,
+                inherit_deadlines: config.dates.inherit_deadlines,
+            };
+            let items = todo_list.list_items(&options);
             if items.is_empty() {
                 println!("No todo items found.");
+                return Err(errors::nothing_found());
+            } else {
+                if let Some(name) = &active_list {
+                    print!("[{}] ", name);
+                }
+                match config.wip.limit {
+                    Some(limit) => println!(
+                        "Todo List({}) | WIP {}/{}:",
+                        todo_list.pending_count(),
+                        wip_count(&todo_list),
+                        limit
+                    ),
+                    None => println!("Todo List({}):", todo_list.pending_count()),
+                }
+                items
+                    .iter()
+                    .skip(offset)
+                    .take(limit.unwrap_or(usize::MAX))
+                    .for_each(|i| i.display(0, None, &options));
+            }
+        }
+        Commands::Next { project } => {
+            let now = Local::now();
+            let mut next: Option<(String, &todo_list::TodoItem, f64)> = None;
+            todo_list.walk(&mut |path, item| {
+                let urgency = item.urgency(now, todo_list.effective_priority(path));
+                let more_urgent = next.as_ref().is_none_or(|(.., best)| urgency > *best);
+                let in_project = project.is_none() || item.project == project;
+                if item.is_actionable() && in_project && more_urgent {
+                    next = Some((format_path(path), item, urgency));
+                }
+            });
+            match next {
+                Some((path, item, _)) => println!("{}", item.format_info_at(&path)),
+                None => {
+                    println!("No actionable todo items found.");
+                    return Err(errors::nothing_found());
+                }
+            }
+            return Ok(());
+        }
+        Commands::Someday { path } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.someday_item(&path, true)?;
+            println!("Moved todo item #{} to someday: {}", path, item.description);
+        }
+        Commands::Revive { path } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.someday_item(&path, false)?;
+            println!("Revived todo item #{}: {}", path, item.description);
+        }
+        Commands::Delegate { path, who } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.delegate_item(&path, who)?;
+            match &item.waiting_on {
+                Some(who) => println!(
+                    "Delegated todo item #{} to {}: {}",
+                    path, who, item.description
+                ),
+                None => println!(
+                    "Cleared waiting status of todo item #{}: {}",
+                    path, item.description
+                ),
+            }
+        }
+        Commands::Agenda { days } => {
+            views::print_agenda(&todo_list, days);
+            return Ok(());
+        }
+        Commands::Report { week, format } => {
+            views::print_report(&todo_list, if week { 7 } else { 1 }, format);
+            return Ok(());
+        }
+        Commands::Remind {
+            email,
+            webhook,
+            lead,
+        } => {
+            webhook::check_overdue(&config.webhooks, &file_path, &todo_list)?;
+            let quiet_hours = config
+                .remind
+                .quiet_hours
+                .as_deref()
+                .map(str::parse::<remind::QuietHours>)
+                .transpose()?;
+            let now = Local::now();
+            // nothing is delivered, and with --lead the state is left alone so
+            // the next run after the quiet hours catches up on what came due
+            if quiet_hours.is_some_and(|quiet| quiet.contains(now.time())) {
+                return Ok(());
+            }
+            let mut fired = None;
+            let digest = match lead {
+                Some(lead) => {
+                    let lead = lead
+                        .or_else(|| config.remind.lead.clone())
+                        .unwrap_or_else(|| "15m".to_string());
+                    let lead = parse_duration(&lead)
+                        .ok_or_else(|| errors::invalid(format!("Invalid lead time: {}", lead)))?;
+                    let throttle = match &config.remind.throttle {
+                        Some(throttle) => parse_duration(throttle).ok_or_else(|| {
+                            errors::invalid(format!("Invalid throttle: {}", throttle))
+                        })?,
+                        None => chrono::Duration::zero(),
+                    };
+                    let state_path = sibling_path(&file_path, ".reminders");
+                    let mut state = remind::ReminderState::load(&state_path);
+                    let digest = state.due_soon(&todo_list, now, lead, throttle);
+                    fired = Some((state, state_path));
+                    digest
+                }
+                None => remind::Digest::build(&todo_list, now),
+            };
+            if !digest.is_empty() {
+                if email {
+                    remind::send_email(&digest, &config.email)?;
+                }
+                if let Some(service) = webhook {
+                    remind::send_chat(&digest, service, &config.remind)?;
+                }
+                if !email && webhook.is_none() {
+                    print!("{}", digest.to_text());
+                }
+            }
+            // recorded only once delivered, so a failed send is retried next run
+            if let Some((state, state_path)) = fired {
+                state.save(&state_path)?;
+            }
+            return Ok(());
+        }
+        Commands::InstallTimer {
+            user,
+            interval,
+            email,
+            webhook,
+            print,
+        } => {
+            let interval = parse_duration(&interval)
+                .filter(|interval| *interval > chrono::Duration::zero())
+                .ok_or_else(|| errors::invalid(format!("Invalid interval: {}", interval)))?;
+            let exe = std::env::current_exe().context("Failed to find the td executable")?;
+            let file = if file.contains("://") {
+                file
+            } else {
+                file_path.display().to_string()
+            };
+            let mut command = vec![
+                exe.display().to_string(),
+                "-f".to_string(),
+                file,
+                "remind".to_string(),
+                "--lead".to_string(),
+            ];
+            // a lead shorter than the interval would remind of some items late,
+            // rounded up to whole minutes as leads don't take seconds
+            let lead = config.remind.lead.as_deref().unwrap_or("15m");
+            if parse_duration(lead).is_some_and(|lead| lead < interval) {
+                let minutes = (interval.num_seconds() as u64).div_ceil(60);
+                command.push(format!("{}m", minutes));
+            }
+            if email {
+                command.push("--email".to_string());
+            }
+            if let Some(service) = webhook {
+                command.push("--webhook".to_string());
+                if let Some(value) = service.to_possible_value() {
+                    command.push(value.get_name().to_string());
+                }
+            }
+            let run_as = std::env::var("USER").ok().filter(|_| !user);
+            let (service, timer) =
+                systemd::units(&command, interval.num_seconds(), run_as.as_deref());
+            if print {
+                print!(
+                    "# {}.service\n{}\n# {}.timer\n{}",
+                    systemd::UNIT_NAME,
+                    service,
+                    systemd::UNIT_NAME,
+                    timer
+                );
+                return Ok(());
+            }
+            let timer_path = systemd::write_units(&systemd::unit_dir(user)?, &service, &timer)?;
+            println!("Wrote {} and its service", timer_path.display());
+            if let Err(err) = systemd::enable(user) {
+                eprintln!("Warning: {:#}, enable the timer with:", err);
+                for command in systemd::enable_commands(user) {
+                    eprintln!("  {}", command.join(" "));
+                }
+            } else {
+                println!("Enabled {}.timer", systemd::UNIT_NAME);
+            }
+            return Ok(());
+        }
+        Commands::Sync(command) => match command {
+            SyncCommands::Github { repo } => {
+                let client = github::Client::new(&repo)?;
+                let report = github::sync(&mut todo_list, &client)?;
+                println!(
+                    "Synced with {}: {} issues created, {} issues updated, {} items added, {} items updated",
+                    repo,
+                    report.issues_created,
+                    report.issues_updated,
+                    report.items_added,
+                    report.items_updated
+                );
+            }
+            SyncCommands::Markdown { dir } => {
+                let dir = expand_path(&dir)?;
+                let report = markdown::sync(&mut todo_list, &dir)?;
+                println!(
+                    "Synced with {}: {} items added, {} items updated, {} tasks updated in {} files",
+                    dir.display(),
+                    report.items_added,
+                    report.items_updated,
+                    report.tasks_updated,
+                    report.files_written
+                );
+            }
+        },
+        Commands::Fmt { compact } => {
+            let storage = StorageConfig {
+                compact,
+                ..config.storage
+            };
+            save_todo_list(&file_path, &todo_list, &storage)?;
+            if let Some(remote) = &session.remote {
+                remote.push()?;
+            }
+            let format = match storage.format_for(&file_path) {
+                StorageFormat::Msgpack => "MessagePack",
+                StorageFormat::Json if compact => "compact JSON",
+                StorageFormat::Json => "pretty JSON",
+            };
+            println!(
+                "Rewrote {} as {}{}",
+                file_path.display(),
+                format,
+                if is_gzip_path(&file_path) {
+                    ", gzip-compressed"
+                } else {
+                    ""
+                }
+            );
+            return Ok(());
+        }
+        Commands::Import {
+            format,
+            files,
+            under,
+        } => {
+            let files = files.iter().map(expand_path).collect::<Result<Vec<_>>>()?;
+            let under = under
+                .map(|under| resolve_path(&mut todo_list, &under))
+                .transpose()?;
+            let report = exchange::import(
+                format,
+                &mut todo_list,
+                &files,
+                under.as_ref(),
+                &config.dates,
+            )?;
+            println!(
+                "Imported {} items, updated {}",
+                report.added, report.updated
+            );
+        }
+        Commands::Scan { dir, complete } => {
+            let report = scan::scan(&mut todo_list, &expand_path(&dir)?, complete)?;
+            println!(
+                "Scanned {}: {} comments added, {} moved, {} completed",
+                dir, report.added, report.moved, report.completed
+            );
+        }
+        Commands::Export {
+            format,
+            output,
+            path,
+        } => {
+            let output = output.map(|output| expand_path(&output)).transpose()?;
+            match path {
+                Some(path) => {
+                    let path = resolve_path(&mut todo_list, &path)?;
+                    let mut subtree = TodoList::new();
+                    subtree.push_item(todo_list.parse_path(&path)?.clone());
+                    exchange::export(format, &subtree, output.as_deref())?;
+                }
+                None => exchange::export(format, &todo_list, output.as_deref())?,
+            }
+            return Ok(());
+        }
+        Commands::Convert { to, output } => {
+            let output = match output {
+                Some(output) => expand_path(&output)?,
+                None => file_path.with_extension(match to {
+                    StorageFormat::Json => "json",
+                    StorageFormat::Msgpack => "msgpack",
+                }),
+            };
+            let storage = StorageConfig {
+                format: to,
+                ..config.storage
+            };
+            if storage.format_for(&output) != to {
+                return Err(errors::invalid(format!(
+                    "The extension of {} names another format",
+                    output.display()
+                )));
+            }
+            save_todo_list(&output, &todo_list, &storage)?;
+            println!("Wrote {}", output.display());
+            return Ok(());
+        }
+        Commands::Schema => {
+            let schema = schemars::schema_for!(TodoList);
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            return Ok(());
+        }
+        Commands::History { limit } => {
+            journal::print_history(&file_path, limit)?;
+            return Ok(());
+        }
+        Commands::Doctor { fix } => {
+            let problems = todo_list.diagnose(fix);
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            for (late, ancestor) in todo_list.late_subtasks() {
+                println!(
+                    "Warning: #{} is due after #{} it belongs to",
+                    late, ancestor
+                );
+            }
+            match (problems.len(), fix) {
+                (0, _) => println!("No problems found."),
+                (count, true) => println!("Fixed {} problems", count),
+                (count, false) => {
+                    println!("Found {} problems, run `td doctor --fix` to repair", count);
+                    return Ok(());
+                }
+            }
+        }
+        Commands::Stale { days, tag } => {
+            let now = Local::now();
+            let before = now - chrono::Duration::days(days);
+            let mut stale = Vec::new();
+            todo_list.walk(&mut |path, item| {
+                let modified = item.modified_time();
+                if !item.is_completed()
+                    && !item.someday
+                    && modified.is_none_or(|time| time < before)
+                {
+                    stale.push((modified, format_path(path)));
+                }
+            });
+            if stale.is_empty() {
+                println!("No stale todo items found.");
+                return Err(errors::nothing_found());
+            }
+            // never modified sorts first
+            stale.sort();
+            println!("Stale({}):", stale.len());
+            for (modified, path) in &stale {
+                let age = match modified {
+                    Some(time) => format!("{}d", (now - *time).num_days()),
+                    None => "?".to_string(),
+                };
+                let item = todo_list.parse_path(path)?;
+                println!("{:>5} {}", age, item.format_info_at(path));
+                // tagged without touching, so they stay stale until really changed
+                if tag && !item.tags.iter().any(|existing| existing == "stale") {
+                    item.tags.push("stale".to_string());
+                }
+            }
+            if !tag {
+                return Ok(());
+            }
+        }
+        Commands::Lint { days, max_items } => {
+            let options = lint::LintOptions { days, max_items };
+            let findings = lint::lint(&todo_list, Local::now(), options);
+            for finding in &findings {
+                println!("{}", finding);
+            }
+            if findings.is_empty() {
+                println!("No problems found.");
+            }
+            return Ok(());
+        }
+        Commands::Log { days } => {
+            views::print_log(&todo_list, days);
+            return Ok(());
+        }
+        Commands::Chart { kind, days } => {
+            views::print_chart(&todo_list, kind, days);
+            return Ok(());
+        }
+        Commands::Review { days } => review::run_review(&mut todo_list, days, &config.dates)?,
+        Commands::Matrix {
+            urgent_within,
+            json,
+        } => {
+            let urgent_within = parse_duration(&urgent_within)
+                .ok_or_else(|| errors::invalid(format!("Invalid duration: {}", urgent_within)))?;
+            views::print_matrix(&todo_list, urgent_within, json);
+            return Ok(());
+        }
+        Commands::Board => board::run_board(&mut todo_list)?,
+        Commands::Serve { addr, web } => {
+            server::serve(&file, &config, &addr, web)?;
+            return Ok(());
+        }
+        Commands::Mcp => {
+            mcp::serve(&file, &config)?;
+            return Ok(());
+        }
+        Commands::Habit(command) => match command {
+            HabitCommands::Add { description, every } => {
+                let habit = todo_list.habits.add(description, every);
+                println!("Added habit #{}: {}", habit.id, habit.description);
+            }
+            HabitCommands::Done { id } => {
+                let today = Local::now().date_naive();
+                let habit = todo_list.habits.get_mut(id)?;
+                if !habit.mark_done(today) {
+                    println!("Habit #{} already done today", id);
+                    return Ok(());
+                }
+                println!(
+                    "Done habit #{}: {} | streak {}",
+                    id,
+                    habit.description,
+                    habit.current_streak(today)
+                );
+            }
+            HabitCommands::Remove { id } => {
+                let habit = todo_list.habits.remove(id)?;
+                println!("Removed habit #{}: {}", id, habit.description);
+            }
+        },
+        Commands::Count { filter } => {
+            let filter = Filter::parse(&filter, &config.dates)?;
+            let count = todo_list.count_where(|item| {
+                (filter.has_status() || !item.is_completed()) && filter.matches(item)
+            });
+            println!("{}", count);
+        }
+        Commands::Complete {
+            path,
+            interactive,
+            recursive,
+        } => {
+            let paths = match path {
+                Some(path) if !interactive => vec![resolve_path(&mut todo_list, &path)?],
+                _ => select_paths(&todo_list, "Complete which items?")?,
+            };
+            for path in paths {
+                complete_item(&mut todo_list, &path, recursive)?;
+            }
+        }
+        Commands::Show { path } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.parse_path(&path)?.clone();
+            item.show(&path);
+            if !item.related.is_empty() {
+                println!("  related:");
+            }
+            for uuid in &item.related {
+                match todo_list.find_by_uuid(uuid) {
+                    Some((path, related)) => println!("    #{}: {}", path, related.description),
+                    None => println!("    {} (removed)", uuid),
+                }
+            }
+            return Ok(());
+        }
+        Commands::Start { path, force } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            check_wip(&mut todo_list, &config.wip, &path, force)?;
+            let item = todo_list.start_item(&path)?;
+            println!("Started todo item #{}: {}", path, item.description);
+        }
+        Commands::Assign { path, who } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.assign_item(&path, who)?;
+            match &item.assignee {
+                Some(who) => println!(
+                    "Assigned todo item #{} to {}: {}",
+                    path, who, item.description
+                ),
+                None => println!("Unassigned todo item #{}: {}", path, item.description),
+            }
+        }
+        Commands::Comment { path, text } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.comment_item(&path, text)?;
+            println!("Commented on todo item #{}: {}", path, item.description);
+        }
+        Commands::Link {
+            path,
+            other,
+            remove,
+        } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let other = resolve_path(&mut todo_list, &other)?;
+            todo_list.link_items(&path, &other, !remove)?;
+            println!(
+                "{} todo items #{} and #{}",
+                if remove { "Unlinked" } else { "Linked" },
+                path,
+                other
+            );
+        }
+        Commands::Attach {
+            command: Some(AttachCommands::Open { path, index }),
+            ..
+        } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.parse_path(&path)?;
+            let reference = item.attachments.get(index).ok_or_else(|| {
+                errors::not_found(format!("Todo item #{} has no attachment {}", path, index))
+            })?;
+            let target = resolve_attachment(reference, config.attachment_dir.as_ref())?;
+            open_url(&target)?;
+            println!("Opened {}", target);
+            return Ok(());
+        }
+        Commands::Attach { path, target, .. } => {
+            let (Some(path), Some(target)) = (path, target) else {
+                unreachable!("clap requires both arguments without a subcommand")
+            };
+            let path = resolve_path(&mut todo_list, &path)?;
+            let reference = attachment_reference(&target, config.attachment_dir.as_ref())?;
+            let item = todo_list.parse_path(&path)?;
+            item.attachments.push(reference);
+            item.touch();
+            println!(
+                "Attached {} to todo item #{}: {}",
+                item.attachments[item.attachments.len() - 1],
+                path,
+                item.description
+            );
+        }
+        Commands::Open { path } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.parse_path(&path)?;
+            let url = item
+                .link()
+                .ok_or_else(|| errors::not_found(format!("Todo item #{} has no url", path)))?;
+            open_url(url)?;
+            println!("Opened {}", url);
+            return Ok(());
+        }
+        Commands::Check { path, index, add } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            if let Some(text) = add {
+                let item = todo_list.parse_path(&path)?;
+                item.checklist
+                    .push(todo_list::ChecklistEntry { text, done: false });
+                item.touch();
+                println!(
+                    "Added checklist entry {} to todo item #{}: {}",
+                    item.checklist.len() - 1,
+                    path,
+                    item.description
+                );
+            } else if let Some(index) = index {
+                let entry = todo_list.toggle_check(&path, index)?;
+                let mark = if entry.done { "x" } else { " " };
+                println!("#{} {} [{}] {}", path, index, mark, entry.text);
+            }
+        }
+        Commands::Star { path } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.star_item(&path, true)?;
+            println!("Starred todo item #{}: {}", path, item.description);
+        }
+        Commands::Unstar { path } => {
+            let path = resolve_path(&mut todo_list, &path)?;
+            let item = todo_list.star_item(&path, false)?;
+            println!("Unstarred todo item #{}: {}", path, item.description);
+        }
+        Commands::Pick {
+            filter,
+            weighted,
+            start,
+        } => {
+            let filter = Filter::parse(&filter, &config.dates)?;
+            let now = Local::now();
+            let mut candidates = Vec::new();
+            todo_list.walk(&mut |path, item| {
+                if !item.is_completed() && filter.matches(item) {
+                    let weight = if weighted {
+                        item.urgency(now, todo_list.effective_priority(path))
+                    } else {
+                        1.0
+                    };
+                    candidates.push((format_path(path), weight));
+                }
+            });
+            let total: f64 = candidates.iter().map(|(_, weight)| weight).sum();
+            let mut target = random_unit() * total;
+            let path = candidates
+                .into_iter()
+                .find(|(_, weight)| {
+                    target -= weight;
+                    target < 0.0
+                })
+                .map(|(path, _)| path)
+                .ok_or_else(|| {
+                    errors::error(ExitCode::NothingFound, "No pending todo items to pick from")
+                })?;
+            if start {
+                check_wip(&mut todo_list, &config.wip, &path, false)?;
+                let item = todo_list.start_item(&path)?;
+                println!("Started todo item #{}: {}", path, item.description);
             } else {
-                println!("Todo List({}):", todo_list.todo_len());
-                items.iter().for_each(|i| i.display(0));
+                let item = todo_list.parse_path(&path)?;
+                println!("Picked todo item #{}: {}", path, item.description);
+                return Ok(());
             }
         }
-        Commands::Complete { path } => {
-            let item = todo_list.complete_item(&path)?;
-            println!("Completed todo item #{}: {}", path, item.description);
+        Commands::Menu { action: None } => {
+            todo_list.walk(&mut |path, item| {
+                if item.is_actionable() {
+                    println!("#{}: {}", format_path(path), item.description);
+                }
+            });
+            return Ok(());
         }
-        Commands::Remove { path } => {
-            let item = todo_list.remove_item(&path)?;
-            println!("Removed todo item #{}: {}", path, item.description);
+        Commands::Menu {
+            action: Some(action),
+        } => {
+            let mut line = String::new();
+            io::stdin().read_line(&mut line)?;
+            let path = line
+                .trim()
+                .strip_prefix('#')
+                .and_then(|line| line.split_once(": ").map(|(path, _)| path));
+            let path = match path {
+                Some(path) => path.to_string(),
+                None => resolve_path(&mut todo_list, line.trim())?,
+            };
+            match action {
+                MenuAction::Complete => {
+                    complete_item(&mut todo_list, &path, false)?;
+                }
+                MenuAction::Start => {
+                    check_wip(&mut todo_list, &config.wip, &path, false)?;
+                    let item = todo_list.start_item(&path)?;
+                    println!("Started todo item #{}: {}", path, item.description);
+                }
+                MenuAction::Show => {
+                    todo_list.parse_path(&path)?.show(&path);
+                    return Ok(());
+                }
+            }
+        }
+        Commands::PickId => {
+            println!("{}", fuzzy_select_path(&todo_list)?);
+            return Ok(());
         }
+        Commands::Remove { path, interactive } => {
+            let mut paths = match path {
+                Some(path) if !interactive => vec![resolve_path(&mut todo_list, &path)?],
+                _ => select_paths(&todo_list, "Remove which items?")?,
+            };
+            // remove subitems before their parents so every path stays valid
+            paths.sort_by_key(|path| std::cmp::Reverse(path.matches(':').count()));
+            for path in paths {
+                let entry = todo_list.trash_item(&path)?;
+                println!(
+                    "Removed todo item #{}: {} (trash #{})",
+                    path, entry.item.description, entry.id
+                );
+            }
+        }
+        Commands::Trash(command) => match command {
+            TrashCommands::List => {
+                if todo_list.trash.is_empty() {
+                    println!("Trash is empty.");
+                }
+                for entry in &todo_list.trash.entries {
+                    println!(
+                        "#{}: {} | from #{} | removed {}",
+                        entry.id, entry.item.description, entry.original_path, entry.removed_at
+                    );
+                }
+                return Ok(());
+            }
+            TrashCommands::Restore { id } => {
+                let path = todo_list.restore_item(id)?;
+                let item = todo_list.parse_path(&path)?;
+                println!("Restored todo item #{}: {}", path, item.description);
+            }
+            TrashCommands::Empty { older_than } => {
+                let before = older_than
+                    .map(|age| {
+                        parse_duration(&age)
+                            .map(|age| Local::now() - age)
+                            .ok_or_else(|| errors::invalid(format!("Invalid duration: {}", age)))
+                    })
+                    .transpose()?;
+                let count = todo_list.trash.empty(before);
+                println!("Deleted {} items from the trash", count);
+            }
+        },
+        Commands::Milestone(command) => match command {
+            MilestoneCommands::Create { name, deadline } => {
+                let deadline = deadline
+                    .map(|deadline| parse_deadline(Some(deadline), &config.dates))
+                    .transpose()?;
+                let milestone = todo_list.milestones.create(name, deadline)?;
+                println!("Created milestone {}", milestone.name);
+            }
+            MilestoneCommands::Assign { path, name } => {
+                todo_list.milestones.get(&name)?;
+                let path = resolve_path(&mut todo_list, &path)?;
+                let item = todo_list.parse_path(&path)?;
+                item.milestone = Some(name);
+                item.touch();
+                println!(
+                    "Assigned todo item #{} to milestone {}: {}",
+                    path,
+                    item.milestone.as_deref().unwrap_or_default(),
+                    item.description
+                );
+            }
+            MilestoneCommands::Unassign { path } => {
+                let path = resolve_path(&mut todo_list, &path)?;
+                let item = todo_list.parse_path(&path)?;
+                item.milestone = None;
+                item.touch();
+                println!("Unassigned todo item #{}: {}", path, item.description);
+            }
+            MilestoneCommands::Status { name } => {
+                milestone::print_status(&todo_list, name.as_deref())?;
+                return Ok(());
+            }
+        },
+        Commands::Tag(command) => match command {
+            TagCommands::List => {
+                let counts = todo_list.tag_counts();
+                if counts.is_empty() {
+                    println!("No tags found.");
+                }
+                for (tag, count) in counts {
+                    println!("#{} ({})", tag, count);
+                }
+                return Ok(());
+            }
+            TagCommands::Rename { old, new } => {
+                if todo_list.tag_counts().contains_key(&new) {
+                    return Err(errors::refused(format!(
+                        "Tag #{} already exists, use `tag merge` instead",
+                        new
+                    )));
+                }
+                let count = todo_list.retag(&old, Some(&new));
+                println!("Renamed #{} to #{} on {} items", old, new, count);
+            }
+            TagCommands::Rm { tag } => {
+                let count = todo_list.retag(&tag, None);
+                println!("Removed #{} from {} items", tag, count);
+            }
+            TagCommands::Merge { from, into } => {
+                let count = todo_list.retag(&from, Some(&into));
+                println!("Merged #{} into #{} on {} items", from, into, count);
+            }
+        },
+        Commands::Status { .. } => unreachable!("status runs before the list is loaded"),
         Commands::Completion { shell } => {
             let mut cmd = Cli::command();
             match shell {
@@ -146,7 +2291,8 @@ fn main() -> Result<()> {
                 }
             }
         }
+        Commands::External(_) => unreachable!("external commands run before the list is loaded"),
     }
 
-    save_todo_list(&file_path, &todo_list)
+    session.save(&mut todo_list, &command_line)
 }
@@ -0,0 +1,324 @@
+use crate::exchange::Node;
+use crate::todo_list::{TodoItem, TodoList};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A `- [ ]` task found in a note
+#[derive(Debug, PartialEq)]
+struct Task {
+    /// Index of the line in the file
+    line: usize,
+    done: bool,
+    /// Text without the checkbox and date annotations
+    description: String,
+    due: Option<DateTime<Local>>,
+}
+
+/// Parses a date written in a task, meaning the end of that day
+fn parse_date(date: &str) -> Option<DateTime<Local>> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()?
+        .and_time(NaiveTime::from_hms_opt(23, 59, 59)?)
+        .and_local_timezone(Local)
+        .earliest()
+}
+
+/// Parses a task line like `- [ ] Call Bob 📅 2024-05-01` or `* [x] Pay rent due:2024-05-01`
+///
+/// The Obsidian Tasks emoji for other dates and their values are dropped from
+/// the description
+fn parse_task(line: &str) -> Option<(bool, String, Option<DateTime<Local>>)> {
+    let rest = line.trim_start();
+    let rest = rest
+        .strip_prefix("- ")
+        .or_else(|| rest.strip_prefix("* "))?;
+    let done = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let mut due = None;
+    let mut words = Vec::new();
+    let mut tokens = rest[3..].split_whitespace();
+    while let Some(token) = tokens.next() {
+        if let Some(date) = token.strip_prefix("due:") {
+            due = parse_date(date).or(due);
+        } else if token == "📅" {
+            due = tokens.next().and_then(parse_date).or(due);
+        } else if ["✅", "⏳", "🛫", "➕", "❌"].contains(&token) {
+            tokens.next();
+        } else {
+            words.push(token);
+        }
+    }
+    let description = words.join(" ");
+    (!description.is_empty()).then_some((done, description, due))
+}
+
+/// Returns the tasks of a note
+fn parse_tasks(content: &str) -> Vec<Task> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let (done, description, due) = parse_task(text)?;
+            Some(Task {
+                line,
+                done,
+                description,
+                due,
+            })
+        })
+        .collect()
+}
+
+/// Returns the Markdown files below `dir`, skipping hidden directories like `.obsidian`
+fn markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+        if path.is_dir() {
+            markdown_files(&path, files)?;
+        } else if path.extension().is_some_and(|extension| extension == "md") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Sets the checkbox of a task line
+fn set_checkbox(line: &str, done: bool) -> String {
+    let (old, new) = if done {
+        ("[ ]", "[x]")
+    } else if line.contains("[x]") {
+        ("[x]", "[ ]")
+    } else {
+        ("[X]", "[ ]")
+    };
+    line.replacen(old, new, 1)
+}
+
+/// Imports the tasks of a Markdown file, more deeply indented tasks becoming
+/// sub items of the task above them
+pub fn import(path: &Path) -> Result<Vec<TodoItem>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut root = Node::new(TodoItem::new(String::new()));
+    // indents of the tasks the next one may be nested in
+    let mut indents: Vec<usize> = Vec::new();
+    for line in content.lines() {
+        let Some((done, description, due)) = parse_task(line) else {
+            continue;
+        };
+        let indent: usize = line
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .map(|c| if c == '\t' { 4 } else { 1 })
+            .sum();
+        while indents.last().is_some_and(|last| *last >= indent) {
+            indents.pop();
+        }
+        let mut item = TodoItem::new(description);
+        item.set_deadline(due);
+        if done {
+            item.complete();
+        }
+        root.insert(indents.len(), Node::new(item));
+        indents.push(indent);
+    }
+    Ok(root.children.into_iter().map(Node::into_item).collect())
+}
+
+fn write_tasks(out: &mut String, todo_list: &TodoList, depth: usize) {
+    for item in &todo_list.items {
+        let checkbox = if item.is_completed() { "[x]" } else { "[ ]" };
+        out.push_str(&format!(
+            "{}- {} {}",
+            "  ".repeat(depth),
+            checkbox,
+            item.description
+        ));
+        if let Some(deadline) = item.deadline_time() {
+            out.push_str(&format!(" 📅 {}", deadline.format("%Y-%m-%d")));
+        }
+        out.push('\n');
+        if let Some(sub_list) = &item.sub_list {
+            write_tasks(out, sub_list, depth + 1);
+        }
+    }
+}
+
+/// Renders the list as a Markdown checklist, sub items indented by two spaces
+pub fn export(todo_list: &TodoList) -> String {
+    let mut out = String::new();
+    write_tasks(&mut out, todo_list, 0);
+    out
+}
+
+/// Counts of what a sync changed on either side
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub items_added: usize,
+    pub items_updated: usize,
+    pub tasks_updated: usize,
+    pub files_written: usize,
+}
+
+/// Brings the tasks in the Markdown files below `dir` and the list in step
+///
+/// Pending tasks no item is linked to are added as items, remembering their file.
+/// When an item and its task disagree on being done, the item wins if it was
+/// modified after the file, and the task's checkbox is rewritten
+pub fn sync(todo_list: &mut TodoList, dir: &Path) -> Result<SyncReport> {
+    let mut report = SyncReport::default();
+    let mut files = Vec::new();
+    markdown_files(dir, &mut files)?;
+    files.sort();
+
+    for file in files {
+        let content = fs::read_to_string(&file)?;
+        let tasks = parse_tasks(&content);
+        if tasks.is_empty() {
+            continue;
+        }
+        let relative = file
+            .strip_prefix(dir)
+            .unwrap_or(&file)
+            .to_string_lossy()
+            .to_string();
+        let modified: DateTime<Local> = fs::metadata(&file)?.modified()?.into();
+        let mut lines: Vec<String> = content.lines().map(String::from).collect();
+        let mut changed = false;
+        // items already matched to a task, so repeated tasks match repeated items
+        let mut matched = Vec::new();
+
+        for task in tasks {
+            let mut found = None;
+            todo_list.walk_mut(&mut |item| {
+                if found.is_none()
+                    && item.markdown_file.as_deref() == Some(relative.as_str())
+                    && item.description == task.description
+                    && !matched.contains(&item.uuid)
+                {
+                    matched.push(item.uuid.clone());
+                    if item.is_completed() == task.done {
+                        found = Some(None);
+                    } else if item.modified_time().is_some_and(|time| time > modified) {
+                        found = Some(Some(item.is_completed()));
+                    } else {
+                        if task.done {
+                            item.complete();
+                        } else {
+                            item.reopen();
+                        }
+                        report.items_updated += 1;
+                        found = Some(None);
+                    }
+                }
+            });
+            match found {
+                Some(Some(done)) => {
+                    lines[task.line] = set_checkbox(&lines[task.line], done);
+                    report.tasks_updated += 1;
+                    changed = true;
+                }
+                Some(None) => {}
+                None if task.done => {}
+                None => {
+                    let item = todo_list.add_item(task.description, task.due, None)?;
+                    item.markdown_file = Some(relative.clone());
+                    matched.push(item.uuid.clone());
+                    report.items_added += 1;
+                }
+            }
+        }
+
+        if changed {
+            let mut content = lines.join("\n");
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            fs::write(&file, content)?;
+            report.files_written += 1;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod markdown_tests {
+    use super::*;
+
+    #[test]
+    fn task_lines() {
+        let due = parse_date("2024-05-01");
+        assert_eq!(
+            parse_task("  - [ ] Call Bob 📅 2024-05-01 ➕ 2024-04-01"),
+            Some((false, "Call Bob".to_string(), due))
+        );
+        assert_eq!(
+            parse_task("* [x] Pay rent due:2024-05-01"),
+            Some((true, "Pay rent".to_string(), due))
+        );
+        assert_eq!(parse_task("- [link](url)"), None);
+        assert_eq!(parse_task("- plain bullet"), None);
+        assert_eq!(set_checkbox("- [ ] a", true), "- [x] a");
+        assert_eq!(set_checkbox("- [X] a", false), "- [ ] a");
+    }
+
+    #[test]
+    fn nested_checklist() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("checklist.md");
+        fs::write(
+            &path,
+            "# Trip\n- [ ] Pack 📅 2024-05-01\n    - [x] Passport\n    - [ ] Charger\n- [ ] Book\n",
+        )?;
+        let items = import(&path)?;
+        assert_eq!(items.len(), 2);
+        let mut list = TodoList::new();
+        for item in items {
+            list.push_item(item);
+        }
+        assert_eq!(
+            export(&list),
+            "- [ ] Pack 📅 2024-05-01\n  - [x] Passport\n  - [ ] Charger\n- [ ] Book\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sync_notes() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        fs::create_dir_all(dir.join(".obsidian"))?;
+        fs::write(dir.join(".obsidian").join("hidden.md"), "- [ ] hidden\n")?;
+        fs::write(dir.join("daily.md"), "# Today\n- [ ] Call Bob\n- [x] Old\n")?;
+
+        let mut list = TodoList::new();
+        let report = sync(&mut list, dir)?;
+        assert_eq!(report.items_added, 1);
+        assert_eq!(list.items[0].markdown_file.as_deref(), Some("daily.md"));
+
+        list.complete_item(&"0".to_string())?;
+        // the item was completed after the file was written
+        list.items[0].modified_at = Some((Local::now() + chrono::Duration::minutes(1)).to_string());
+        let report = sync(&mut list, dir)?;
+        assert_eq!((report.items_added, report.tasks_updated), (0, 1));
+        assert_eq!(
+            fs::read_to_string(dir.join("daily.md"))?,
+            "# Today\n- [x] Call Bob\n- [x] Old\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use crate::config::Config;
+use crate::filter::Filter;
+use crate::session::Session;
+use crate::utils::{format_path, parse_deadline, resolve_path};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+
+/// Protocol revision this server implements
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Describes the tools offered to the client, see `call_tool`
+fn tools() -> Value {
+    json!([
+        {
+            "name": "list_items",
+            "description": "List todo items with their ID paths, only pending ones unless \
+                            the filter asks for done items",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "filter": {
+                        "type": "string",
+                        "description": "Whitespace-separated terms that must all match: \
+                                        done, pending, overdue, due:<deadline> or words \
+                                        of the description, e.g. \"overdue report\""
+                    }
+                }
+            }
+        },
+        {
+            "name": "add_item",
+            "description": "Add a todo item, optionally as a sub item of another",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "description": { "type": "string" },
+                    "parent": { "type": "string", "description": "ID path like \"0:1\"" },
+                    "deadline": { "type": "string", "description": "e.g. \"2024-05-01 18:00\"" }
+                },
+                "required": ["description"]
+            }
+        },
+        {
+            "name": "complete_item",
+            "description": "Mark a todo item as done",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "ID path like \"0:1\"" }
+                },
+                "required": ["path"]
+            }
+        }
+    ])
+}
+
+/// Runs a Model Context Protocol server on stdin and stdout until stdin closes
+///
+/// Messages are newline-delimited JSON-RPC. The list is read for every tool call
+/// and changes are saved like the command line saves them
+pub fn serve(file: &str, config: &Config) -> Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle(&message, file, config),
+            Err(error) => Some(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": error.to_string() }
+            })),
+        };
+        if let Some(response) = response {
+            writeln!(stdout, "{}", response)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Answers one JSON-RPC message, notifications getting no answer
+fn handle(message: &Value, file: &str, config: &Config) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let method = message["method"].as_str().unwrap_or_default();
+    let result = match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "td", "version": env!("CARGO_PKG_VERSION") }
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools() })),
+        "tools/call" => {
+            let params = &message["params"];
+            let name = params["name"].as_str().unwrap_or_default();
+            // tool failures are reported to the model, not as protocol errors
+            Ok(match call_tool(name, &params["arguments"], file, config) {
+                Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+                Err(error) => json!({
+                    "content": [{ "type": "text", "text": error.to_string() }],
+                    "isError": true
+                }),
+            })
+        }
+        _ => Err(json!({ "code": -32601, "message": format!("Unknown method {}", method) })),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    })
+}
+
+/// Runs a tool, returning the text shown to the model
+fn call_tool(name: &str, arguments: &Value, file: &str, config: &Config) -> Result<String> {
+    let argument = |key: &str| arguments.get(key).and_then(Value::as_str).map(String::from);
+    let required =
+        |key: &str| argument(key).ok_or_else(|| anyhow::anyhow!("Missing argument '{}'", key));
+    let mut session = Session::open(file, config)?;
+    let mut todo_list = session.load()?;
+
+    let text = match name {
+        "list_items" => {
+            let filter = Filter::parse(&argument("filter").unwrap_or_default(), &config.dates)?;
+            let mut lines = Vec::new();
+            todo_list.walk(&mut |path, item| {
+                if (filter.has_status() || !item.is_completed()) && filter.matches(item) {
+                    lines.push(item.format_info_at(&format_path(path)));
+                }
+            });
+            if lines.is_empty() {
+                return Ok("No matching items".to_string());
+            }
+            return Ok(lines.join("\n"));
+        }
+        "add_item" => {
+            let deadline = argument("deadline")
+                .map(|deadline| parse_deadline(Some(deadline), &config.dates))
+                .transpose()?;
+            let parent = argument("parent")
+                .map(|parent| resolve_path(&mut todo_list, &parent))
+                .transpose()?;
+            let item = todo_list.add_item(required("description")?, deadline, parent.as_ref())?;
+            let path = match &parent {
+                Some(parent) => format!("{}:{}", parent, item.id),
+                None => item.id.to_string(),
+            };
+            format!("Added todo item #{}: {}", path, item.description)
+        }
+        "complete_item" => {
+            let path = resolve_path(&mut todo_list, &required("path")?)?;
+            let item = todo_list.complete_item(&path)?;
+            format!("Completed todo item #{}: {}", path, item.description)
+        }
+        _ => return Err(anyhow::anyhow!("Unknown tool {}", name)),
+    };
+    session.save(&mut todo_list, &format!("mcp {}", name))?;
+    Ok(text)
+}
+
+#[cfg(test)]
+mod mcp_tests {
+    use super::*;
+
+    #[test]
+    fn tool_calls() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("todo.json").display().to_string();
+        let config = Config::default();
+        let call = |id: u64, name: &str, arguments: Value| {
+            let message = json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": name, "arguments": arguments }
+            });
+            handle(&message, &file, &config).unwrap()
+        };
+
+        let added = call(1, "add_item", json!({ "description": "write tests" }));
+        assert_eq!(
+            added["result"]["content"][0]["text"],
+            "Added todo item #0: write tests"
+        );
+        let listed = call(2, "list_items", json!({}));
+        assert!(listed["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("write tests"));
+        let missing = call(3, "complete_item", json!({}));
+        assert_eq!(missing["result"]["isError"], true);
+        // targets are resolved like on the command line
+        let completed = call(4, "complete_item", json!({ "path": "tests" }));
+        assert_eq!(
+            completed["result"]["content"][0]["text"],
+            "Completed todo item #0: write tests"
+        );
+        let listed = call(5, "list_items", json!({}));
+        assert_eq!(listed["result"]["content"][0]["text"], "No matching items");
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(handle(&notification, &file, &config).is_none());
+        Ok(())
+    }
+}
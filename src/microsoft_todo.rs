@@ -0,0 +1,278 @@
+use crate::exchange::{group_by_list, parse_due, parse_flag, read_rows};
+use crate::todo_list::{Comment, Priority, TodoItem, TodoList};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+/// List of tasks that don't name theirs, the default list of the app
+const DEFAULT_LIST: &str = "Tasks";
+
+/// A JSON export, the lists of the Graph API with their tasks, bare or wrapped
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Export {
+    Lists(Vec<TaskList>),
+    Wrapped {
+        #[serde(alias = "value")]
+        lists: Vec<TaskList>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskList {
+    #[serde(alias = "name")]
+    display_name: String,
+    #[serde(default)]
+    tasks: Vec<Task>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Task {
+    title: String,
+    /// notStarted, inProgress or completed
+    #[serde(default)]
+    status: String,
+    /// low, normal or high
+    #[serde(default)]
+    importance: String,
+    #[serde(default)]
+    body: Option<Body>,
+    #[serde(default)]
+    due_date_time: Option<ZonedTime>,
+    #[serde(default)]
+    completed_date_time: Option<ZonedTime>,
+    #[serde(default)]
+    checklist_items: Vec<ChecklistItem>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Body {
+    content: String,
+    #[serde(default)]
+    content_type: String,
+}
+
+/// A time without offset and the name of its time zone
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ZonedTime {
+    date_time: String,
+    #[serde(default)]
+    time_zone: String,
+}
+
+impl ZonedTime {
+    /// Returns the time, in UTC if the zone says so and local time otherwise
+    fn time(&self) -> Option<DateTime<Local>> {
+        let time = NaiveDateTime::parse_from_str(&self.date_time, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+        match self.time_zone.as_str() {
+            "UTC" | "Etc/UTC" => Some(time.and_utc().with_timezone(&Local)),
+            _ => time.and_local_timezone(Local).earliest(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChecklistItem {
+    display_name: String,
+    #[serde(default)]
+    is_checked: bool,
+}
+
+/// Drops the tags of an HTML body, keeping line breaks
+fn strip_html(html: &str) -> String {
+    let html = html
+        .replace("<br>", "\n")
+        .replace("<br/>", "\n")
+        .replace("</p>", "\n")
+        .replace("</div>", "\n");
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+impl Task {
+    /// Reads a CSV row, whose due date may be any date td understands
+    fn from_row(row: &HashMap<String, String>) -> Self {
+        let field = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| row.get(*name).filter(|value| !value.is_empty()))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let zoned = |date_time: String| {
+            Some(ZonedTime {
+                date_time,
+                time_zone: String::new(),
+            })
+        };
+        let status = field(&["status", "completed"]);
+        Self {
+            title: field(&["title", "subject"]),
+            status: match status.as_str() {
+                "notStarted" | "inProgress" | "completed" => status,
+                _ if parse_flag(&status) => "completed".to_string(),
+                _ => String::new(),
+            },
+            importance: field(&["importance", "priority"]).to_lowercase(),
+            body: Some(Body {
+                content: field(&["notes", "body", "description"]),
+                content_type: "text".to_string(),
+            }),
+            due_date_time: zoned(field(&["due date", "due"])),
+            completed_date_time: zoned(field(&["completed date", "completion date"])),
+            checklist_items: Vec::new(),
+        }
+    }
+
+    fn into_item(self) -> TodoItem {
+        let mut item = TodoItem::new(self.title);
+        item.priority = match self.importance.as_str() {
+            "high" => Some(Priority::High),
+            "low" => Some(Priority::Low),
+            _ => None,
+        };
+        if let Some(body) = self.body {
+            let text = match body.content_type.as_str() {
+                "html" => strip_html(&body.content),
+                _ => body.content,
+            };
+            if !text.trim().is_empty() {
+                item.comments.push(Comment {
+                    at: Local::now().to_string(),
+                    text: text.trim().to_string(),
+                });
+            }
+        }
+        // To Do only has due days, stored as midnight of the day
+        if let Some(due) = self.due_date_time.filter(|due| !due.date_time.is_empty()) {
+            match parse_due(due.date_time.get(..10).unwrap_or(&due.date_time)) {
+                Some(deadline) => item.set_deadline(Some(deadline)),
+                None => eprintln!(
+                    "Ignoring the due date '{}' of '{}'",
+                    due.date_time, item.description
+                ),
+            }
+        }
+        if !self.checklist_items.is_empty() {
+            let mut sub_list = TodoList::new();
+            for step in self.checklist_items {
+                let sub_item = sub_list.push_item(TodoItem::new(step.display_name));
+                if step.is_checked {
+                    sub_item.complete();
+                }
+            }
+            item.sub_list = Some(sub_list);
+        }
+        match self.status.as_str() {
+            "completed" => {
+                item.complete();
+                let completed = self
+                    .completed_date_time
+                    .as_ref()
+                    .and_then(|time| time.time().or_else(|| parse_due(&time.date_time)));
+                if let Some(time) = completed {
+                    item.completed_at = Some(time.to_string());
+                }
+            }
+            "inProgress" => item.start(),
+            _ => {}
+        }
+        item
+    }
+}
+
+/// Imports the lists of a JSON export in the shape of the Graph API, or a CSV
+/// file with a row per task, each list becoming a top-level item
+///
+/// Steps of tasks become their sub items
+pub fn import(path: &Path) -> Result<Vec<TodoItem>> {
+    let tasks: Vec<(String, Task)> = match path.extension().and_then(|extension| extension.to_str())
+    {
+        Some("json") => {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let export: Export = serde_json::from_str(&content)
+                .with_context(|| format!("{} is no Microsoft To Do export", path.display()))?;
+            let (Export::Lists(lists) | Export::Wrapped { lists }) = export;
+            lists
+                .into_iter()
+                .flat_map(|list| {
+                    let name = list.display_name;
+                    list.tasks.into_iter().map(move |task| (name.clone(), task))
+                })
+                .collect()
+        }
+        _ => read_rows(path)?
+            .iter()
+            .map(|row| {
+                let list = ["list", "folder"]
+                    .iter()
+                    .find_map(|name| row.get(*name).filter(|list| !list.is_empty()))
+                    .cloned()
+                    .unwrap_or(DEFAULT_LIST.to_string());
+                (list, Task::from_row(row))
+            })
+            .collect(),
+    };
+    Ok(group_by_list(
+        tasks
+            .into_iter()
+            .filter(|(_, task)| !task.title.is_empty())
+            .map(|(list, task)| (list, task.into_item()))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod microsoft_todo_tests {
+    use super::*;
+
+    #[test]
+    fn graph_lists() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("mstodo.json");
+        fs::write(
+            &path,
+            r#"{"value": [{"displayName": "Trip", "tasks": [
+                {"title": "Book hotel", "status": "completed", "importance": "high",
+                 "completedDateTime": {"dateTime": "2024-04-20T10:00:00.0000000", "timeZone": "UTC"}},
+                {"title": "Pack", "status": "notStarted",
+                 "dueDateTime": {"dateTime": "2024-05-01T00:00:00.0000000", "timeZone": "UTC"},
+                 "body": {"content": "<p>Passport &amp; tickets</p>", "contentType": "html"},
+                 "checklistItems": [{"displayName": "Passport", "isChecked": true}]}
+            ]}]}"#,
+        )?;
+        let items = import(&path)?;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].description, "Trip");
+        let tasks = &items[0].sub_list.as_ref().unwrap().items;
+        assert!(tasks[0].is_completed());
+        assert_eq!(tasks[0].priority, Some(Priority::High));
+        assert!(tasks[1]
+            .deadline
+            .as_ref()
+            .unwrap()
+            .starts_with("2024-05-01T23:59:59"));
+        assert_eq!(tasks[1].comments[0].text, "Passport & tickets");
+        assert!(tasks[1].sub_list.as_ref().unwrap().items[0].is_completed());
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use crate::errors;
+use crate::todo_list::TodoList;
+use crate::utils::{format_deadline, parse_timestamp, progress_bar};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A named goal whose progress is rolled up from the items assigned to it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Milestone {
+    pub name: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub deadline: Option<String>,
+}
+
+/// The collection of milestones stored alongside the root todo list
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+pub struct Milestones {
+    #[serde(default)]
+    pub items: Vec<Milestone>,
+}
+
+impl Milestones {
+    /// Returns true if no milestones exist
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Creates a new milestone, names must be unique
+    pub fn create(
+        &mut self,
+        name: String,
+        deadline: Option<DateTime<Local>>,
+    ) -> Result<&Milestone> {
+        if self.items.iter().any(|milestone| milestone.name == name) {
+            return Err(errors::refused(format!(
+                "Milestone {} already exists",
+                name
+            )));
+        }
+        self.items.push(Milestone {
+            name,
+            created_at: Local::now().to_string(),
+            deadline: deadline.map(format_deadline),
+        });
+        Ok(&self.items[self.items.len() - 1])
+    }
+
+    /// Finds a milestone by its name
+    pub fn get(&self, name: &str) -> Result<&Milestone> {
+        self.items
+            .iter()
+            .find(|milestone| milestone.name == name)
+            .ok_or_else(|| errors::not_found(format!("Milestone {} not found", name)))
+    }
+}
+
+/// Prints the progress of one or all milestones, counting assigned items anywhere in the tree
+pub fn print_status(todo_list: &TodoList, name: Option<&str>) -> Result<()> {
+    let milestones: Vec<&Milestone> = match name {
+        Some(name) => vec![todo_list.milestones.get(name)?],
+        None => todo_list.milestones.items.iter().collect(),
+    };
+    if milestones.is_empty() {
+        println!("No milestones found.");
+    }
+    for milestone in milestones {
+        let assigned = |done: bool| {
+            todo_list.count_where(|item| {
+                item.milestone.as_ref() == Some(&milestone.name) && (!done || item.is_completed())
+            })
+        };
+        let (done, total) = (assigned(true), assigned(false));
+        print!(
+            "{}: {} {}/{}",
+            milestone.name,
+            progress_bar(done, total, 20),
+            done,
+            total
+        );
+        match milestone.deadline.as_deref() {
+            Some(deadline) => match parse_timestamp(deadline) {
+                Some(time) => println!(" | deadline: {}", time.format("%Y-%m-%d %H:%M")),
+                None => println!(" | deadline: {}", deadline),
+            },
+            None => println!(),
+        }
+    }
+    Ok(())
+}
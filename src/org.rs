@@ -0,0 +1,307 @@
+use crate::exchange::Node;
+use crate::todo_list::{Comment, Priority, Status, TodoItem, TodoList};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Timelike};
+use std::{fs, path::Path};
+
+/// Keywords written on export, declared in the file so Emacs knows STARTED
+const TODO_LINE: &str = "#+TODO: TODO STARTED | DONE";
+
+/// Parses an org timestamp like `<2024-05-01 Wed 18:00>` or `[2024-05-01 Wed]`,
+/// a date without time meaning the end of that day
+fn parse_org_time(timestamp: &str) -> Option<DateTime<Local>> {
+    let inner = timestamp.trim_matches(['<', '>', '[', ']']);
+    let mut parts = inner.split_whitespace();
+    let date = NaiveDate::parse_from_str(parts.next()?, "%Y-%m-%d").ok()?;
+    // a time range like 10:00-11:00 starts at its first time
+    let time = parts
+        .find_map(|part| NaiveTime::parse_from_str(part.split('-').next()?, "%H:%M").ok())
+        .unwrap_or(NaiveTime::from_hms_opt(23, 59, 59)?);
+    date.and_time(time).and_local_timezone(Local).earliest()
+}
+
+/// Formats a time as an org timestamp, leaving out the end of the day
+fn format_org_time(time: DateTime<Local>, active: bool) -> String {
+    let format = if time.hour() == 23 && time.minute() == 59 {
+        "%Y-%m-%d %a"
+    } else {
+        "%Y-%m-%d %a %H:%M"
+    };
+    let (open, close) = if active { ('<', '>') } else { ('[', ']') };
+    format!("{}{}{}", open, time.format(format), close)
+}
+
+/// Returns the timestamp following `keyword` on a planning line
+fn planning_time(line: &str, keyword: &str) -> Option<DateTime<Local>> {
+    let rest = line[line.find(keyword)? + keyword.len()..].trim_start();
+    let close = match rest.chars().next()? {
+        '<' => '>',
+        '[' => ']',
+        _ => return None,
+    };
+    parse_org_time(&rest[..=rest.find(close)?])
+}
+
+/// TODO keywords of a file, those after `|` in a `#+TODO:` line meaning done
+struct Keywords {
+    pending: Vec<String>,
+    done: Vec<String>,
+}
+
+impl Keywords {
+    fn add_line(&mut self, line: &str) {
+        let mut done = false;
+        for word in line.split_whitespace() {
+            // fast-access keys like WAIT(w) aren't part of the keyword
+            let word = word.split('(').next().unwrap_or(word).to_string();
+            match word.as_str() {
+                "|" => done = true,
+                _ if done => self.done.push(word),
+                _ => self.pending.push(word),
+            }
+        }
+    }
+}
+
+/// Builds an item from a heading without its stars
+fn parse_heading(heading: &str, keywords: &Keywords) -> TodoItem {
+    let mut words: Vec<&str> = heading.split_whitespace().collect();
+    let mut status = None;
+    if let Some(first) = words.first() {
+        if keywords.done.iter().any(|keyword| keyword == first) {
+            status = Some(Status::Done);
+        } else if keywords.pending.iter().any(|keyword| keyword == first) {
+            status = Some(if *first == "STARTED" {
+                Status::Started
+            } else {
+                Status::Todo
+            });
+        }
+    }
+    if status.is_some() {
+        words.remove(0);
+    }
+    let priority = match words.first() {
+        Some(&"[#A]") => Some(Priority::High),
+        Some(&"[#B]") => Some(Priority::Medium),
+        Some(&"[#C]") => Some(Priority::Low),
+        _ => None,
+    };
+    if priority.is_some() {
+        words.remove(0);
+    }
+    let mut tags = Vec::new();
+    if let Some(last) = words
+        .last()
+        .filter(|last| last.len() > 1 && last.starts_with(':') && last.ends_with(':'))
+    {
+        tags = last
+            .split(':')
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect();
+        words.pop();
+    }
+
+    let mut item = TodoItem::new(words.join(" "));
+    item.priority = priority;
+    item.tags = tags;
+    match status {
+        Some(Status::Done) => item.complete(),
+        Some(Status::Started) => item.start(),
+        _ => {}
+    }
+    item
+}
+
+/// Adds the body paragraphs of a heading as comments
+fn add_body(item: &mut TodoItem, body: &[&str]) {
+    for paragraph in body.split(|line| line.trim().is_empty()) {
+        if !paragraph.is_empty() {
+            item.comments.push(Comment {
+                at: Local::now().to_string(),
+                text: paragraph.join("\n"),
+            });
+        }
+    }
+}
+
+/// Adds a heading read with its level and body lines below `root`
+fn finish(root: &mut Node, heading: Option<(usize, TodoItem, Vec<&str>)>) {
+    if let Some((level, mut item, body)) = heading {
+        add_body(&mut item, &body);
+        root.insert(level - 1, Node::new(item));
+    }
+}
+
+/// Imports the headings of an org file, nesting levels becoming sub items
+///
+/// DEADLINE sets the deadline, SCHEDULED only when there is none, CLOSED the
+/// completion time and the body paragraphs the comments, drawers are skipped
+pub fn import(path: &Path) -> Result<Vec<TodoItem>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut keywords = Keywords {
+        pending: vec!["TODO".to_string()],
+        done: vec!["DONE".to_string()],
+    };
+    let mut root = Node::new(TodoItem::new(String::new()));
+    // the heading read last, with its level and body lines
+    let mut current: Option<(usize, TodoItem, Vec<&str>)> = None;
+    let mut in_drawer = false;
+
+    for line in content.lines() {
+        let level = line.chars().take_while(|c| *c == '*').count();
+        if level > 0 && line[level..].starts_with(' ') {
+            finish(&mut root, current.take());
+            current = Some((level, parse_heading(&line[level..], &keywords), Vec::new()));
+            in_drawer = false;
+            continue;
+        }
+        let trimmed = line.trim();
+        let Some((_, item, body)) = current.as_mut() else {
+            for prefix in ["#+TODO:", "#+SEQ_TODO:", "#+TYP_TODO:"] {
+                if let Some(line) = trimmed.strip_prefix(prefix) {
+                    keywords.add_line(line);
+                }
+            }
+            continue;
+        };
+        if in_drawer {
+            in_drawer = trimmed != ":END:";
+        } else if trimmed.len() > 2 && trimmed.starts_with(':') && trimmed.ends_with(':') {
+            in_drawer = true;
+        } else if ["DEADLINE:", "SCHEDULED:", "CLOSED:"]
+            .iter()
+            .any(|keyword| trimmed.starts_with(keyword))
+        {
+            let deadline = planning_time(trimmed, "DEADLINE:")
+                .or_else(|| planning_time(trimmed, "SCHEDULED:"));
+            if let Some(deadline) = deadline {
+                item.set_deadline(Some(deadline));
+            }
+            if let Some(closed) = planning_time(trimmed, "CLOSED:").filter(|_| item.is_completed())
+            {
+                item.completed_at = Some(closed.to_string());
+            }
+        } else {
+            body.push(trimmed);
+        }
+    }
+    finish(&mut root, current);
+    Ok(root.children.into_iter().map(Node::into_item).collect())
+}
+
+/// Writes the items of a list as headings `level` stars deep
+fn write_items(out: &mut String, todo_list: &TodoList, level: usize) {
+    for item in &todo_list.items {
+        let keyword = match item.status {
+            Status::Todo => "TODO",
+            Status::Started => "STARTED",
+            Status::Done => "DONE",
+        };
+        out.push_str(&format!("{} {}", "*".repeat(level), keyword));
+        match item.priority {
+            Some(Priority::High) => out.push_str(" [#A]"),
+            Some(Priority::Medium) => out.push_str(" [#B]"),
+            Some(Priority::Low) => out.push_str(" [#C]"),
+            None => {}
+        }
+        out.push_str(&format!(" {}", item.description));
+        if !item.tags.is_empty() {
+            out.push_str(&format!(" :{}:", item.tags.join(":")));
+        }
+        out.push('\n');
+
+        let mut planning = Vec::new();
+        if let Some(closed) = item.completed_time().filter(|_| item.is_completed()) {
+            planning.push(format!("CLOSED: {}", format_org_time(closed, false)));
+        }
+        if let Some(deadline) = item.deadline_time() {
+            planning.push(format!("DEADLINE: {}", format_org_time(deadline, true)));
+        }
+        if !planning.is_empty() {
+            out.push_str(&format!("{}\n", planning.join(" ")));
+        }
+        for (index, comment) in item.comments.iter().enumerate() {
+            if index > 0 {
+                out.push('\n');
+            }
+            out.push_str(&format!("{}\n", comment.text));
+        }
+        if let Some(sub_list) = &item.sub_list {
+            write_items(out, sub_list, level + 1);
+        }
+    }
+}
+
+/// Renders the list as an org document
+pub fn export(todo_list: &TodoList) -> String {
+    let mut out = format!("{}\n\n", TODO_LINE);
+    write_items(&mut out, todo_list, 1);
+    out
+}
+
+#[cfg(test)]
+mod org_tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("notes.org");
+        fs::write(
+            &path,
+            "#+TITLE: Notes\n\
+             #+TODO: TODO NEXT | DONE CANCELLED\n\
+             * Projects\n\
+             ** NEXT [#A] Write report :work:urgent:\n\
+             SCHEDULED: <2024-05-01 Wed>\n\
+             First paragraph\n\
+             \n\
+             Second paragraph\n\
+             *** DONE Outline\n\
+             CLOSED: [2024-04-20 Sat 09:30] DEADLINE: <2024-04-21 Sun 18:00>\n\
+             :LOGBOOK:\n\
+             - State \"DONE\" from \"TODO\" [2024-04-20 Sat 09:30]\n\
+             :END:\n\
+             * CANCELLED Old idea\n",
+        )?;
+        let items = import(&path)?;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].description, "Projects");
+        let report = &items[0].sub_list.as_ref().unwrap().items[0];
+        assert_eq!(report.description, "Write report");
+        assert_eq!(report.priority, Some(Priority::High));
+        assert_eq!(report.tags, ["work", "urgent"]);
+        assert!(report
+            .deadline
+            .as_ref()
+            .unwrap()
+            .starts_with("2024-05-01T23:59:59"));
+        assert_eq!(report.comments.len(), 2);
+        let outline = &report.sub_list.as_ref().unwrap().items[0];
+        assert!(outline.is_completed());
+        assert!(outline.comments.is_empty());
+        assert!(outline
+            .completed_at
+            .as_ref()
+            .unwrap()
+            .starts_with("2024-04-20 09:30"));
+        assert!(items[1].is_completed());
+
+        let mut list = TodoList::new();
+        for item in items {
+            list.push_item(item);
+        }
+        let org = export(&list);
+        assert!(
+            org.contains("** TODO [#A] Write report :work:urgent:\nDEADLINE: <2024-05-01 Wed>\n")
+        );
+        assert!(org.contains(
+            "*** DONE Outline\nCLOSED: [2024-04-20 Sat 09:30] DEADLINE: <2024-04-21 Sun 18:00>\n"
+        ));
+        assert!(org.contains("First paragraph\n\nSecond paragraph\n"));
+        Ok(())
+    }
+}
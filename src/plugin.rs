@@ -0,0 +1,31 @@
+use crate::config::Config;
+use crate::errors::{self, ExitCode};
+use anyhow::{Context, Result};
+use std::{io::ErrorKind, process::Command};
+
+/// Runs the external subcommand `td-<name>` found on PATH, git-style
+///
+/// The plugin gets the resolved todo file in `TD_FILE` and td's configuration
+/// directory in `TD_CONFIG_DIR`. Returns the plugin's exit code
+pub fn run(args: &[String], file: &str) -> Result<i32> {
+    let (name, args) = args
+        .split_first()
+        .ok_or_else(|| errors::error(ExitCode::Usage, "No subcommand given"))?;
+    let program = format!("td-{}", name);
+    let status = Command::new(&program)
+        .args(args)
+        .env("TD_FILE", file)
+        .env("TD_CONFIG_DIR", Config::dir()?)
+        .status();
+    match status {
+        Ok(status) => Ok(status.code().unwrap_or(1)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Err(errors::error(
+            ExitCode::Usage,
+            format!(
+                "Unknown command '{}', and no {} found on PATH",
+                name, program
+            ),
+        )),
+        Err(error) => Err(error).with_context(|| format!("Failed to run {}", program)),
+    }
+}
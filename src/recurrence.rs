@@ -0,0 +1,128 @@
+use crate::errors;
+use crate::todo_list::TodoItem;
+use crate::utils::parse_duration;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local};
+use croner::Cron;
+use std::str::FromStr;
+
+/// How an item recurs: an interval like `1d` or `2d 12h`, or a cron expression
+/// like `0 9 * * MON-FRI` (minute, hour, day of month, month and weekday)
+#[derive(Debug, Clone)]
+pub enum Recurrence {
+    Every(Duration),
+    Cron(Box<Cron>),
+}
+
+impl FromStr for Recurrence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.split_whitespace().count() == 5 {
+            return Cron::new(s)
+                .parse()
+                .map(|cron| Recurrence::Cron(Box::new(cron)))
+                .map_err(|err| {
+                    errors::invalid(format!("Invalid cron expression '{}': {}", s, err))
+                });
+        }
+        parse_duration(s)
+            .filter(|interval| *interval > Duration::zero())
+            .map(Recurrence::Every)
+            .ok_or_else(|| {
+                errors::invalid(format!(
+                    "Invalid repeat '{}', expected an interval like 1d or a cron expression like \"0 9 * * MON\"",
+                    s
+                ))
+            })
+    }
+}
+
+impl Recurrence {
+    /// Returns the first time the item recurs at after `time`
+    pub fn after(&self, time: DateTime<Local>) -> Result<DateTime<Local>> {
+        match self {
+            Recurrence::Every(interval) => Ok(time + *interval),
+            Recurrence::Cron(cron) => cron.find_next_occurrence(&time, false).map_err(|err| {
+                errors::invalid(format!("No next occurrence of '{}': {}", cron.pattern, err))
+            }),
+        }
+    }
+
+    /// Returns the deadline of the occurrence following one due at `deadline`
+    /// that is completed at `now`, skipping the occurrences missed meanwhile
+    ///
+    /// Intervals count from the deadline, or from `now` without one
+    pub fn next_deadline(
+        &self,
+        deadline: Option<DateTime<Local>>,
+        now: DateTime<Local>,
+    ) -> Result<DateTime<Local>> {
+        let Some(deadline) = deadline else {
+            return self.after(now);
+        };
+        match self {
+            Recurrence::Every(interval) => {
+                let missed = if deadline < now {
+                    (now - deadline).num_seconds() / interval.num_seconds().max(1)
+                } else {
+                    0
+                };
+                Ok(deadline + *interval * (missed as i32 + 1))
+            }
+            Recurrence::Cron(_) => self.after(deadline.max(now)),
+        }
+    }
+}
+
+/// Returns the pending copy of a repeating item that follows it once it is
+/// completed at `now`, None if the item doesn't repeat
+pub fn next_occurrence(item: &TodoItem, now: DateTime<Local>) -> Result<Option<TodoItem>> {
+    let Some(repeat) = &item.repeat else {
+        return Ok(None);
+    };
+    let deadline = repeat
+        .parse::<Recurrence>()?
+        .next_deadline(item.deadline_time(), now)?;
+    let mut next = item.fresh_copy(true);
+    next.set_deadline(Some(deadline));
+    Ok(Some(next))
+}
+
+#[cfg(test)]
+mod recurrence_tests {
+    use super::*;
+    use chrono::{Datelike, TimeZone, Timelike, Weekday};
+
+    #[test]
+    fn next_deadlines() -> Result<()> {
+        // a Wednesday
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let daily: Recurrence = "1d".parse()?;
+        let yesterday = now - Duration::days(1) + Duration::hours(1);
+        assert_eq!(
+            daily.next_deadline(Some(yesterday), now)?,
+            yesterday + Duration::days(1)
+        );
+        // missed occurrences are skipped
+        let last_week = now - Duration::days(7) - Duration::hours(1);
+        assert_eq!(
+            daily.next_deadline(Some(last_week), now)?,
+            last_week + Duration::days(8)
+        );
+
+        let weekdays: Recurrence = "0 9 * * MON-FRI".parse()?;
+        let next = weekdays.next_deadline(Some(now), now)?;
+        assert_eq!((next.day(), next.hour(), next.minute()), (2, 9, 0));
+        let friday = Local.with_ymd_and_hms(2024, 5, 3, 9, 0, 0).unwrap();
+        assert_eq!(
+            weekdays.next_deadline(Some(friday), now)?.weekday(),
+            Weekday::Mon
+        );
+
+        assert!("0 9 * * NOPE".parse::<Recurrence>().is_err());
+        assert!("often".parse::<Recurrence>().is_err());
+        Ok(())
+    }
+}
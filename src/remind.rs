@@ -0,0 +1,541 @@
+use crate::config::{EmailConfig, RemindConfig, SmtpSecurity};
+use crate::errors;
+use crate::todo_list::TodoList;
+use crate::utils::{format_path, parse_duration, parse_time_of_day, parse_timestamp};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Days, Local, NaiveTime};
+use lettre::{
+    message::MultiPart, transport::smtp::authentication::Credentials, Message, SmtpTransport,
+    Transport,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::BTreeMap, env, fs, path::Path, str::FromStr, time::Duration};
+
+/// Items worth a reminder, each as its ID path and description
+#[derive(Debug, Default)]
+pub struct Digest {
+    pub due_soon: Vec<(String, String)>,
+    pub overdue: Vec<(String, String)>,
+    pub due_today: Vec<(String, String)>,
+    pub completed_yesterday: Vec<(String, String)>,
+}
+
+/// Chat service whose incoming webhooks `td remind --webhook` posts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChatService {
+    Slack,
+    Discord,
+}
+
+impl Digest {
+    /// Collects the pending items that are overdue or due later today, and the
+    /// items completed yesterday
+    pub fn build(todo_list: &TodoList, now: DateTime<Local>) -> Self {
+        let mut overdue = Vec::new();
+        let mut due_today = Vec::new();
+        let mut completed_yesterday = Vec::new();
+        let yesterday = now.date_naive().checked_sub_days(Days::new(1));
+        todo_list.walk(&mut |path, item| {
+            if let Some(time) = item.completed_time().filter(|_| item.is_completed()) {
+                if Some(time.date_naive()) == yesterday {
+                    completed_yesterday.push((time, format_path(path), item.description.clone()));
+                }
+            }
+            let Some(deadline) = item.deadline_time().filter(|_| !item.is_completed()) else {
+                return;
+            };
+            let entry = (deadline, format_path(path), item.description.clone());
+            if deadline < now {
+                overdue.push(entry);
+            } else if deadline.date_naive() == now.date_naive() {
+                due_today.push(entry);
+            }
+        });
+        // sorted by deadline, dropped once in order
+        let by_deadline = |mut entries: Vec<(DateTime<Local>, String, String)>| {
+            entries.sort();
+            entries
+                .into_iter()
+                .map(|(_, path, description)| (path, description))
+                .collect()
+        };
+        Digest {
+            due_soon: Vec::new(),
+            overdue: by_deadline(overdue),
+            due_today: by_deadline(due_today),
+            completed_yesterday: by_deadline(completed_yesterday),
+        }
+    }
+
+    /// Returns true if there is nothing to remind of
+    pub fn is_empty(&self) -> bool {
+        self.due_soon.is_empty()
+            && self.overdue.is_empty()
+            && self.due_today.is_empty()
+            && self.completed_yesterday.is_empty()
+    }
+
+    /// Returns the sections of the digest that have items, with their titles
+    fn sections(&self) -> Vec<(&'static str, &[(String, String)])> {
+        [
+            ("Due soon", self.due_soon.as_slice()),
+            ("Overdue", self.overdue.as_slice()),
+            ("Due today", self.due_today.as_slice()),
+            ("Completed yesterday", self.completed_yesterday.as_slice()),
+        ]
+        .into_iter()
+        .filter(|(_, items)| !items.is_empty())
+        .collect()
+    }
+
+    /// Renders the digest as plain text
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (title, items) in self.sections() {
+            text.push_str(&format!("{}:\n", title));
+            for (path, description) in items {
+                text.push_str(&format!("  #{}: {}\n", path, description));
+            }
+        }
+        text
+    }
+
+    /// Renders the digest as an HTML document
+    pub fn to_html(&self) -> String {
+        let mut html = String::from("<html><body>\n");
+        for (title, items) in self.sections() {
+            html.push_str(&format!("<h3>{}</h3>\n<ul>\n", title));
+            for (path, description) in items {
+                html.push_str(&format!(
+                    "<li><code>#{}</code> {}</li>\n",
+                    path,
+                    escape_html(description)
+                ));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</body></html>\n");
+        html
+    }
+
+    /// Renders the digest in the markdown flavour of a chat service
+    pub fn to_chat(&self, service: ChatService) -> String {
+        let mut text = String::new();
+        for (title, items) in self.sections() {
+            match service {
+                ChatService::Slack => text.push_str(&format!("*{}*\n", title)),
+                ChatService::Discord => text.push_str(&format!("**{}**\n", title)),
+            }
+            for (path, description) in items {
+                let description = match service {
+                    // Slack only requires these three to be escaped
+                    ChatService::Slack => description
+                        .replace('&', "&amp;")
+                        .replace('<', "&lt;")
+                        .replace('>', "&gt;"),
+                    ChatService::Discord => description.clone(),
+                };
+                text.push_str(&format!("• `#{}` {}\n", path, description));
+            }
+        }
+        text
+    }
+}
+
+/// Parses a reminder of an item, a positive duration before its deadline
+pub fn parse_reminder(value: &str) -> Result<chrono::Duration> {
+    parse_duration(value)
+        .filter(|offset| *offset > chrono::Duration::zero())
+        .ok_or_else(|| {
+            errors::invalid(format!(
+                "Invalid reminder '{}', expected a duration like 30m or 1d",
+                value
+            ))
+        })
+}
+
+/// Times of day without reminders, spanning midnight if they end before they
+/// start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl FromStr for QuietHours {
+    type Err = anyhow::Error;
+
+    /// Parses a range like "22:00-08:00" or "10pm-7am"
+    fn from_str(s: &str) -> Result<Self> {
+        s.split_once('-')
+            .and_then(|(start, end)| {
+                Some(QuietHours {
+                    start: parse_time_of_day(start)?,
+                    end: parse_time_of_day(end)?,
+                })
+            })
+            .ok_or_else(|| {
+                errors::invalid(format!(
+                    "Invalid quiet hours '{}', expected a range like 22:00-08:00",
+                    s
+                ))
+            })
+    }
+}
+
+impl QuietHours {
+    /// Returns true if `time` falls within the quiet hours
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Reminders `td remind --lead` already sent, kept in `<file>.reminders` so a
+/// cron entry running every minute reminds of each deadline once
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReminderState {
+    /// When td last looked for items coming due
+    last_run: Option<String>,
+    /// Deadlines already reminded of by item uuid, followed by the reminder
+    /// of the item for those it asked for
+    fired: BTreeMap<String, String>,
+    /// When each item was last reminded of by uuid, for the throttle
+    #[serde(default)]
+    notified: BTreeMap<String, String>,
+}
+
+impl ReminderState {
+    /// Reads the state file, a missing or unreadable one meaning nothing fired yet
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Collects the pending items due within `lead` from now, or within one of
+    /// their own reminders, that weren't reminded of yet, and records them as
+    /// fired
+    ///
+    /// Items that came due while td didn't run are included too, but the first
+    /// run doesn't remind of items that were overdue already. Changing the
+    /// deadline of an item brings its reminders back. Reminders of an item
+    /// within `throttle` of its previous one are skipped
+    pub fn due_soon(
+        &mut self,
+        todo_list: &TodoList,
+        now: DateTime<Local>,
+        lead: chrono::Duration,
+        throttle: chrono::Duration,
+    ) -> Digest {
+        let since = self
+            .last_run
+            .as_deref()
+            .and_then(parse_timestamp)
+            .map_or(now, |last_run| last_run.min(now));
+        let mut pending = BTreeMap::new();
+        let mut due = Vec::new();
+        todo_list.walk(&mut |path, item| {
+            let (Some(deadline), Some(time)) = (&item.deadline, item.deadline_time()) else {
+                return;
+            };
+            if item.is_completed() {
+                return;
+            }
+            pending.insert(item.uuid.clone(), deadline.clone());
+            if time < since {
+                return;
+            }
+            // the lead is keyed by the uuid alone, the item's own reminders
+            // by the uuid and the reminder
+            let reminders = item.remind.iter().filter_map(|reminder| {
+                let offset = parse_reminder(reminder).ok()?;
+                Some((format!("{} {}", item.uuid, reminder), offset))
+            });
+            let keys: Vec<String> = [(item.uuid.clone(), lead)]
+                .into_iter()
+                .chain(reminders)
+                .filter(|(key, offset)| {
+                    time <= now + *offset && self.fired.get(key) != Some(deadline)
+                })
+                .map(|(key, _)| key)
+                .collect();
+            if !keys.is_empty() {
+                due.push((
+                    time,
+                    format_path(path),
+                    item.description.clone(),
+                    keys,
+                    deadline,
+                ));
+            }
+        });
+        // forget items completed, removed or rescheduled since
+        self.fired.retain(|key, deadline| {
+            let uuid = key.split(' ').next().unwrap_or_default();
+            pending.get(uuid) == Some(deadline)
+        });
+        self.notified.retain(|uuid, _| pending.contains_key(uuid));
+        due.sort_by_key(|(time, ..)| *time);
+        let mut digest = Digest::default();
+        for (_, path, description, keys, deadline) in due {
+            let uuid = keys[0].split(' ').next().unwrap_or_default().to_string();
+            for key in keys {
+                self.fired.insert(key, deadline.clone());
+            }
+            let throttled = self
+                .notified
+                .get(&uuid)
+                .and_then(|time| parse_timestamp(time))
+                .is_some_and(|last| now < last + throttle);
+            if !throttled {
+                self.notified.insert(uuid, now.to_string());
+                digest.due_soon.push((path, description));
+            }
+        }
+        self.last_run = Some(now.to_string());
+        digest
+    }
+}
+
+/// Longest message Discord accepts from a webhook
+const DISCORD_LIMIT: usize = 2000;
+
+/// Posts the digest to an incoming webhook of Slack or Discord
+pub fn send_chat(digest: &Digest, service: ChatService, config: &RemindConfig) -> Result<()> {
+    let (url, payload) = match service {
+        ChatService::Slack => (
+            config
+                .slack_url
+                .as_deref()
+                .context("No slack_url in the [remind] config")?,
+            json!({ "text": digest.to_chat(service) }),
+        ),
+        ChatService::Discord => {
+            let mut content = digest.to_chat(service);
+            if content.chars().count() > DISCORD_LIMIT {
+                content = content.chars().take(DISCORD_LIMIT - 1).collect();
+                content.push('…');
+            }
+            (
+                config
+                    .discord_url
+                    .as_deref()
+                    .context("No discord_url in the [remind] config")?,
+                json!({ "content": content }),
+            )
+        }
+    };
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .post(url)
+        .set("Content-Type", "application/json")
+        .send_string(&payload.to_string())
+        .with_context(|| format!("Failed to post the reminder to {:?}", service))?;
+    Ok(())
+}
+
+/// Escapes text for use inside HTML elements
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Sends the digest by email with the `[email]` settings
+///
+/// The SMTP password is read from the config or `TD_SMTP_PASSWORD`
+pub fn send_email(digest: &Digest, config: &EmailConfig) -> Result<()> {
+    let host = config
+        .smtp_host
+        .as_deref()
+        .context("No smtp_host in the [email] config")?;
+    let from = config
+        .from
+        .as_deref()
+        .context("No from address in the [email] config")?;
+    let to = config
+        .to
+        .as_deref()
+        .context("No to address in the [email] config")?;
+
+    let message = Message::builder()
+        .from(from.parse().context("Invalid from address")?)
+        .to(to.parse().context("Invalid to address")?)
+        .subject(if digest.due_soon.is_empty() {
+            format!(
+                "td: {} overdue, {} due today",
+                digest.overdue.len(),
+                digest.due_today.len()
+            )
+        } else {
+            format!("td: {} due soon", digest.due_soon.len())
+        })
+        .multipart(MultiPart::alternative_plain_html(
+            digest.to_text(),
+            digest.to_html(),
+        ))?;
+
+    let mut transport = match config.security {
+        SmtpSecurity::Starttls => SmtpTransport::starttls_relay(host)?,
+        SmtpSecurity::Tls => SmtpTransport::relay(host)?,
+        SmtpSecurity::None => SmtpTransport::builder_dangerous(host),
+    };
+    if let Some(port) = config.smtp_port {
+        transport = transport.port(port);
+    }
+    if let Some(username) = &config.username {
+        let password = config
+            .password
+            .clone()
+            .or_else(|| env::var("TD_SMTP_PASSWORD").ok())
+            .unwrap_or_default();
+        transport = transport.credentials(Credentials::new(username.clone(), password));
+    }
+    transport
+        .build()
+        .send(&message)
+        .with_context(|| format!("Failed to send the reminder through {}", host))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod remind_tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn digest() -> Result<()> {
+        let now = Local::now();
+        let mut list = TodoList::new();
+        list.add_item(
+            "late <b>".to_string(),
+            Some(now - Duration::minutes(1)),
+            None,
+        )?;
+        list.add_item("next week".to_string(), Some(now + Duration::days(7)), None)?;
+        list.add_item("no deadline".to_string(), None, None)?;
+        let digest = Digest::build(&list, now);
+        assert_eq!(digest.overdue, [("0".to_string(), "late <b>".to_string())]);
+        assert!(digest.due_today.is_empty());
+        assert_eq!(digest.to_text(), "Overdue:\n  #0: late <b>\n");
+        assert!(digest.to_html().contains("<code>#0</code> late &lt;b&gt;"));
+        assert_eq!(
+            digest.to_chat(ChatService::Slack),
+            "*Overdue*\n• `#0` late &lt;b&gt;\n"
+        );
+        assert_eq!(
+            digest.to_chat(ChatService::Discord),
+            "**Overdue**\n• `#0` late <b>\n"
+        );
+
+        list.complete_item(&"2".to_string())?;
+        let digest = Digest::build(&list, now + Duration::days(1));
+        assert_eq!(
+            digest.completed_yesterday,
+            [("2".to_string(), "no deadline".to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn due_soon() -> Result<()> {
+        let now = Local::now();
+        let mut list = TodoList::new();
+        list.add_item("late".to_string(), Some(now - Duration::hours(1)), None)?;
+        list.add_item("soon".to_string(), Some(now + Duration::minutes(10)), None)?;
+        list.add_item("later".to_string(), Some(now + Duration::hours(2)), None)?;
+        let mut state = ReminderState::default();
+        let lead = Duration::minutes(15);
+        let digest = state.due_soon(&list, now, lead, Duration::zero());
+        assert_eq!(digest.due_soon, [("1".to_string(), "soon".to_string())]);
+        assert!(state
+            .due_soon(&list, now, lead, Duration::zero())
+            .is_empty());
+
+        let later = now + Duration::hours(2);
+        let digest = state.due_soon(&list, later, lead, Duration::zero());
+        assert_eq!(digest.due_soon, [("2".to_string(), "later".to_string())]);
+        assert_eq!(state.fired.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn item_reminders() -> Result<()> {
+        let now = Local::now();
+        let deadline = now + Duration::hours(20);
+        let mut list = TodoList::new();
+        let item = list.add_item("trip".to_string(), Some(deadline), None)?;
+        item.remind = vec!["1d".to_string(), "2h".to_string()];
+        let mut state = ReminderState::default();
+        let lead = Duration::minutes(15);
+        assert_eq!(
+            state
+                .due_soon(&list, now, lead, Duration::zero())
+                .due_soon
+                .len(),
+            1
+        );
+        assert!(state
+            .due_soon(&list, now, lead, Duration::zero())
+            .is_empty());
+        // the 2h reminder and the lead fire together once, each only once
+        let later = deadline - Duration::minutes(10);
+        assert_eq!(
+            state
+                .due_soon(&list, later, lead, Duration::zero())
+                .due_soon
+                .len(),
+            1
+        );
+        assert!(state
+            .due_soon(&list, later, lead, Duration::zero())
+            .is_empty());
+        assert_eq!(state.fired.len(), 3);
+
+        assert!(parse_reminder("soon").is_err());
+        assert!(parse_reminder("0m").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn quiet_hours_and_throttle() -> Result<()> {
+        let night: QuietHours = "22:00-08:00".parse()?;
+        let at = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+        assert!(night.contains(at(23)) && night.contains(at(7)));
+        assert!(!night.contains(at(8)) && !night.contains(at(12)));
+        let lunch: QuietHours = "12pm-1pm".parse()?;
+        assert!(lunch.contains(at(12)) && !lunch.contains(at(13)));
+        assert!("22:00".parse::<QuietHours>().is_err());
+
+        let now = Local::now();
+        let deadline = now + Duration::hours(2);
+        let mut list = TodoList::new();
+        let item = list.add_item("call".to_string(), Some(deadline), None)?;
+        item.remind = vec!["2h".to_string(), "1h".to_string()];
+        let mut state = ReminderState::default();
+        let (lead, throttle) = (Duration::minutes(15), Duration::minutes(90));
+        assert_eq!(state.due_soon(&list, now, lead, throttle).due_soon.len(), 1);
+        // within the throttle the 1h reminder is skipped, the lead isn't
+        let later = deadline - Duration::hours(1);
+        assert!(state.due_soon(&list, later, lead, throttle).is_empty());
+        let last = deadline - Duration::minutes(10);
+        assert_eq!(
+            state.due_soon(&list, last, lead, throttle).due_soon.len(),
+            1
+        );
+        Ok(())
+    }
+}
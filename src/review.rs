@@ -0,0 +1,98 @@
+use crate::config::DatesConfig;
+use crate::todo_list::TodoList;
+use crate::utils::{format_path, parse_deadline};
+use anyhow::Result;
+use chrono::{Duration, Local};
+use std::io::{self, BufRead, Write};
+
+/// Reads one trimmed line from stdin after printing a prompt, None on EOF
+fn prompt(message: &str) -> Result<Option<String>> {
+    print!("{}", message);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+/// Interactively walks through overdue, stale and someday items
+///
+/// Items not modified within `stale_days` days are considered stale. Each item can be
+/// rescheduled, completed, delegated, kept (marked as reviewed), dropped, or skipped
+pub fn run_review(todo_list: &mut TodoList, stale_days: i64, dates: &DatesConfig) -> Result<()> {
+    let now = Local::now();
+    let stale_before = now - Duration::days(stale_days);
+    let mut queue: Vec<(&str, Vec<usize>)> = Vec::new();
+    todo_list.walk(&mut |path, item| {
+        if item.is_overdue(now) {
+            queue.push(("overdue", path.to_vec()));
+        } else if !item.is_completed()
+            && item.modified_time().is_none_or(|time| time < stale_before)
+        {
+            queue.push(("stale", path.to_vec()));
+        }
+    });
+    queue.sort_by_key(|(reason, _)| *reason != "overdue");
+
+    if queue.is_empty() {
+        println!("Nothing to review.");
+        return Ok(());
+    }
+
+    let mut dropped = Vec::new();
+    for (position, (reason, path)) in queue.iter().enumerate() {
+        let item = todo_list.parse_path(&format_path(path))?;
+        println!("[{}/{}] ({}) {}", position + 1, queue.len(), reason, item);
+        loop {
+            let Some(answer) =
+                prompt("[r]eschedule, [c]omplete, [w]ait on, [k]eep, [d]rop, [s]kip, [q]uit? ")?
+            else {
+                return finish(todo_list, dropped);
+            };
+            match answer.as_str() {
+                "r" => {
+                    let Some(deadline) = prompt("New deadline: ")? else {
+                        return finish(todo_list, dropped);
+                    };
+                    match parse_deadline(Some(deadline), dates) {
+                        Ok(deadline) => {
+                            item.set_deadline(Some(deadline));
+                            item.touch();
+                        }
+                        Err(err) => {
+                            println!("{}", err);
+                            continue;
+                        }
+                    }
+                }
+                "c" => {
+                    todo_list.complete_item(&format_path(path))?;
+                }
+                "w" => {
+                    let Some(who) = prompt("Waiting on: ")? else {
+                        return finish(todo_list, dropped);
+                    };
+                    item.waiting_on = Some(who).filter(|who| !who.is_empty());
+                    item.touch();
+                }
+                "k" => item.touch(),
+                "d" => dropped.push(format_path(path)),
+                "s" | "" => {}
+                "q" => return finish(todo_list, dropped),
+                _ => continue,
+            }
+            break;
+        }
+    }
+    finish(todo_list, dropped)
+}
+
+/// Moves dropped items to the trash once the review is over
+fn finish(todo_list: &mut TodoList, dropped: Vec<String>) -> Result<()> {
+    for path in dropped {
+        let entry = todo_list.trash_item(&path)?;
+        println!("Dropped todo item #{}: {}", path, entry.item.description);
+    }
+    Ok(())
+}
@@ -0,0 +1,227 @@
+use crate::config::S3Config;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::env;
+
+/// An object in an S3-compatible bucket
+///
+/// Credentials are read from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and the
+/// optional `AWS_SESSION_TOKEN` whenever a request is made
+#[derive(Debug)]
+pub struct Object {
+    pub bucket: String,
+    pub key: String,
+    region: String,
+    /// Host requests go to
+    host: String,
+    /// Scheme and host, e.g. `https://examplebucket.s3.amazonaws.com`
+    base_url: String,
+    /// Url-encoded path of the object on `host`
+    uri: String,
+}
+
+/// Percent-encodes everything but unreserved characters, and `/` unless `encode_slash`
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Computes an AWS Signature Version 4 for an S3 request
+///
+/// `headers` are the signed headers with lowercase names, sorted by name
+#[allow(clippy::too_many_arguments)]
+fn signature(
+    method: &str,
+    uri: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+    amz_date: &str,
+    region: &str,
+    secret: &str,
+) -> String {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = signed_header_names(headers);
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method, uri, canonical_headers, signed_headers, payload_hash
+    );
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let mut key = hmac(format!("AWS4{}", secret).as_bytes(), date);
+    for part in [region, "s3", "aws4_request"] {
+        key = hmac(&key, part);
+    }
+    hex(&hmac(&key, &string_to_sign))
+}
+
+fn signed_header_names(headers: &[(&str, &str)]) -> String {
+    headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+impl Object {
+    /// Parses `s3://bucket/key`, a bare `s3://` naming the configured bucket and key
+    pub fn parse(url: &str, config: &S3Config) -> Result<Self> {
+        let rest = url.trim_start_matches("s3://");
+        let (bucket, key) = match rest.split_once('/') {
+            Some((bucket, key)) => (bucket.to_string(), key.to_string()),
+            None if rest.is_empty() => (
+                config
+                    .bucket
+                    .clone()
+                    .context("No bucket in url or s3.bucket config")?,
+                config
+                    .key
+                    .clone()
+                    .unwrap_or_else(|| "todo.json".to_string()),
+            ),
+            None => (rest.to_string(), "todo.json".to_string()),
+        };
+        if bucket.is_empty() || key.is_empty() {
+            return Err(anyhow::anyhow!("Invalid S3 object '{}'", url));
+        }
+        let region = config
+            .region
+            .clone()
+            .or_else(|| env::var("AWS_REGION").ok())
+            .or_else(|| env::var("AWS_DEFAULT_REGION").ok())
+            .unwrap_or_else(|| "us-east-1".to_string());
+
+        // custom endpoints (MinIO, R2, ...) use path-style addressing
+        let (base_url, uri) = match &config.endpoint {
+            Some(endpoint) => (
+                endpoint.trim_end_matches('/').to_string(),
+                format!("/{}/{}", uri_encode(&bucket, true), uri_encode(&key, false)),
+            ),
+            None => (
+                format!("https://{}.s3.{}.amazonaws.com", bucket, region),
+                format!("/{}", uri_encode(&key, false)),
+            ),
+        };
+        let host = base_url
+            .split_once("://")
+            .map_or(base_url.as_str(), |(_, host)| host)
+            .to_string();
+        Ok(Self {
+            bucket,
+            key,
+            region,
+            host,
+            base_url,
+            uri,
+        })
+    }
+
+    /// Builds a signed request for the object, `headers` being sent unsigned
+    pub fn request(
+        &self,
+        method: &str,
+        body: &[u8],
+        headers: &[(&str, &str)],
+    ) -> Result<ureq::Request> {
+        let access_key = env::var("AWS_ACCESS_KEY_ID").context("AWS_ACCESS_KEY_ID not set")?;
+        let secret = env::var("AWS_SECRET_ACCESS_KEY").context("AWS_SECRET_ACCESS_KEY not set")?;
+        let token = env::var("AWS_SESSION_TOKEN").ok();
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex(&Sha256::digest(body));
+
+        let mut signed = vec![
+            ("host", self.host.as_str()),
+            ("x-amz-content-sha256", payload_hash.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        if let Some(token) = &token {
+            signed.push(("x-amz-security-token", token.as_str()));
+        }
+        let signature = signature(
+            method,
+            &self.uri,
+            &signed,
+            &payload_hash,
+            &amz_date,
+            &self.region,
+            &secret,
+        );
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}/{}/s3/aws4_request, SignedHeaders={}, Signature={}",
+            access_key,
+            &amz_date[..8],
+            self.region,
+            signed_header_names(&signed),
+            signature
+        );
+
+        let mut request = ureq::request(method, &format!("{}{}", self.base_url, self.uri))
+            .set("Authorization", &authorization);
+        for (name, value) in signed.iter().filter(|(name, _)| *name != "host") {
+            request = request.set(name, value);
+        }
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod s3_tests {
+    use super::*;
+
+    #[test]
+    fn signature_v4() {
+        // the "GET Object" example of the AWS Signature Version 4 documentation
+        let empty = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let headers = [
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("range", "bytes=0-9"),
+            ("x-amz-content-sha256", empty),
+            ("x-amz-date", "20130524T000000Z"),
+        ];
+        assert_eq!(
+            signature(
+                "GET",
+                "/test.txt",
+                &headers,
+                empty,
+                "20130524T000000Z",
+                "us-east-1",
+                "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY"
+            ),
+            "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41"
+        );
+        assert_eq!(uri_encode("a b/c+d", false), "a%20b/c%2Bd");
+    }
+}
@@ -0,0 +1,239 @@
+use crate::todo_list::TodoList;
+use anyhow::{Context, Result};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Comment markers picked up, with the tag their items get
+const MARKERS: [(&str, &str); 2] = [("TODO:", "todo"), ("FIXME:", "fixme")];
+
+/// Endings of the text before a marker that make it a comment, like `// TODO:`
+const COMMENT_LEADERS: [&str; 6] = ["//", "#", "*", "--", ";", "%"];
+
+/// A TODO or FIXME comment found in a file
+#[derive(Debug, PartialEq)]
+struct Found {
+    /// `path:line`, the path relative to the scanned directory
+    location: String,
+    tag: &'static str,
+    text: String,
+}
+
+/// Returns the files to scan, those git tracks if `dir` is in a repository,
+/// otherwise every file outside hidden directories
+fn source_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["ls-files", "-z"])
+        .output();
+    if let Some(output) = output.ok().filter(|output| output.status.success()) {
+        return Ok(String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|file| !file.is_empty())
+            .map(PathBuf::from)
+            .collect());
+    }
+    let mut files = Vec::new();
+    walk(dir, Path::new(""), &mut files)?;
+    Ok(files)
+}
+
+/// Collects the files below `dir`, relative to the directory scanned
+fn walk(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            walk(&entry.path(), &relative.join(&name), files)?;
+        } else {
+            files.push(relative.join(&name));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the text of a TODO or FIXME comment on a line, with its tag
+///
+/// The marker has to follow a comment leader, so strings mentioning it don't count
+fn parse_comment(line: &str) -> Option<(&'static str, String)> {
+    MARKERS.iter().find_map(|(marker, tag)| {
+        let start = line.find(marker)?;
+        let before = line[..start].trim_end();
+        if !COMMENT_LEADERS
+            .iter()
+            .any(|leader| before.ends_with(leader))
+        {
+            return None;
+        }
+        let text = &line[start + marker.len()..];
+        let text = text
+            .trim()
+            .trim_end_matches("*/")
+            .trim_end_matches("-->")
+            .trim();
+        (!text.is_empty()).then(|| (*tag, text.to_string()))
+    })
+}
+
+/// Finds the TODO and FIXME comments of the files below `dir`, skipping files
+/// that aren't text
+fn find_comments(dir: &Path) -> Result<Vec<Found>> {
+    let mut files = source_files(dir)?;
+    files.sort();
+    let mut found = Vec::new();
+    for file in files {
+        let Ok(content) = fs::read_to_string(dir.join(&file)) else {
+            continue;
+        };
+        let file = file.to_string_lossy().replace('\\', "/");
+        for (index, line) in content.lines().enumerate() {
+            if let Some((tag, text)) = parse_comment(line) {
+                found.push(Found {
+                    location: format!("{}:{}", file, index + 1),
+                    tag,
+                    text,
+                });
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Returns the `path:line` tag of an item imported by a scan
+fn location_tag(tags: &[String]) -> Option<&String> {
+    if !tags
+        .iter()
+        .any(|tag| MARKERS.iter().any(|(_, marker)| tag == marker))
+    {
+        return None;
+    }
+    tags.iter().find(|tag| {
+        tag.rsplit_once(':')
+            .is_some_and(|(file, line)| !file.is_empty() && line.parse::<usize>().is_ok())
+    })
+}
+
+/// Counts of what a scan changed
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub added: usize,
+    pub moved: usize,
+    pub completed: usize,
+}
+
+/// Imports the TODO and FIXME comments below `dir` as items tagged `todo` or
+/// `fixme` and with their `path:line`
+///
+/// Comments are recognised again by file and text, so an item whose comment
+/// moved only gets its line updated. With `complete`, pending items whose
+/// comment is gone are completed
+pub fn scan(todo_list: &mut TodoList, dir: &Path, complete: bool) -> Result<ScanReport> {
+    let mut report = ScanReport::default();
+    let mut found = find_comments(dir)?;
+
+    // pending imported items by file and text, matched to comments in order
+    let mut known: HashMap<(String, String), Vec<String>> = HashMap::new();
+    todo_list.walk(&mut |_, item| {
+        if let Some(location) = location_tag(&item.tags).filter(|_| !item.is_completed()) {
+            let (file, _) = location.rsplit_once(':').unwrap_or_default();
+            known
+                .entry((file.to_string(), item.description.clone()))
+                .or_default()
+                .push(item.uuid.clone());
+        }
+    });
+
+    let mut locations = HashMap::new();
+    found.retain(|comment| {
+        let (file, _) = comment.location.rsplit_once(':').unwrap_or_default();
+        let uuids = known.get_mut(&(file.to_string(), comment.text.clone()));
+        match uuids.filter(|uuids| !uuids.is_empty()) {
+            Some(uuids) => {
+                locations.insert(uuids.remove(0), comment.location.clone());
+                false
+            }
+            None => true,
+        }
+    });
+
+    todo_list.walk_mut(&mut |item| {
+        let Some(old) = location_tag(&item.tags).cloned() else {
+            return;
+        };
+        match locations.get(&item.uuid) {
+            Some(location) if *location != old => {
+                for tag in item.tags.iter_mut().filter(|tag| **tag == old) {
+                    *tag = location.clone();
+                }
+                item.touch();
+                report.moved += 1;
+            }
+            Some(_) => {}
+            None if complete && !item.is_completed() => {
+                item.complete();
+                report.completed += 1;
+            }
+            None => {}
+        }
+    });
+
+    for comment in found {
+        let item = todo_list.add_item(comment.text, None, None)?;
+        item.tags = vec![comment.tag.to_string(), comment.location];
+        report.added += 1;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod scan_tests {
+    use super::*;
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            parse_comment("    // TODO: handle errors"),
+            Some(("todo", "handle errors".to_string()))
+        );
+        assert_eq!(
+            parse_comment("/* FIXME: leaks */"),
+            Some(("fixme", "leaks".to_string()))
+        );
+        assert_eq!(parse_comment("// TODO:"), None);
+        assert_eq!(
+            parse_comment("<!-- TODO: docs -->"),
+            Some(("todo", "docs".to_string()))
+        );
+        assert_eq!(parse_comment("let todo = 1;"), None);
+        assert_eq!(parse_comment("println!(\"TODO: {}\", x);"), None);
+    }
+
+    #[test]
+    fn rescan() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        fs::create_dir_all(dir.join("src"))?;
+        let file = dir.join("src").join("main.rs");
+        fs::write(&file, "// TODO: first\nfn main() {} // FIXME: second\n")?;
+
+        let mut list = TodoList::new();
+        let report = scan(&mut list, dir, false)?;
+        assert_eq!(report.added, 2);
+        assert_eq!(list.items[1].tags, ["fixme", "src/main.rs:2"]);
+
+        fs::write(&file, "\n// TODO: first\n")?;
+        let report = scan(&mut list, dir, true)?;
+        assert_eq!((report.added, report.moved, report.completed), (0, 1, 1));
+        assert_eq!(list.items[0].tags, ["todo", "src/main.rs:2"]);
+        assert!(list.items[1].is_completed());
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+use crate::config::Config;
+use crate::errors;
+use crate::session::Session;
+use crate::todo_list::TodoList;
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::{SocketAddr, ToSocketAddrs};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// The single-page UI served by `td serve --web`
+const INDEX_HTML: &str = include_str!("../assets/index.html");
+
+/// Body of `POST /api/items`
+#[derive(Deserialize)]
+struct NewItem {
+    description: String,
+    #[serde(default)]
+    parent: Option<String>,
+}
+
+/// Body of `POST /api/items/<path>/move`
+#[derive(Deserialize)]
+struct Reorder {
+    index: usize,
+}
+
+/// An error answered with an http status and a JSON message
+struct ApiError(u16, String);
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        ApiError(400, error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(error: serde_json::Error) -> Self {
+        ApiError(400, format!("Invalid request body: {}", error))
+    }
+}
+
+fn json_response(status: u16, body: &Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").expect("valid header"))
+}
+
+/// Serves the todo file over http until interrupted
+///
+/// The list is read from disk for every request and changes are saved like the
+/// command line saves them, so it can be used alongside. With `web` the UI is
+/// served at `/`. API requests must carry the configured token, or a token made
+/// up for this run that is printed; addresses beyond loopback are refused
+/// without a configured token
+pub fn serve(file: &str, config: &Config, addr: &str, web: bool) -> Result<()> {
+    let token = config.serve.token.as_deref();
+    if token.is_none() && !is_loopback(addr)? {
+        return Err(errors::refused(format!(
+            "Refusing to serve on {} without a token, set `token` in the [serve] config",
+            addr
+        )));
+    }
+    let server = Server::http(addr)
+        .map_err(|error| anyhow::anyhow!("Cannot listen on {}: {}", addr, error))?;
+    let session_token = uuid::Uuid::new_v4().simple().to_string();
+    let token = token.unwrap_or(&session_token);
+    if web {
+        println!("Serving {} on http://{}/#token={}", file, addr, token);
+    } else {
+        println!("Serving {} on http://{}/api/items", file, addr);
+        if token == session_token {
+            println!("Send the header \"Authorization: Bearer {}\"", token);
+        }
+    }
+    for mut request in server.incoming_requests() {
+        let response = match handle(&mut request, file, config, web, token) {
+            Ok(response) => response,
+            Err(ApiError(status, message)) => json_response(status, &json!({ "error": message })),
+        };
+        // a client that went away doesn't stop the server
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+/// Returns true if every address `addr` resolves to is a loopback address
+fn is_loopback(addr: &str) -> Result<bool> {
+    let addrs: Vec<SocketAddr> = addr
+        .to_socket_addrs()
+        .map_err(|error| errors::invalid(format!("Invalid address {}: {}", addr, error)))?
+        .collect();
+    Ok(!addrs.is_empty() && addrs.iter().all(|addr| addr.ip().is_loopback()))
+}
+
+/// Refuses requests without the token, and requests other than GET that aren't
+/// JSON, so other web pages can't change the list through the browser
+fn authorize(
+    method: &Method,
+    authorization: Option<&str>,
+    content_type: Option<&str>,
+    token: &str,
+) -> Result<(), ApiError> {
+    if authorization.and_then(|value| value.strip_prefix("Bearer ")) != Some(token) {
+        return Err(ApiError(
+            401,
+            "Missing or wrong token, send the one td serve printed as \"Authorization: Bearer <token>\"".to_string(),
+        ));
+    }
+    let is_json = content_type
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("application/json"));
+    if *method != Method::Get && !is_json {
+        return Err(ApiError(
+            415,
+            "Requests must be sent as application/json".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Answers one request
+fn handle(
+    request: &mut Request,
+    file: &str,
+    config: &Config,
+    web: bool,
+    token: &str,
+) -> Result<Response<std::io::Cursor<Vec<u8>>>, ApiError> {
+    let url = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let segments: Vec<&str> = url.trim_matches('/').split('/').collect();
+    let method = request.method().clone();
+
+    if web && method == Method::Get && url == "/" {
+        return Ok(Response::from_string(INDEX_HTML).with_header(
+            Header::from_bytes("Content-Type", "text/html; charset=utf-8").expect("valid header"),
+        ));
+    }
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str())
+    };
+    authorize(
+        &method,
+        header("Authorization"),
+        header("Content-Type"),
+        token,
+    )?;
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|error| ApiError(400, error.to_string()))?;
+
+    let response = handle_api(&method, &segments, &body, file, config)?;
+    Ok(json_response(200, &response))
+}
+
+/// Answers an API request for `file` with the JSON to send back
+fn handle_api(
+    method: &Method,
+    segments: &[&str],
+    body: &str,
+    file: &str,
+    config: &Config,
+) -> Result<Value, ApiError> {
+    let mut session = Session::open(file, config)?;
+    let mut todo_list = session.load()?;
+    let response = match (method, segments) {
+        (Method::Get, ["api", "items"]) => return Ok(items_json(&todo_list)),
+        (Method::Post, ["api", "items"]) => {
+            let new: NewItem = serde_json::from_str(body)?;
+            let item = todo_list.add_item(new.description, None, new.parent.as_ref())?;
+            json!({ "uuid": item.uuid })
+        }
+        (Method::Post, ["api", "items", path, "complete"]) => {
+            todo_list.complete_item(&path.to_string())?;
+            json!({})
+        }
+        (Method::Post, ["api", "items", path, "reopen"]) => {
+            let item = todo_list.parse_path(&path.to_string())?;
+            item.reopen();
+            item.touch();
+            json!({})
+        }
+        (Method::Post, ["api", "items", path, "move"]) => {
+            let reorder: Reorder = serde_json::from_str(body)?;
+            todo_list.reorder_item(&path.to_string(), reorder.index)?;
+            json!({})
+        }
+        (Method::Delete, ["api", "items", path]) => {
+            let entry = todo_list.trash_item(path)?;
+            json!({ "trash_id": entry.id })
+        }
+        _ => {
+            return Err(ApiError(
+                404,
+                format!("No route for {} /{}", method, segments.join("/")),
+            ))
+        }
+    };
+    session.save(
+        &mut todo_list,
+        &format!("serve {} /{}", method, segments.join("/")),
+    )?;
+    Ok(response)
+}
+
+/// Serializes the items of a list as a tree carrying each item's ID path
+fn items_json(todo_list: &TodoList) -> Value {
+    fn list_json(list: &TodoList, prefix: &str) -> Value {
+        list.items
+            .iter()
+            .map(|item| {
+                let path = format!("{}{}", prefix, item.id);
+                let children = item.sub_list.as_ref().map_or(json!([]), |sub_list| {
+                    list_json(sub_list, &format!("{}:", path))
+                });
+                json!({
+                    "path": path,
+                    "uuid": item.uuid,
+                    "description": item.description,
+                    "status": item.status,
+                    "deadline": item.deadline,
+                    "items": children,
+                })
+            })
+            .collect()
+    }
+    list_json(todo_list, "")
+}
+
+#[cfg(test)]
+mod server_tests {
+    use super::*;
+
+    #[test]
+    fn guards() {
+        let json = Some("application/json; charset=utf-8");
+        let bearer = Some("Bearer secret");
+        assert!(authorize(&Method::Post, bearer, json, "secret").is_ok());
+        assert!(authorize(&Method::Get, bearer, None, "secret").is_ok());
+        // a form or fetch from another page can't set the token or JSON without CORS
+        let status = |result: Result<(), ApiError>| result.err().map(|ApiError(status, _)| status);
+        assert_eq!(
+            status(authorize(&Method::Post, None, json, "secret")),
+            Some(401)
+        );
+        assert_eq!(
+            status(authorize(
+                &Method::Post,
+                Some("Bearer other"),
+                json,
+                "secret"
+            )),
+            Some(401)
+        );
+        assert_eq!(
+            status(authorize(
+                &Method::Post,
+                bearer,
+                Some("text/plain"),
+                "secret"
+            )),
+            Some(415)
+        );
+        assert_eq!(
+            status(authorize(&Method::Delete, bearer, None, "secret")),
+            Some(415)
+        );
+
+        assert!(is_loopback("127.0.0.1:7878").unwrap());
+        assert!(is_loopback("[::1]:7878").unwrap());
+        assert!(!is_loopback("0.0.0.0:7878").unwrap());
+    }
+
+    #[test]
+    fn completing_fires_webhook() -> Result<()> {
+        let receiver = Server::http("127.0.0.1:0").map_err(|error| anyhow::anyhow!(error))?;
+        let port = receiver.server_addr().to_ip().expect("tcp listener").port();
+        let listener = std::thread::spawn(move || {
+            let mut request = receiver.recv().expect("webhook request");
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let _ = request.respond(Response::empty(204));
+            body
+        });
+
+        let temp = tempfile::tempdir()?;
+        let file = temp.path().join("todo.json").display().to_string();
+        let config = Config {
+            webhooks: vec![crate::config::WebhookConfig {
+                url: format!("http://127.0.0.1:{}/", port),
+                events: vec![crate::events::Event::Complete],
+            }],
+            ..Default::default()
+        };
+        let new = json!({ "description": "call back" }).to_string();
+        assert!(handle_api(&Method::Post, &["api", "items"], &new, &file, &config).is_ok());
+        assert!(handle_api(
+            &Method::Post,
+            &["api", "items", "0", "complete"],
+            "",
+            &file,
+            &config,
+        )
+        .is_ok());
+
+        let payload: Value = serde_json::from_str(&listener.join().unwrap())?;
+        assert_eq!(payload["event"], "complete");
+        assert_eq!(payload["item"]["description"], "call back");
+        Ok(())
+    }
+}
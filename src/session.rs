@@ -0,0 +1,251 @@
+use crate::config::Config;
+use crate::errors;
+use crate::events;
+use crate::hooks::Hooks;
+use crate::journal;
+use crate::remote::Remote;
+use crate::sync;
+use crate::todo_list::TodoList;
+use crate::utils::{
+    archive_items, expand_path, load_or_recover, parse_duration, read_todo_list, save_todo_list,
+};
+use crate::webhook;
+use anyhow::Result;
+use chrono::Local;
+use std::path::{Path, PathBuf};
+
+/// A todo file opened for a change, shared by the commands, `td serve` and `td mcp`
+/// so every change goes through the same hooks, webhooks, archiving and saving
+pub struct Session<'a> {
+    /// The file the list is read from and saved to, the local copy of a remote list
+    pub file_path: PathBuf,
+    pub remote: Option<Remote>,
+    config: &'a Config,
+    hooks: Option<Hooks>,
+    /// The list as loaded, kept when hooks or webhooks need to know what changed
+    loaded: Option<TodoList>,
+}
+
+impl<'a> Session<'a> {
+    /// Opens `file`, a path or a remote url that is fetched first
+    pub fn open(file: &str, config: &'a Config) -> Result<Self> {
+        let mut remote = Remote::parse(file, &config.s3)?;
+        let file_path = match &mut remote {
+            Some(remote) => {
+                remote.fetch()?;
+                remote.cache_path().clone()
+            }
+            None => expand_path(&file.to_string())?,
+        };
+        Ok(Session {
+            file_path,
+            remote,
+            config,
+            hooks: Hooks::load()?,
+            loaded: None,
+        })
+    }
+
+    /// Loads the list, recovering from a damaged file and merging sync conflicts
+    pub fn load(&mut self) -> Result<TodoList> {
+        let mut todo_list = load_or_recover(&self.file_path, &self.config.storage)?;
+        sync::resolve_conflicts(&self.file_path, &mut todo_list, &self.config.storage)?;
+        self.loaded =
+            (!self.config.webhooks.is_empty() || self.hooks.is_some()).then(|| todo_list.clone());
+        if self.config.ids.monotonic {
+            todo_list.set_monotonic_ids(true);
+        }
+        Ok(todo_list)
+    }
+
+    /// Saves the changes made to `todo_list` since it was loaded, if any
+    ///
+    /// A remote list is pushed back, a journaled one gets `command` appended
+    pub fn save(&self, todo_list: &mut TodoList, command: &str) -> Result<()> {
+        let config = self.config;
+        let file_path = &self.file_path;
+        save_changes(
+            todo_list,
+            self.loaded.as_ref(),
+            self.hooks.as_ref(),
+            file_path,
+            config,
+            |todo_list| {
+                if let Some(remote) = &self.remote {
+                    remote.check_writable()?;
+                    save_todo_list(file_path, todo_list, &config.storage)?;
+                    remote.push()
+                } else if config.storage.journal {
+                    journal::append(file_path, todo_list, &config.storage, command)
+                } else {
+                    save_todo_list(file_path, todo_list, &config.storage)
+                }
+            },
+        )
+    }
+}
+
+/// Reads the list at `file` without fetching, recovering, merging conflicts or
+/// writing anything, for commands run from shell prompts
+///
+/// A remote list is read from the copy fetched last
+pub fn peek(file: &str, config: &Config) -> Result<TodoList> {
+    let file_path = match Remote::parse(file, &config.s3)? {
+        Some(remote) => remote.cache_path().clone(),
+        None => expand_path(&file.to_string())?,
+    };
+    read_todo_list(&file_path)
+}
+
+/// Runs the hooks on the changes made to the list, then saves it with `save` and
+/// tells the webhooks about the changes
+///
+/// Nothing leaves td before the pre-save hook could veto: completed items are
+/// archived after it, and webhooks only fire once the list is saved. Items that
+/// became overdue are reported along, commands that only read the list skip it
+fn save_changes(
+    todo_list: &mut TodoList,
+    loaded: Option<&TodoList>,
+    hooks: Option<&Hooks>,
+    file_path: &Path,
+    config: &Config,
+    save: impl FnOnce(&TodoList) -> Result<()>,
+) -> Result<()> {
+    let mut changes = Vec::new();
+    if let Some(loaded) = loaded {
+        if let Some(hooks) = hooks {
+            hooks.run_item_hooks(loaded, todo_list, file_path)?;
+        }
+        changes = events::changes(loaded, todo_list);
+    }
+    let archived = match &config.auto_archive_after {
+        Some(after) => {
+            let age = parse_duration(after).ok_or_else(|| {
+                errors::invalid(format!("Invalid auto_archive_after duration: {}", after))
+            })?;
+            todo_list.take_completed_before(Local::now() - age)
+        }
+        None => Vec::new(),
+    };
+    if !todo_list.is_dirty() {
+        return Ok(());
+    }
+    if let Some(hooks) = hooks {
+        hooks.run_pre_save(todo_list, file_path)?;
+    }
+    archive_items(file_path, archived, &config.storage)?;
+    save(todo_list)?;
+    webhook::fire(&config.webhooks, file_path, &changes);
+    // the list is saved by now, so a failed check must not fail the command
+    if let Err(error) = webhook::check_overdue(&config.webhooks, file_path, todo_list) {
+        eprintln!("Checking for overdue items failed: {}", error);
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod session_tests {
+    use super::*;
+    use crate::hooks::hooks_tests::{hooks_in, write_hook};
+    use crate::utils::archive_path;
+
+    #[test]
+    fn vetoed_save() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        let file_path = dir.join("todo.json");
+        let hooks = hooks_in(dir);
+        let config = Config {
+            auto_archive_after: Some("1m".to_string()),
+            ..Default::default()
+        };
+        let mut loaded = TodoList::new();
+        loaded.add_item("done long ago".to_string(), None, None)?;
+        loaded.complete_item(&"0".to_string())?;
+        loaded.items[0].completed_at = Some("2020-01-01 00:00:00 +00:00".to_string());
+        let mut todo_list = loaded.clone();
+        todo_list.add_item("new".to_string(), None, None)?;
+
+        // nothing is archived or saved when the pre-save hook refuses
+        write_hook(dir, "pre-save", "exit 1")?;
+        let mut saved = false;
+        let result = save_changes(
+            &mut todo_list,
+            Some(&loaded),
+            Some(&hooks),
+            &file_path,
+            &config,
+            |_| {
+                saved = true;
+                Ok(())
+            },
+        );
+        assert!(result.is_err() && !saved);
+        assert!(!archive_path(&file_path).exists());
+
+        write_hook(dir, "pre-save", "cat")?;
+        let mut todo_list = loaded.clone();
+        todo_list.add_item("new".to_string(), None, None)?;
+        save_changes(
+            &mut todo_list,
+            Some(&loaded),
+            Some(&hooks),
+            &file_path,
+            &config,
+            |_| {
+                saved = true;
+                Ok(())
+            },
+        )?;
+        assert!(saved && archive_path(&file_path).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn peek_writes_nothing() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        let file_path = dir.join("todo.json");
+        let config = Config {
+            storage: crate::config::StorageConfig {
+                journal: true,
+                ..Default::default()
+            },
+            webhooks: vec![crate::config::WebhookConfig {
+                url: "http://127.0.0.1:9/".to_string(),
+                events: Vec::new(),
+            }],
+            ..Default::default()
+        };
+        let mut todo_list = TodoList::new();
+        todo_list.add_item("first".to_string(), None, None)?;
+        save_todo_list(&file_path, &todo_list, &config.storage)?;
+        todo_list.add_item("journaled".to_string(), None, None)?;
+        journal::append(&file_path, &todo_list, &config.storage, "add journaled")?;
+        // a torn journal line and a sync conflict copy that loading would repair
+        let journal_path = journal::journal_path(&file_path);
+        let mut journal = std::fs::read_to_string(&journal_path)?;
+        journal.push_str("{\"torn");
+        std::fs::write(&journal_path, journal)?;
+        std::fs::copy(
+            &file_path,
+            dir.join("todo.sync-conflict-20240101-000000-ABC.json"),
+        )?;
+        let listing = |dir: &Path| -> Result<Vec<(String, Vec<u8>)>> {
+            let mut files = std::fs::read_dir(dir)?
+                .map(|entry| {
+                    let path = entry?.path();
+                    Ok((path.display().to_string(), std::fs::read(&path)?))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            files.sort();
+            Ok(files)
+        };
+        let before = listing(dir)?;
+
+        let todo_list = peek(&file_path.display().to_string(), &config)?;
+        assert_eq!(todo_list.items.len(), 2);
+        assert_eq!(listing(dir)?, before);
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use crate::exchange::ImportReport;
+use crate::todo_list::{Priority, Status, TodoItem, TodoList};
+use crate::utils::{format_path, parse_timestamp};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use std::{collections::HashMap, io, path::Path, str::FromStr};
+
+/// Columns of an exported CSV file, in order
+pub const COLUMNS: [&str; 10] = [
+    "uuid",
+    "path",
+    "description",
+    "status",
+    "priority",
+    "deadline",
+    "tags",
+    "created",
+    "modified",
+    "completed",
+];
+
+/// Format of the times written, read back together with dates alone
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+fn format_time(time: Option<DateTime<Local>>) -> String {
+    time.map(|time| time.format(TIME_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+/// Parses a time as written by the export, as stored by td, or a date alone
+/// meaning the end of that day
+fn parse_time(time: &str) -> Option<DateTime<Local>> {
+    parse_timestamp(time).or_else(|| {
+        let time = NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+            .or_else(|_| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M"))
+            .or_else(|_| {
+                NaiveDate::parse_from_str(time, "%Y-%m-%d")
+                    .map(|date| date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap()))
+            })
+            .ok()?;
+        time.and_local_timezone(Local).earliest()
+    })
+}
+
+/// Writes every item of the list as a row, sub items after their parent
+pub fn export(todo_list: &TodoList, writer: impl io::Write) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(COLUMNS)?;
+    let mut rows = Vec::new();
+    todo_list.walk(&mut |path, item| {
+        let status = match item.status {
+            Status::Todo => "todo",
+            Status::Started => "started",
+            Status::Done => "done",
+        };
+        rows.push([
+            item.uuid.clone(),
+            format_path(path),
+            item.description.clone(),
+            status.to_string(),
+            item.priority
+                .map(|priority| priority.to_string())
+                .unwrap_or_default(),
+            format_time(item.deadline_time()),
+            item.tags.join(" "),
+            format_time(item.created_time()),
+            format_time(item.modified_time()),
+            format_time(item.completed_time().filter(|_| item.is_completed())),
+        ]);
+    });
+    for row in rows {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Copies the editable columns of a row to an item, returning true if it changed
+fn apply_row(item: &mut TodoItem, row: &HashMap<&str, &str>) -> Result<bool> {
+    let before = serde_json::to_value(&*item)?;
+    if let Some(description) = row.get("description").filter(|text| !text.is_empty()) {
+        item.description = description.to_string();
+    }
+    if let Some(priority) = row.get("priority") {
+        item.priority = match *priority {
+            "" => None,
+            priority => Some(Priority::from_str(priority)?),
+        };
+    }
+    if let Some(deadline) = row.get("deadline") {
+        let deadline = match *deadline {
+            "" => None,
+            deadline => Some(
+                parse_time(deadline).with_context(|| format!("Invalid deadline '{}'", deadline))?,
+            ),
+        };
+        // times are exported to the second, keep the stored one if that's all that differs
+        if format_time(deadline) != format_time(item.deadline_time()) {
+            item.set_deadline(deadline);
+        }
+    }
+    if let Some(tags) = row.get("tags") {
+        item.tags = tags.split_whitespace().map(String::from).collect();
+    }
+    match row.get("status").copied() {
+        Some("done") => {
+            item.complete();
+            let completed = row.get("completed").and_then(|time| parse_time(time));
+            if completed.is_some() && format_time(completed) != format_time(item.completed_time()) {
+                item.completed_at = completed.map(|time| time.to_string());
+            }
+        }
+        Some("started") if item.status != Status::Started => item.start(),
+        Some("todo") if item.status != Status::Todo => item.reopen(),
+        Some("todo" | "started" | "") | None => {}
+        Some(status) => return Err(anyhow::anyhow!("Invalid status '{}'", status)),
+    }
+    let changed = serde_json::to_value(&*item)? != before;
+    if changed {
+        item.touch();
+    }
+    Ok(changed)
+}
+
+/// Imports a CSV file with some of the exported columns
+///
+/// Rows whose uuid names an item of the list update it, other rows are added,
+/// below the row their path's parent names if the file has one, else below `under`
+pub fn import(
+    todo_list: &mut TodoList,
+    path: &Path,
+    under: Option<&String>,
+) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let headers = reader.headers()?.clone();
+    if !headers.iter().any(|header| header == "description") {
+        return Err(anyhow::anyhow!(
+            "{} has no description column",
+            path.display()
+        ));
+    }
+    // paths of the file mapped to the paths of the items added for them
+    let mut added: HashMap<String, String> = HashMap::new();
+
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        let row: HashMap<&str, &str> = headers.iter().zip(record.iter()).collect();
+        let context = || format!("Invalid row {} of {}", index + 2, path.display());
+        let existing = row
+            .get("uuid")
+            .and_then(|uuid| todo_list.find_by_uuid(uuid))
+            .map(|(path, _)| path);
+        if let Some(existing) = existing {
+            if apply_row(todo_list.parse_path(&existing)?, &row).with_context(context)? {
+                report.updated += 1;
+            }
+            continue;
+        }
+
+        let file_path = row.get("path").copied().unwrap_or_default();
+        let parent = file_path
+            .rsplit_once(':')
+            .and_then(|(parent, _)| added.get(parent))
+            .or(under)
+            .cloned();
+        let list = todo_list.list_at(parent.as_ref())?;
+        let description = row.get("description").copied().unwrap_or_default();
+        let mut item = TodoItem::new(description.to_string());
+        apply_row(&mut item, &row).with_context(context)?;
+        for (column, field) in [
+            ("created", &mut item.created_at),
+            ("modified", &mut item.modified_at),
+        ] {
+            if let Some(time) = row.get(column).and_then(|time| parse_time(time)) {
+                *field = Some(time.to_string());
+            }
+        }
+        let id = list.push_item(item).id;
+        let new_path = match parent {
+            Some(parent) => format!("{}:{}", parent, id),
+            None => id.to_string(),
+        };
+        if !file_path.is_empty() {
+            added.insert(file_path.to_string(), new_path);
+        }
+        report.added += 1;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod spreadsheet_tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() -> Result<()> {
+        let mut list = TodoList::new();
+        let item = list.add_item("Report, final".to_string(), None, None)?;
+        item.tags = vec!["work".to_string(), "q3".to_string()];
+        item.priority = Some(Priority::High);
+        list.add_item("Draft".to_string(), None, Some(&"0".to_string()))?;
+        list.complete_item(&"0:0".to_string())?;
+
+        let mut out = Vec::new();
+        export(&list, &mut out)?;
+        let csv = String::from_utf8(out)?;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].contains(",0,\"Report, final\",todo,high,,work q3,"));
+        assert!(lines[2].contains(",0:0,Draft,done,"));
+
+        // an edited export updates the list, new rows are added below their parent
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("export.csv");
+        let edited = csv.replace("Report, final", "Final report")
+            + "new,5,Later,todo,,2024-05-01,,,,\nnew,5:0,Sub,started,,,,,,\n";
+        std::fs::write(&path, edited)?;
+        let report = import(&mut list, &path, None)?;
+        assert_eq!((report.added, report.updated), (2, 1));
+        assert_eq!(list.items[0].description, "Final report");
+        assert_eq!(list.items[1].description, "Later");
+        assert!(list.items[1]
+            .deadline
+            .as_ref()
+            .unwrap()
+            .starts_with("2024-05-01T23:59:59"));
+        let sub = &list.items[1].sub_list.as_ref().unwrap().items[0];
+        assert_eq!(
+            (sub.description.as_str(), sub.status),
+            ("Sub", Status::Started)
+        );
+        Ok(())
+    }
+}
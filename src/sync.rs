@@ -0,0 +1,247 @@
+use crate::config::StorageConfig;
+use crate::todo_list::{TodoItem, TodoList};
+use crate::utils::{load_todo_list, parse_timestamp, save_todo_list};
+use anyhow::Result;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// What merging a conflicting copy changed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+/// Returns the conflicting copies file sync tools left next to a todo file
+///
+/// Recognises Syncthing's `todo.sync-conflict-<date>-<time>-<device>.json` and
+/// Dropbox's `todo (<who>'s conflicted copy <date>).json`
+pub fn conflict_copies(file_path: &Path) -> Vec<PathBuf> {
+    let (Some(stem), Some(parent)) = (
+        file_path.file_stem().and_then(|stem| stem.to_str()),
+        file_path.parent(),
+    ) else {
+        return Vec::new();
+    };
+    let extension = file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| format!(".{}", extension))
+        .unwrap_or_default();
+    let dir = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut copies: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let Some(middle) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(stem))
+                .and_then(|rest| rest.strip_suffix(extension.as_str()))
+            else {
+                return false;
+            };
+            middle.starts_with(".sync-conflict-")
+                || (middle.starts_with(" (") && middle.contains("conflicted copy"))
+        })
+        .map(|entry| entry.path())
+        .collect();
+    copies.sort();
+    copies
+}
+
+/// Merges every conflicting copy next to `file_path` into the list, then saves
+/// the result and deletes the copies
+pub fn resolve_conflicts(
+    file_path: &PathBuf,
+    todo_list: &mut TodoList,
+    storage: &StorageConfig,
+) -> Result<()> {
+    let copies = conflict_copies(file_path);
+    if copies.is_empty() {
+        return Ok(());
+    }
+    for copy in &copies {
+        let theirs = load_todo_list(copy)?;
+        let report = merge(todo_list, theirs);
+        eprintln!(
+            "Merged {}: {} added, {} updated, {} removed",
+            copy.display(),
+            report.added,
+            report.updated,
+            report.removed
+        );
+    }
+    save_todo_list(file_path, todo_list, storage)?;
+    todo_list.mark_clean();
+    for copy in &copies {
+        fs::remove_file(copy)?;
+    }
+    Ok(())
+}
+
+/// Merges a concurrently modified copy of a list into `mine`, matching items by uuid
+///
+/// Of two versions of an item the more recently modified one wins, items only
+/// `theirs` has are added with a fresh ID, and items `theirs` trashed after they
+/// were last modified here are moved to the trash
+pub fn merge(mine: &mut TodoList, theirs: TodoList) -> MergeReport {
+    let mut report = MergeReport::default();
+    let mut known: HashSet<String> = mine.iter().map(|(_, item)| item.uuid.clone()).collect();
+    known.extend(
+        mine.trash
+            .entries
+            .iter()
+            .map(|entry| entry.item.uuid.clone()),
+    );
+
+    let TodoList {
+        items,
+        trash,
+        milestones,
+        ..
+    } = theirs;
+    merge_items(mine, items, &known, &mut report);
+
+    for entry in trash.entries {
+        let Some((path, item)) = mine.find_by_uuid(&entry.item.uuid) else {
+            continue;
+        };
+        let removed_later = match (parse_timestamp(&entry.removed_at), item.modified_time()) {
+            (Some(removed), Some(modified)) => removed >= modified,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if removed_later && mine.trash_item(&path).is_ok() {
+            report.removed += 1;
+        }
+    }
+
+    for milestone in milestones.items {
+        if mine.milestones.get(&milestone.name).is_err() {
+            mine.milestones.items.push(milestone);
+        }
+    }
+    report
+}
+
+/// Merges `theirs` into the items of one level of `mine`
+fn merge_items(
+    mine: &mut TodoList,
+    theirs: Vec<TodoItem>,
+    known: &HashSet<String>,
+    report: &mut MergeReport,
+) {
+    for mut their_item in theirs {
+        let their_sub_list = their_item.sub_list.take();
+        let Some(my_item) = mine
+            .items
+            .iter_mut()
+            .find(|item| item.uuid == their_item.uuid)
+        else {
+            if !known.contains(&their_item.uuid) {
+                // sub items are merged separately so their uuids are checked too
+                let added = mine.push_item(their_item);
+                report.added += 1;
+                if let Some(sub_list) = their_sub_list {
+                    let list = added.sub_list.get_or_insert_with(TodoList::new);
+                    merge_items(list, sub_list.items, known, report);
+                }
+            }
+            continue;
+        };
+
+        if their_item.modified_time() > my_item.modified_time() {
+            their_item.id = my_item.id;
+            their_item.sub_list = my_item.sub_list.take();
+            *my_item = their_item;
+            report.updated += 1;
+        }
+        if let Some(sub_list) = their_sub_list {
+            let list = my_item.sub_list.get_or_insert_with(TodoList::new);
+            merge_items(list, sub_list.items, known, report);
+        }
+    }
+}
+
+#[cfg(test)]
+mod sync_tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn merge_copies() -> Result<()> {
+        let mut mine = TodoList::new();
+        mine.add_item("a".to_string(), None, None)?;
+        mine.add_item("b".to_string(), None, None)?;
+        mine.add_item("c".to_string(), None, None)?;
+        let mut theirs = mine.clone();
+
+        mine.add_item("mine".to_string(), None, None)?;
+        mine.edit_item(&"0".to_string(), Some("a (mine)".to_string()), None)?;
+        theirs.edit_item(&"1".to_string(), Some("b (theirs)".to_string()), None)?;
+        theirs.add_item("theirs".to_string(), None, Some(&"1".to_string()))?;
+        theirs.add_item("also theirs".to_string(), None, None)?;
+        theirs.trash_item("2")?;
+
+        let report = merge(&mut mine, theirs);
+        assert_eq!(
+            report,
+            MergeReport {
+                added: 2,
+                updated: 1,
+                removed: 1
+            }
+        );
+        let descriptions: Vec<&str> = mine
+            .items
+            .iter()
+            .map(|item| item.description.as_str())
+            .collect();
+        assert_eq!(
+            descriptions,
+            ["a (mine)", "b (theirs)", "mine", "also theirs"]
+        );
+        assert_eq!(mine.parse_path(&"1:0".to_string())?.description, "theirs");
+        assert_eq!(
+            mine.parse_path(&"4".to_string())?.description,
+            "also theirs"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn detect_copies() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        for name in [
+            "todo.json",
+            "todo.sync-conflict-20240101-120000-ABCDEFG.json",
+            "todo (Alice's conflicted copy 2024-01-01).json",
+            "todo.json.bak",
+            "other.sync-conflict-20240101-120000-ABCDEFG.json",
+        ] {
+            fs::write(dir.join(name), "{}")?;
+        }
+        let copies = conflict_copies(&dir.join("todo.json"));
+        assert_eq!(
+            copies,
+            [
+                dir.join("todo (Alice's conflicted copy 2024-01-01).json"),
+                dir.join("todo.sync-conflict-20240101-120000-ABCDEFG.json"),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Name of the service and timer units `td install-timer` writes
+pub const UNIT_NAME: &str = "td-remind";
+
+/// Quotes an argument of `ExecStart=` if needed, doubling `%` so systemd
+/// doesn't take it for a specifier
+fn quote(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    if arg.is_empty() || arg.contains([' ', '"', '\\', '\'', ';']) {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        arg
+    }
+}
+
+/// Returns the service and timer units running `command` every `interval`
+/// seconds, as `user` for a system unit
+pub fn units(command: &[String], interval: i64, user: Option<&str>) -> (String, String) {
+    let exec = command
+        .iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut service = format!(
+        "[Unit]\nDescription=Remind of td items coming due\n\n[Service]\nType=oneshot\nExecStart={}\n",
+        exec
+    );
+    if let Some(user) = user {
+        service.push_str(&format!("User={}\n", user));
+    }
+    let timer = format!(
+        "[Unit]\nDescription=Run td remind periodically\n\n[Timer]\nOnBootSec=1min\nOnUnitActiveSec={}s\n\n[Install]\nWantedBy=timers.target\n",
+        interval
+    );
+    (service, timer)
+}
+
+/// Returns the directory of user units, or of system units
+pub fn unit_dir(user: bool) -> Result<PathBuf> {
+    if !user {
+        return Ok(PathBuf::from("/etc/systemd/system"));
+    }
+    match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => Ok(PathBuf::from(dir).join("systemd/user")),
+        _ => {
+            let home = env::var("HOME").context("HOME environment variable not set")?;
+            Ok(PathBuf::from(home).join(".config/systemd/user"))
+        }
+    }
+}
+
+/// Writes both units into `dir`, returning the path of the timer
+pub fn write_units(dir: &Path, service: &str, timer: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let service_path = dir.join(format!("{}.service", UNIT_NAME));
+    fs::write(&service_path, service)
+        .with_context(|| format!("Failed to write {}", service_path.display()))?;
+    let timer_path = dir.join(format!("{}.timer", UNIT_NAME));
+    fs::write(&timer_path, timer)
+        .with_context(|| format!("Failed to write {}", timer_path.display()))?;
+    Ok(timer_path)
+}
+
+/// Returns the systemctl invocations that load and start the timer
+pub fn enable_commands(user: bool) -> Vec<Vec<String>> {
+    let timer = format!("{}.timer", UNIT_NAME);
+    [
+        vec!["daemon-reload"],
+        vec!["enable", "--now", timer.as_str()],
+    ]
+    .into_iter()
+    .map(|args| {
+        let mut command = vec!["systemctl".to_string()];
+        if user {
+            command.push("--user".to_string());
+        }
+        command.extend(args.into_iter().map(String::from));
+        command
+    })
+    .collect()
+}
+
+/// Runs `systemctl` to load and start the timer
+pub fn enable(user: bool) -> Result<()> {
+    for command in enable_commands(user) {
+        let status = Command::new(&command[0])
+            .args(&command[1..])
+            .status()
+            .with_context(|| format!("Failed to run {}", command.join(" ")))?;
+        if !status.success() {
+            return Err(anyhow::anyhow!(
+                "{} failed with {}",
+                command.join(" "),
+                status
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod systemd_tests {
+    use super::*;
+
+    #[test]
+    fn timer_units() {
+        let command = [
+            "/usr/bin/td",
+            "-f",
+            "/home/me/My Todo.json",
+            "remind",
+            "--lead",
+        ]
+        .map(String::from);
+        let (service, timer) = units(&command, 900, Some("me"));
+        assert!(
+            service.contains("ExecStart=/usr/bin/td -f \"/home/me/My Todo.json\" remind --lead\n")
+        );
+        assert!(service.ends_with("User=me\n"));
+        assert!(timer.contains("OnUnitActiveSec=900s\n"));
+        assert_eq!(quote("50%"), "50%%");
+    }
+}
@@ -1,43 +1,264 @@
+use crate::errors;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, fmt, sync::atomic::Ordering};
+use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet},
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
 
-use crate::SHOW_COMPLETE;
+use crate::filter::Filter;
+use crate::habit::Habits;
+use crate::milestone::Milestones;
+use crate::recurrence;
+use crate::trash::{Trash, TrashEntry};
+use crate::utils::{format_deadline, format_path, is_url, parse_timestamp, progress_bar};
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Returns a user supplied path with the `:` separators the list works with,
+/// `2.1` naming the same item as `2:1`
+pub fn normalize_path(path: &str) -> String {
+    path.replace('.', ":")
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+/// Progress of a todo item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    #[default]
+    Todo,
+    Started,
+    Done,
+}
+
+/// How `td list` picks, orders and shows items, see `TodoList::list_items`
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    /// List completed items too
+    pub include_completed: bool,
+    /// List only items matching the filter, and the items they belong to
+    pub filter: Option<Filter>,
+    /// Order of the items within each list
+    pub sort: Option<SortKey>,
+    /// Reverse the sort order
+    pub reverse: bool,
+    /// Levels of subtasks shown below the top level items, all of them if None
+    pub depth: Option<usize>,
+    /// Show a completion bar on items with subtasks
+    pub progress: bool,
+    /// Show subtasks without a deadline as due with the item they belong to
+    pub inherit_deadlines: bool,
+}
+
+impl ListOptions {
+    /// Returns true if the item is listed, or one of its subtasks is for a filter
+    fn lists(&self, item: &TodoItem) -> bool {
+        let completed_ok = self.include_completed
+            || self.filter.as_ref().is_some_and(Filter::has_status)
+            || !item.is_completed();
+        if item.someday || !completed_ok {
+            return false;
+        }
+        match &self.filter {
+            None => true,
+            Some(filter) => {
+                filter.matches(item)
+                    || item
+                        .sub_list
+                        .as_ref()
+                        .is_some_and(|sub_list| sub_list.items.iter().any(|sub| self.lists(sub)))
+            }
+        }
+    }
+}
+
+/// Keys `list --sort` can order items by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    /// Earliest deadline first, items without deadline last
+    Deadline,
+    /// Highest priority first
+    Priority,
+    /// Oldest item first
+    Created,
+    /// Alphabetically by description
+    Alpha,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TodoItem {
+    /// Stable identity that survives moves and renumbering, generated for older files on load
+    #[serde(default = "new_uuid")]
+    #[schemars(transform = without_default)]
+    pub uuid: String,
     pub id: usize,
     pub description: String,
-    pub completed: bool,
+    #[serde(default)]
+    pub status: Status,
+    #[serde(default)]
+    pub starred: bool,
     #[serde(default)]
     pub deadline: Option<String>,
     #[serde(default)]
     pub sub_list: Option<TodoList>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+    #[serde(default)]
+    pub waiting_on: Option<String>,
+    #[serde(default)]
+    pub someday: bool,
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub context: Option<String>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub milestone: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checklist: Vec<ChecklistEntry>,
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Urls or file paths, relative paths being inside the configured attachment directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<String>,
+    /// Uuids of related items, kept symmetric by `link_items`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<Comment>,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    /// Number of the GitHub issue `td sync github` keeps in step with this item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_issue: Option<u64>,
+    /// Note `td sync markdown` found this item in, relative to the synced directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub markdown_file: Option<String>,
+    /// UID of the calendar event or todo `td import --format ics` created this item from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar_uid: Option<String>,
+    /// Interval or cron expression after which a completed item comes back,
+    /// see `recurrence::Recurrence`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<String>,
+    /// How long before the deadline `td remind --lead` reminds of the item,
+    /// like 30m or 1d
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remind: Vec<String>,
+}
+
+/// A timestamped note on an item, kept apart from its description
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Comment {
+    pub at: String,
+    pub text: String,
+}
+
+fn is_zero(version: &u32) -> bool {
+    *version == 0
+}
+
+pub fn new_uuid() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Drops the generated default from a schema, a random uuid is no meaningful default
+fn without_default(schema: &mut schemars::Schema) {
+    schema.remove("default");
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-struct IdPool {
+/// A lightweight checkbox inside a single item, without an ID of its own
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChecklistEntry {
+    pub text: String,
+    #[serde(default)]
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+pub(crate) struct IdPool {
     #[serde(default)]
     next_id: usize,
     #[serde(default)]
     recycled_ids: Vec<usize>,
     #[serde(default)]
-    used_ids: HashSet<usize>,
+    used_ids: BTreeSet<usize>,
+    /// Never hand out a released ID again
+    #[serde(default)]
+    monotonic: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct TodoList {
+    /// On-disk format version, only set on the root list, see `utils::migrate`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub version: u32,
     #[serde(default)]
     pub items: Vec<TodoItem>,
     #[serde(default)]
     id_pool: IdPool,
+    #[serde(default, skip_serializing_if = "Habits::is_empty")]
+    pub habits: Habits,
+    #[serde(default, skip_serializing_if = "Trash::is_empty")]
+    pub trash: Trash,
+    #[serde(default, skip_serializing_if = "Milestones::is_empty")]
+    pub milestones: Milestones,
+    /// Hash of the content as last loaded or saved, None for a list never saved
+    #[serde(skip)]
+    saved_hash: Option<u64>,
+}
+
+impl FromStr for Priority {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "low" | "l" => Ok(Priority::Low),
+            "medium" | "med" | "m" => Ok(Priority::Medium),
+            "high" | "h" => Ok(Priority::High),
+            _ => Err(errors::invalid(format!(
+                "Invalid priority '{}', expected high, medium or low",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 impl IdPool {
     /// Acquires a new ID, reusing recycled IDs when available
-    fn acquire_id(&mut self) -> usize {
-        if let Some(id) = self.recycled_ids.pop() {
+    pub(crate) fn acquire_id(&mut self) -> usize {
+        if let Some(id) = self.recycled_ids.pop().filter(|_| !self.monotonic) {
             self.used_ids.insert(id);
             id
         } else {
@@ -48,8 +269,69 @@ impl IdPool {
         }
     }
 
+    /// Creates a pool where IDs `0..count` are in use and nothing is recycled
+    fn sequential(&self, count: usize) -> Self {
+        Self {
+            next_id: count,
+            recycled_ids: Vec::new(),
+            used_ids: (0..count).collect(),
+            monotonic: self.monotonic,
+        }
+    }
+
+    /// Switches between reusing released IDs and purely monotonic allocation
+    ///
+    /// Already recycled IDs are discarded when switching to monotonic, `next_id`
+    /// is always past them so no ID can be handed out twice
+    fn set_monotonic(&mut self, monotonic: bool) {
+        self.monotonic = monotonic;
+        if monotonic {
+            self.recycled_ids.clear();
+        }
+    }
+
+    /// Rebuilds the pool so exactly `ids` are in use, recycling the gaps below the highest
+    fn rebuild(&self, ids: &BTreeSet<usize>) -> Self {
+        let next_id =
+            ids.last()
+                .map_or(0, |id| id + 1)
+                .max(if self.monotonic { self.next_id } else { 0 });
+        let recycled_ids = if self.monotonic {
+            Vec::new()
+        } else {
+            (0..next_id).rev().filter(|id| !ids.contains(id)).collect()
+        };
+        Self {
+            next_id,
+            recycled_ids,
+            used_ids: ids.iter().copied().collect(),
+            monotonic: self.monotonic,
+        }
+    }
+
+    /// Describes how the pool disagrees with the IDs actually used by a list's items
+    fn inconsistencies(&self, ids: &BTreeSet<usize>) -> Vec<String> {
+        let mut problems = Vec::new();
+        for id in ids.iter().filter(|id| !self.used_ids.contains(id)) {
+            problems.push(format!("ID {} is used by an item but not reserved", id));
+        }
+        for id in self.used_ids.iter().filter(|id| !ids.contains(id)) {
+            problems.push(format!("ID {} is reserved but no item has it", id));
+        }
+        for id in self.recycled_ids.iter().filter(|id| ids.contains(id)) {
+            problems.push(format!("ID {} is in use but also recycled", id));
+        }
+        if let Some(max) = ids.last().filter(|max| **max >= self.next_id) {
+            problems.push(format!(
+                "next ID {} is not past the highest ID {}",
+                self.next_id, max
+            ));
+        }
+        problems
+    }
+
     /// Releases an ID back to the pool for reuse
-    fn release_id(&mut self, id: usize) -> Result<()> {
+    pub(crate) fn release_id(&mut self, id: usize) -> Result<()> {
         if !self.used_ids.contains(&id) {
             return Err(anyhow::anyhow!(
                 "Cannot release ID {}: ID is not currently in use",
@@ -58,7 +340,9 @@ impl IdPool {
         }
 
         self.used_ids.remove(&id);
-        self.recycled_ids.push(id);
+        if !self.monotonic {
+            self.recycled_ids.push(id);
+        }
         Ok(())
     }
 }
@@ -69,50 +353,81 @@ impl TodoList {
         Self::default()
     }
 
+    /// Remembers the current content as saved, see `is_dirty`
+    pub fn mark_clean(&mut self) {
+        self.saved_hash = Some(self.content_hash());
+    }
+
+    /// Returns true if the list changed since it was loaded or last marked clean
+    pub fn is_dirty(&self) -> bool {
+        self.saved_hash != Some(self.content_hash())
+    }
+
+    fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_vec(self)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        hasher.finish()
+    }
+
     /// Parses a path string to navigate to a specific TodoItem
     ///
-    /// Path format: "0" for top level item, "0:1:2" for nested items
-    fn parse_path(&mut self, path: &String) -> Result<&mut TodoItem> {
-        let indices: Vec<usize> = path
+    /// Path format: "0" for top level item, "0:1:2" for nested items, each
+    /// segment being the ID shown by `list`
+    pub fn parse_path(&mut self, path: &String) -> Result<&mut TodoItem> {
+        let ids: Vec<usize> = path
             .split(':')
             .map(|s| s.parse::<usize>())
             .collect::<Result<Vec<_>, _>>()
-            .map_err(|_| anyhow::anyhow!("Invalid path format: {}", path))?;
+            .map_err(|_| errors::invalid(format!("Invalid path format: {}", path)))?;
 
-        if indices.is_empty() {
-            return Err(anyhow::anyhow!("Invalid path: path cannot be empty"));
+        if ids.is_empty() {
+            return Err(errors::invalid("Invalid path: path cannot be empty"));
         }
 
         let mut current_list = self;
-        for (depth, &index) in indices.iter().enumerate() {
-            if index >= current_list.items.len() {
-                return Err(anyhow::anyhow!(
-                    "Invalid path '{}': item {} does not exist at depth {} (available: 0-{})",
-                    path,
-                    index,
-                    depth,
-                    current_list.items.len().saturating_sub(1)
-                ));
-            }
+        for (depth, &id) in ids.iter().enumerate() {
+            let item = current_list
+                .items
+                .iter_mut()
+                .find(|item| item.id == id)
+                .ok_or_else(|| {
+                    errors::not_found(format!(
+                        "Invalid path '{}': item {} does not exist at depth {}",
+                        path, id, depth
+                    ))
+                })?;
 
-            if depth == indices.len() - 1 {
-                return Ok(&mut current_list.items[index]);
+            if depth == ids.len() - 1 {
+                return Ok(item);
             }
 
-            let item = &mut current_list.items[index];
             current_list = item.sub_list.as_mut().ok_or_else(|| {
-                anyhow::anyhow!(
+                errors::not_found(format!(
                     "Invalid path '{}': item {} at depth {} has no subitems",
-                    path,
-                    index,
-                    depth
-                )
+                    path, id, depth
+                ))
             })?;
         }
 
         unreachable!()
     }
 
+    /// Finds items whose path starts with `query` or whose description contains it
+    ///
+    /// Matching on descriptions is case-insensitive
+    pub fn find_matches(&self, query: &str) -> Vec<(String, &TodoItem)> {
+        let lower = query.to_lowercase();
+        let prefix = normalize_path(query);
+        self.iter()
+            .map(|(path, item)| (format_path(&path), item))
+            .filter(|(path, item)| {
+                path.starts_with(&prefix) || item.description.to_lowercase().contains(&lower)
+            })
+            .collect()
+    }
+
     /// Creates a new TodoItem and adds it to the list or a sublist
     ///
     /// # Arguments
@@ -124,63 +439,188 @@ impl TodoList {
         description: String,
         deadline: Option<DateTime<Local>>,
         parent_path: Option<&String>,
-    ) -> Result<&TodoItem> {
-        // get sublist or create a new one
-        let list = if let Some(p) = parent_path {
-            let parent = self.parse_path(p)?;
-            parent.sub_list.get_or_insert(TodoList::new())
-        } else {
-            self
-        };
-        let id = list.id_pool.acquire_id();
-        let time = deadline.map(|deadline| deadline.to_string());
+    ) -> Result<&mut TodoItem> {
+        let list = self.list_at(parent_path)?;
         let item = TodoItem {
-            id,
-            description,
-            completed: false,
-            deadline: time,
-            sub_list: None,
+            deadline: deadline.map(format_deadline),
+            ..TodoItem::new(description)
         };
-        list.items.push(item);
-        list.items
-            .last()
-            .ok_or(anyhow::anyhow!("Failed to add item to todo list"))
+        Ok(list.push_item(item))
+    }
+
+    /// Returns the sub list of the item at `parent_path`, creating it if needed,
+    /// or this list itself when no parent is given
+    ///
+    /// A newly created sub list inherits this list's ID allocation mode
+    pub fn list_at(&mut self, parent_path: Option<&String>) -> Result<&mut TodoList> {
+        let monotonic = self.id_pool.monotonic;
+        match parent_path {
+            Some(path) => Ok(self.parse_path(path)?.sub_list.get_or_insert_with(|| {
+                let mut list = TodoList::new();
+                list.id_pool.monotonic = monotonic;
+                list
+            })),
+            None => Ok(self),
+        }
+    }
+
+    /// Sets whether this list and all its sub lists reuse released IDs
+    pub fn set_monotonic_ids(&mut self, monotonic: bool) {
+        self.id_pool.set_monotonic(monotonic);
+        for item in &mut self.items {
+            if let Some(sub_list) = &mut item.sub_list {
+                sub_list.set_monotonic_ids(monotonic);
+            }
+        }
+    }
+
+    /// Appends an item to this list, assigning it a fresh ID from the pool
+    pub fn push_item(&mut self, mut item: TodoItem) -> &mut TodoItem {
+        item.id = self.id_pool.acquire_id();
+        self.items.push(item);
+        let last = self.items.len() - 1;
+        &mut self.items[last]
+    }
+
+    /// Copies the TodoItem at `path` as a new pending item
+    ///
+    /// The copy is added under `to`, or next to the original when no target is
+    /// given. Returns the path of the copy
+    pub fn duplicate_item(
+        &mut self,
+        path: &String,
+        to: Option<&String>,
+        with_subtasks: bool,
+    ) -> Result<String> {
+        let copy = self.parse_path(path)?.fresh_copy(with_subtasks);
+        let parent_path = match to {
+            Some(to) => Some(to.clone()),
+            None => path.rsplit_once(':').map(|(parent, _)| parent.to_string()),
+        };
+        let id = self.list_at(parent_path.as_ref())?.push_item(copy).id;
+        Ok(match parent_path {
+            Some(parent) => format!("{}:{}", parent, id),
+            None => id.to_string(),
+        })
     }
 
     /// Edits an existing TodoItem at the specified path
     ///
     /// # Arguments
     /// * `path` - Path to the item to edit
-    /// * `description` - New description for the todo item
-    /// * `deadline` - Optional new deadline for the todo item
+    /// * `description` - New description, or None to keep the current one
+    /// * `deadline` - New deadline, `Some(None)` clears it and None keeps the current one
     pub fn edit_item(
         &mut self,
         path: &String,
-        description: String,
-        deadline: Option<DateTime<Local>>,
+        description: Option<String>,
+        deadline: Option<Option<DateTime<Local>>>,
     ) -> Result<&TodoItem> {
         let item = self.parse_path(path)?;
-        item.description = description;
-        item.deadline = deadline.map(|deadline| deadline.to_string());
+        if let Some(description) = description {
+            item.description = description;
+        }
+        if let Some(deadline) = deadline {
+            item.set_deadline(deadline);
+        }
+        item.touch();
         Ok(item)
     }
 
-    /// Returns a list of TodoItems based on SHOW_COMPLETE flag
-    ///
-    /// If SHOW_COMPLETE is true, returns all items; otherwise returns only incomplete items
-    pub fn list_items(&self) -> Vec<&TodoItem> {
-        if SHOW_COMPLETE.load(Ordering::SeqCst) {
-            self.items.iter().collect()
+    /// Adds text to the end, or with `prepend` the start, of a TodoItem's description
+    pub fn amend_item(&mut self, path: &String, text: &str, prepend: bool) -> Result<&TodoItem> {
+        let item = self.parse_path(path)?;
+        item.description = if prepend {
+            format!("{} {}", text, item.description)
         } else {
-            self.items.iter().filter(|item| !item.completed).collect()
-        }
+            format!("{} {}", item.description, text)
+        };
+        item.touch();
+        Ok(item)
+    }
+
+    /// Returns the items of this list to show, as picked and ordered by `options`
+    ///
+    /// Someday items are never listed. Items keep their insertion order unless
+    /// sorted; starred items always come first
+    pub fn list_items(&self, options: &ListOptions) -> Vec<&TodoItem> {
+        let mut items: Vec<&TodoItem> = self
+            .items
+            .iter()
+            .filter(|item| options.lists(item))
+            .collect();
+        items.sort_by(|a, b| {
+            let order = options
+                .sort
+                .map_or(CmpOrdering::Equal, |key| a.compare_by(b, key));
+            let order = if options.reverse {
+                order.reverse()
+            } else {
+                order
+            };
+            b.starred.cmp(&a.starred).then(order)
+        });
+        items
     }
 
     /// Marks a TodoItem as completed at the specified path
+    ///
+    /// A pending repeating item gets its next occurrence added after the last
+    /// item of its list
     pub fn complete_item(&mut self, path: &String) -> Result<&TodoItem> {
+        self.complete_repeating(path)?;
+        Ok(self.parse_path(path)?)
+    }
+
+    /// Completes the item at `path` like `complete_item`, returning the path of
+    /// the next occurrence if one was added
+    pub fn complete_repeating(&mut self, path: &String) -> Result<Option<String>> {
         let item = self.parse_path(path)?;
+        let next = if item.is_completed() {
+            None
+        } else {
+            recurrence::next_occurrence(item, Local::now())?
+        };
         item.complete();
-        Ok(item)
+        let Some(next) = next else {
+            return Ok(None);
+        };
+        let parent = path.rsplit_once(':').map(|(parent, _)| parent.to_string());
+        let id = self.list_at(parent.as_ref())?.push_item(next).id;
+        Ok(Some(match parent {
+            Some(parent) => format!("{}:{}", parent, id),
+            None => id.to_string(),
+        }))
+    }
+
+    /// Moves the item at `path` to `status`, completing it like `complete_item`
+    /// and returning the path of a next occurrence if one was added
+    pub fn set_status(&mut self, path: &String, status: Status) -> Result<Option<String>> {
+        match status {
+            Status::Todo => self.parse_path(path)?.reopen(),
+            Status::Started => self.parse_path(path)?.start(),
+            Status::Done => return self.complete_repeating(path),
+        }
+        Ok(None)
+    }
+
+    /// Completes the item at `path` like `complete_item` along with all its
+    /// pending subtasks, returning how many subtasks were completed and the path
+    /// of the next occurrence if one was added
+    ///
+    /// Only the item itself brings back a next occurrence if it repeats
+    pub fn complete_item_recursive(&mut self, path: &String) -> Result<(usize, Option<String>)> {
+        let mut completed = 0;
+        if let Some(sub_list) = &mut self.parse_path(path)?.sub_list {
+            sub_list.walk_mut(&mut |item| {
+                if !item.is_completed() {
+                    item.complete();
+                    completed += 1;
+                }
+            });
+        }
+        let next = self.complete_repeating(path)?;
+        Ok((completed, next))
     }
 
     /// Removes a TodoItem at the specified path and returns it
@@ -189,7 +629,7 @@ impl TodoList {
     pub fn remove_item(&mut self, path: &str) -> Result<TodoItem> {
         /// Splits parent and child path from a colon-separated string
         fn split_path(path: &str) -> Result<(Option<String>, usize)> {
-            if let Some((parent_str, child_str)) = path.rsplit_once(":") {
+            if let Some((parent_str, child_str)) = path.rsplit_once(':') {
                 let child = child_str.parse::<usize>().context("Invalid parse format")?;
                 Ok((Some(parent_str.into()), child))
             } else {
@@ -209,52 +649,895 @@ impl TodoList {
             .items
             .iter()
             .position(|item| item.id == id)
-            .ok_or_else(|| anyhow::anyhow!("Item with ID {} not found in path '{}'", id, path))?;
+            .ok_or_else(|| {
+                errors::not_found(format!("Item with ID {} not found in path '{}'", id, path))
+            })?;
 
         parent.id_pool.release_id(id)?;
         Ok(parent.items.remove(index))
     }
 
+    /// Moves an item to the front of its list and refreshes its modification time
+    pub fn bump_item(&mut self, path: &String) -> Result<&TodoItem> {
+        let item = self.reorder_item(path, 0)?;
+        item.touch();
+        Ok(item)
+    }
+
+    /// Moves an item to position `index` among its siblings, or last if past the end
+    pub fn reorder_item(&mut self, path: &String, index: usize) -> Result<&mut TodoItem> {
+        let (parent_path, id) = match path.rsplit_once(':') {
+            Some((parent, id)) => (Some(parent.to_string()), id),
+            None => (None, path.as_str()),
+        };
+        let id: usize = id.parse().context("Invalid parse format")?;
+        let parent = self.list_at(parent_path.as_ref())?;
+        let position = parent
+            .items
+            .iter()
+            .position(|item| item.id == id)
+            .ok_or_else(|| {
+                errors::not_found(format!("Item with ID {} not found in path '{}'", id, path))
+            })?;
+        let item = parent.items.remove(position);
+        let index = index.min(parent.items.len());
+        parent.items.insert(index, item);
+        Ok(&mut parent.items[index])
+    }
+
+    /// Removes a TodoItem at the specified path and moves it into the trash
+    pub fn trash_item(&mut self, path: &str) -> Result<&TrashEntry> {
+        let parent_uuid = path
+            .rsplit_once(':')
+            .and_then(|(parent, _)| self.find_by_path(parent))
+            .map(|parent| parent.uuid.clone());
+        let item = self.remove_item(path)?;
+        Ok(self.trash.push(path.to_string(), parent_uuid, item))
+    }
+
+    /// Restores an item from the trash to its original parent, found by its uuid
+    ///
+    /// Falls back to the top level if the parent no longer exists. The item gets
+    /// a fresh ID, the new path is returned
+    pub fn restore_item(&mut self, trash_id: usize) -> Result<String> {
+        let entry = self.trash.take(trash_id)?;
+        let parent = entry
+            .parent_uuid
+            .and_then(|uuid| self.find_by_uuid(&uuid))
+            .map(|(path, _)| path);
+        let id = self.list_at(parent.as_ref())?.push_item(entry.item).id;
+        Ok(match parent {
+            Some(parent) => format!("{}:{}", parent, id),
+            None => id.to_string(),
+        })
+    }
+
+    /// Moves the TodoItem at `path` with its subtasks under `new_parent`, or to the
+    /// top level when no parent is given
+    ///
+    /// The item gets a fresh ID in its new list. Returns the new path
+    pub fn move_item(&mut self, path: &String, new_parent: Option<&String>) -> Result<String> {
+        if let Some(parent) = new_parent {
+            if parent == path || parent.starts_with(&format!("{}:", path)) {
+                return Err(errors::refused(format!(
+                    "Cannot move item '{}' under itself or its subitems",
+                    path
+                )));
+            }
+            // validate the target before taking the item out of the tree
+            self.parse_path(parent)?;
+        }
+        let item = self.remove_item(path)?;
+        let id = self.list_at(new_parent)?.push_item(item).id;
+        Ok(match new_parent {
+            Some(parent) => format!("{}:{}", parent, id),
+            None => id.to_string(),
+        })
+    }
+
+    /// Reassigns sequential IDs in list order and resets the ID pool
+    ///
+    /// Sub lists are renumbered too when `recursive` is set. Returns the
+    /// old and new path of every item whose path changed
+    pub fn renumber(&mut self, recursive: bool) -> Vec<(String, String)> {
+        fn renumber_inner(
+            list: &mut TodoList,
+            recursive: bool,
+            old_prefix: &str,
+            new_prefix: &str,
+            mapping: &mut Vec<(String, String)>,
+        ) {
+            for (new_id, item) in list.items.iter_mut().enumerate() {
+                let old_path = format!("{}{}", old_prefix, item.id);
+                let new_path = format!("{}{}", new_prefix, new_id);
+                if old_path != new_path {
+                    mapping.push((old_path.clone(), new_path.clone()));
+                }
+                item.id = new_id;
+                if let Some(sub_list) = item.sub_list.as_mut().filter(|_| recursive) {
+                    renumber_inner(
+                        sub_list,
+                        recursive,
+                        &format!("{}:", old_path),
+                        &format!("{}:", new_path),
+                        mapping,
+                    );
+                }
+            }
+            list.id_pool = list.id_pool.sequential(list.items.len());
+        }
+
+        let mut mapping = Vec::new();
+        renumber_inner(self, recursive, "", "", &mut mapping);
+        mapping
+    }
+
+    /// Removes every completed item finished before `cutoff`, with its subtasks,
+    /// from the whole tree and returns them
+    ///
+    /// Items with pending subtasks stay, so no pending work is taken along
+    pub fn take_completed_before(&mut self, cutoff: DateTime<Local>) -> Vec<TodoItem> {
+        let mut taken = Vec::new();
+        let mut index = 0;
+        while index < self.items.len() {
+            let item = &mut self.items[index];
+            let pending_below = item
+                .sub_list
+                .as_ref()
+                .is_some_and(|sub_list| sub_list.pending_count() > 0);
+            if item.is_completed()
+                && !pending_below
+                && item.completed_time().is_some_and(|time| time < cutoff)
+            {
+                let item = self.items.remove(index);
+                // IDs come from this list's pool, releasing cannot fail
+                let _ = self.id_pool.release_id(item.id);
+                taken.push(item);
+                continue;
+            }
+            if let Some(sub_list) = &mut item.sub_list {
+                taken.extend(sub_list.take_completed_before(cutoff));
+            }
+            index += 1;
+        }
+        taken
+    }
+
     /// Returns the count of incomplete todo items
     pub fn todo_len(&self) -> usize {
-        self.items.iter().filter(|item| !item.completed).count()
+        self.items
+            .iter()
+            .filter(|item| !item.is_completed())
+            .count()
+    }
+
+    /// Marks a TodoItem as in progress at the specified path
+    pub fn start_item(&mut self, path: &String) -> Result<&TodoItem> {
+        let item = self.parse_path(path)?;
+        item.start();
+        Ok(item)
+    }
+
+    /// Sets or clears the starred flag of a TodoItem at the specified path
+    pub fn star_item(&mut self, path: &String, starred: bool) -> Result<&TodoItem> {
+        let item = self.parse_path(path)?;
+        item.starred = starred;
+        item.touch();
+        Ok(item)
+    }
+
+    /// Sets or clears who a TodoItem at the specified path is waiting on
+    pub fn delegate_item(&mut self, path: &String, who: Option<String>) -> Result<&TodoItem> {
+        let item = self.parse_path(path)?;
+        item.waiting_on = who;
+        item.touch();
+        Ok(item)
+    }
+
+    /// Moves a TodoItem at the specified path into or out of the someday bucket
+    pub fn someday_item(&mut self, path: &String, someday: bool) -> Result<&TodoItem> {
+        let item = self.parse_path(path)?;
+        item.someday = someday;
+        item.touch();
+        Ok(item)
+    }
+
+    /// Visits every item in the tree in depth-first order
+    ///
+    /// The callback receives the ID path of each item, usable with `parse_path`
+    pub fn walk<'a>(&'a self, f: &mut dyn FnMut(&[usize], &'a TodoItem)) {
+        fn walk_inner<'a>(
+            list: &'a TodoList,
+            path: &mut Vec<usize>,
+            f: &mut dyn FnMut(&[usize], &'a TodoItem),
+        ) {
+            for item in &list.items {
+                path.push(item.id);
+                f(path, item);
+                if let Some(sub_list) = &item.sub_list {
+                    walk_inner(sub_list, path, f);
+                }
+                path.pop();
+            }
+        }
+
+        walk_inner(self, &mut Vec::new(), f);
+    }
+
+    /// Returns the priority the item at `path` counts with: its own, or else
+    /// that of the closest item it belongs to that has one
+    ///
+    /// Giving a subtask a priority, even low, overrides its parent's
+    pub fn effective_priority(&self, path: &[usize]) -> Option<Priority> {
+        let mut list = self;
+        let mut priority = None;
+        for id in path {
+            let Some(item) = list.items.iter().find(|item| item.id == *id) else {
+                break;
+            };
+            priority = item.priority.or(priority);
+            match &item.sub_list {
+                Some(sub_list) => list = sub_list,
+                None => break,
+            }
+        }
+        priority
+    }
+
+    /// Visits every item in the tree mutably in depth-first order
+    pub fn walk_mut(&mut self, f: &mut dyn FnMut(&mut TodoItem)) {
+        for item in &mut self.items {
+            f(item);
+            if let Some(sub_list) = &mut item.sub_list {
+                sub_list.walk_mut(f);
+            }
+        }
+    }
+
+    /// Replaces `from` with `to` in the tags of every item, or strips it if `to` is None
+    ///
+    /// Returns the count of items changed. Items already tagged `to` keep a single copy
+    pub fn retag(&mut self, from: &str, to: Option<&str>) -> usize {
+        let mut count = 0;
+        self.walk_mut(&mut |item| {
+            if !item.tags.iter().any(|tag| tag == from) {
+                return;
+            }
+            item.tags.retain(|tag| tag != from);
+            if let Some(to) = to.filter(|to| !item.tags.iter().any(|tag| tag == to)) {
+                item.tags.push(to.to_string());
+            }
+            item.touch();
+            count += 1;
+        });
+        count
+    }
+
+    /// Returns every tag used in the tree with the count of items carrying it
+    pub fn tag_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for tag in self.iter().flat_map(|(_, item)| &item.tags) {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Assigns an item to someone, or clears the assignee when `who` is None
+    pub fn assign_item(&mut self, path: &String, who: Option<String>) -> Result<&TodoItem> {
+        let item = self.parse_path(path)?;
+        item.assignee = who;
+        item.touch();
+        Ok(item)
+    }
+
+    /// Adds a comment stamped with the current time to an item
+    pub fn comment_item(&mut self, path: &String, text: String) -> Result<&TodoItem> {
+        let item = self.parse_path(path)?;
+        item.comments.push(Comment {
+            at: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            text,
+        });
+        item.touch();
+        Ok(item)
+    }
+
+    /// Toggles a checklist entry of an item, returning the entry
+    pub fn toggle_check(&mut self, path: &String, index: usize) -> Result<&ChecklistEntry> {
+        let item = self.parse_path(path)?;
+        let entry = item.checklist.get_mut(index).ok_or_else(|| {
+            errors::not_found(format!("Item '{}' has no checklist entry {}", path, index))
+        })?;
+        entry.done = !entry.done;
+        item.touch();
+        Ok(&item.checklist[index])
+    }
+
+    /// Finds an item anywhere in the tree by its uuid, returning its ID path
+    pub fn find_by_uuid(&self, uuid: &str) -> Option<(String, &TodoItem)> {
+        self.iter()
+            .find(|(_, item)| item.uuid == uuid)
+            .map(|(path, item)| (format_path(&path), item))
+    }
+
+    /// Finds the item at an ID path like `2:1`, the read-only counterpart of
+    /// `parse_path`
+    pub fn find_by_path(&self, path: &str) -> Option<&TodoItem> {
+        let mut list = Some(self);
+        let mut found = None;
+        for id in path.split(':') {
+            let id = id.parse::<usize>().ok()?;
+            let item = list?.items.iter().find(|item| item.id == id)?;
+            list = item.sub_list.as_ref();
+            found = Some(item);
+        }
+        found
+    }
+
+    /// Marks two items as related to each other, or removes the relation
+    pub fn link_items(&mut self, path: &String, other: &String, linked: bool) -> Result<()> {
+        let uuid = self.parse_path(path)?.uuid.clone();
+        let other_uuid = self.parse_path(other)?.uuid.clone();
+        if uuid == other_uuid {
+            return Err(errors::refused(format!(
+                "Cannot link item '{}' to itself",
+                path
+            )));
+        }
+        for (path, target) in [(path, other_uuid), (other, uuid)] {
+            let item = self.parse_path(path)?;
+            item.related.retain(|related| *related != target);
+            if linked {
+                item.related.push(target);
+            }
+            item.touch();
+        }
+        Ok(())
+    }
+
+    /// Checks the list for duplicate IDs, ID pool drift, unparseable timestamps and
+    /// status inconsistencies, repairing them with `fix`
+    ///
+    /// Returns a description of every problem found
+    pub fn diagnose(&mut self, fix: bool) -> Vec<String> {
+        let mut problems = Vec::new();
+        self.diagnose_inner("", fix, &mut problems);
+        problems
+    }
+
+    /// Returns the pending items due later than the closest item with a
+    /// deadline they belong to, as the paths of both
+    pub fn late_subtasks(&self) -> Vec<(String, String)> {
+        let mut late = Vec::new();
+        self.late_subtasks_inner("", None, &mut late);
+        late
+    }
+
+    fn late_subtasks_inner(
+        &self,
+        prefix: &str,
+        ancestor: Option<(&str, DateTime<Local>)>,
+        late: &mut Vec<(String, String)>,
+    ) {
+        for item in &self.items {
+            let path = match prefix {
+                "" => item.id.to_string(),
+                prefix => format!("{}:{}", prefix, item.id),
+            };
+            let deadline = item.deadline_time();
+            if let (Some(deadline), Some((ancestor_path, ancestor_deadline))) = (deadline, ancestor)
+            {
+                if deadline > ancestor_deadline && !item.is_completed() {
+                    late.push((path.clone(), ancestor_path.to_string()));
+                }
+            }
+            if let Some(sub_list) = &item.sub_list {
+                let closest = match deadline {
+                    Some(deadline) => Some((path.as_str(), deadline)),
+                    None => ancestor,
+                };
+                sub_list.late_subtasks_inner(&path, closest, late);
+            }
+        }
+    }
+
+    fn diagnose_inner(&mut self, prefix: &str, fix: bool, problems: &mut Vec<String>) {
+        let label = match prefix {
+            "" => "root list".to_string(),
+            prefix => format!("list #{}", prefix),
+        };
+        let mut ids = BTreeSet::new();
+        let mut duplicates = Vec::new();
+        for (index, item) in self.items.iter().enumerate() {
+            if !ids.insert(item.id) {
+                problems.push(format!("{}: duplicate ID {}", label, item.id));
+                duplicates.push(index);
+            }
+        }
+        let pool_problems = self.id_pool.inconsistencies(&ids);
+        problems.extend(
+            pool_problems
+                .iter()
+                .map(|problem| format!("{}: {}", label, problem)),
+        );
+        if fix && !(pool_problems.is_empty() && duplicates.is_empty()) {
+            self.id_pool = self.id_pool.rebuild(&ids);
+            for index in duplicates {
+                self.items[index].id = self.id_pool.acquire_id();
+            }
+        }
+
+        for item in &mut self.items {
+            let path = match prefix {
+                "" => item.id.to_string(),
+                prefix => format!("{}:{}", prefix, item.id),
+            };
+            let timestamps = [
+                ("deadline", &mut item.deadline),
+                ("created", &mut item.created_at),
+                ("modified", &mut item.modified_at),
+                ("completed", &mut item.completed_at),
+            ];
+            for (name, timestamp) in timestamps {
+                if let Some(value) = timestamp
+                    .as_ref()
+                    .filter(|value| parse_timestamp(value).is_none())
+                {
+                    problems.push(format!("#{}: unparseable {} '{}'", path, name, value));
+                    if fix {
+                        *timestamp = None;
+                    }
+                }
+            }
+            if item.completed_at.is_some() && !item.is_completed() {
+                problems.push(format!("#{}: completion time set on a pending item", path));
+                if fix {
+                    item.completed_at = None;
+                }
+            }
+            if let Some(sub_list) = &mut item.sub_list {
+                sub_list.diagnose_inner(&path, fix, problems);
+            }
+        }
+    }
+
+    /// Returns the count of completed and total items in the whole tree
+    pub fn progress(&self) -> (usize, usize) {
+        (
+            self.count_where(|item| item.is_completed()),
+            self.count_where(|_| true),
+        )
+    }
+
+    /// Returns the count of items in the whole tree matching a predicate
+    pub fn count_where(&self, predicate: impl Fn(&TodoItem) -> bool) -> usize {
+        self.iter().filter(|(_, item)| predicate(item)).count()
+    }
+
+    /// Returns the number of pending items in the whole tree
+    pub fn pending_count(&self) -> usize {
+        self.count_where(|item| !item.is_completed())
+    }
+
+    /// Iterates over every item in the tree in depth-first order, with the ID
+    /// path of each like `walk`
+    ///
+    /// Items own their sub lists, so changing items while visiting them goes
+    /// through `walk_mut` instead
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            stack: vec![self.items.iter()],
+            path: Vec::new(),
+        }
+    }
+}
+
+/// Depth-first iterator over the items of a list and its sub lists, see
+/// `TodoList::iter`
+pub struct Iter<'a> {
+    stack: Vec<std::slice::Iter<'a, TodoItem>>,
+    path: Vec<usize>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (Vec<usize>, &'a TodoItem);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let level = self.stack.last_mut()?;
+            let Some(item) = level.next() else {
+                self.stack.pop();
+                continue;
+            };
+            self.path.truncate(self.stack.len() - 1);
+            self.path.push(item.id);
+            if let Some(sub_list) = &item.sub_list {
+                self.stack.push(sub_list.items.iter());
+            }
+            return Some((self.path.clone(), item));
+        }
     }
 }
 
 impl TodoItem {
+    /// Creates a pending item without ID, to be added with `TodoList::push_item`
+    pub fn new(description: String) -> Self {
+        let now = Local::now().to_string();
+        TodoItem {
+            uuid: new_uuid(),
+            id: 0,
+            description,
+            status: Status::Todo,
+            starred: false,
+            deadline: None,
+            sub_list: None,
+            created_at: Some(now.clone()),
+            completed_at: None,
+            modified_at: Some(now),
+            waiting_on: None,
+            someday: false,
+            priority: None,
+            tags: Vec::new(),
+            context: None,
+            project: None,
+            milestone: None,
+            checklist: Vec::new(),
+            url: None,
+            attachments: Vec::new(),
+            related: Vec::new(),
+            comments: Vec::new(),
+            assignee: None,
+            github_issue: None,
+            markdown_file: None,
+            calendar_uid: None,
+            repeat: None,
+            remind: Vec::new(),
+        }
+    }
+
     /// Marks this TodoItem as completed
     pub fn complete(&mut self) {
-        self.completed = true;
+        if self.status != Status::Done {
+            self.status = Status::Done;
+            self.completed_at = Some(Local::now().to_string());
+            self.touch();
+        }
+    }
+
+    /// Records that this TodoItem was modified now
+    pub fn touch(&mut self) {
+        self.modified_at = Some(Local::now().to_string());
+    }
+
+    /// Builds a pending copy of this item with fresh timestamps
+    ///
+    /// Subtasks are copied recursively with new IDs when `with_subtasks` is set
+    pub fn fresh_copy(&self, with_subtasks: bool) -> TodoItem {
+        let now = Local::now().to_string();
+        let sub_list = self
+            .sub_list
+            .as_ref()
+            .filter(|_| with_subtasks)
+            .map(|sub_list| {
+                let mut copy = TodoList::new();
+                for item in &sub_list.items {
+                    copy.push_item(item.fresh_copy(true));
+                }
+                copy
+            });
+        TodoItem {
+            uuid: new_uuid(),
+            related: Vec::new(),
+            comments: Vec::new(),
+            github_issue: None,
+            markdown_file: None,
+            calendar_uid: None,
+            status: Status::Todo,
+            completed_at: None,
+            created_at: Some(now.clone()),
+            modified_at: Some(now),
+            sub_list,
+            ..self.clone()
+        }
+    }
+
+    /// Marks this TodoItem as not started nor completed again
+    pub fn reopen(&mut self) {
+        self.status = Status::Todo;
+        self.completed_at = None;
+        self.touch();
+    }
+
+    /// Marks this TodoItem as in progress, reopening it if it was completed
+    pub fn start(&mut self) {
+        self.status = Status::Started;
+        self.completed_at = None;
+        self.touch();
+    }
+
+    /// Returns true if this TodoItem is done
+    pub fn is_completed(&self) -> bool {
+        self.status == Status::Done
+    }
+
+    /// Returns true if this TodoItem is in progress
+    pub fn is_started(&self) -> bool {
+        self.status == Status::Started
+    }
+
+    /// Computes an urgency score used to weight random picks
+    ///
+    /// Overdue and soon-due items score highest, high priority and started items get a boost,
+    /// the priority being the one the item counts with, see `TodoList::effective_priority`
+    pub fn urgency(&self, now: DateTime<Local>, priority: Option<Priority>) -> f64 {
+        let deadline_score = match self.deadline_time() {
+            Some(deadline) if deadline < now => 10.0,
+            Some(deadline) if deadline - now <= chrono::Duration::days(1) => 6.0,
+            Some(deadline) if deadline - now <= chrono::Duration::weeks(1) => 3.0,
+            Some(_) => 1.5,
+            None => 1.0,
+        };
+        let priority_score = match priority {
+            Some(Priority::High) => 3.0,
+            Some(Priority::Medium) => 1.0,
+            _ => 0.0,
+        };
+        let started_score = if self.is_started() { 2.0 } else { 0.0 };
+        deadline_score + priority_score + started_score
+    }
+
+    /// Returns true if this item is pending, not blocked on someone else and not deferred
+    pub fn is_actionable(&self) -> bool {
+        !self.is_completed() && self.waiting_on.is_none() && !self.someday
+    }
+
+    /// Compares two items by a sort key
+    pub fn compare_by(&self, other: &TodoItem, key: SortKey) -> CmpOrdering {
+        match key {
+            SortKey::Deadline => match (self.deadline_time(), other.deadline_time()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => CmpOrdering::Less,
+                (None, Some(_)) => CmpOrdering::Greater,
+                (None, None) => CmpOrdering::Equal,
+            },
+            SortKey::Priority => other.priority.cmp(&self.priority),
+            SortKey::Created => self.created_time().cmp(&other.created_time()),
+            SortKey::Alpha => self
+                .description
+                .to_lowercase()
+                .cmp(&other.description.to_lowercase()),
+        }
+    }
+
+    /// Returns the item's url, or else the first link found in its description
+    pub fn link(&self) -> Option<&str> {
+        self.url.as_deref().or_else(|| {
+            self.description
+                .split_whitespace()
+                .find(|word| is_url(word))
+        })
+    }
+
+    /// Parses the stored deadline string back into a DateTime
+    pub fn deadline_time(&self) -> Option<DateTime<Local>> {
+        self.deadline.as_deref().and_then(parse_timestamp)
+    }
+
+    /// Sets or clears the deadline, without touching the item
+    pub fn set_deadline(&mut self, deadline: Option<DateTime<Local>>) {
+        self.deadline = deadline.map(format_deadline);
+    }
+
+    /// Returns the deadline as shown, in the local time zone to the minute
+    pub fn deadline_label(&self) -> Option<String> {
+        let deadline = self.deadline.as_ref()?;
+        Some(match parse_timestamp(deadline) {
+            Some(time) => time.format("%Y-%m-%d %H:%M").to_string(),
+            None => deadline.clone(),
+        })
+    }
+
+    /// Returns the creation time, if recorded
+    pub fn created_time(&self) -> Option<DateTime<Local>> {
+        self.created_at.as_deref().and_then(parse_timestamp)
+    }
+
+    /// Returns the last modification time, falling back to the creation time
+    pub fn modified_time(&self) -> Option<DateTime<Local>> {
+        self.modified_at
+            .as_deref()
+            .and_then(parse_timestamp)
+            .or_else(|| self.created_time())
+    }
+
+    /// Returns the completion time, if recorded
+    pub fn completed_time(&self) -> Option<DateTime<Local>> {
+        self.completed_at.as_deref().and_then(parse_timestamp)
+    }
+
+    /// Returns true if this item is pending and its deadline is before `now`
+    pub fn is_overdue(&self, now: DateTime<Local>) -> bool {
+        !self.is_completed() && self.deadline_time().is_some_and(|deadline| deadline < now)
     }
 
     /// Formats the item's core information (ID, description, deadline, subitem count)
     fn format_info(&self) -> String {
+        self.format_info_at(&self.id.to_string())
+    }
+
+    /// Formats priority, tags and context as they are written in the inline add syntax
+    fn format_attributes(&self) -> String {
+        let mut attributes = String::new();
+        if let Some(priority) = self.priority {
+            attributes.push_str(&format!(" !{}", priority));
+        }
+        for tag in &self.tags {
+            attributes.push_str(&format!(" #{}", tag));
+        }
+        if let Some(context) = &self.context {
+            attributes.push_str(&format!(" @{}", context));
+        }
+        if let Some(project) = &self.project {
+            attributes.push_str(&format!(" +{}", project));
+        }
+        attributes
+    }
+
+    /// Formats the item's core information, labelled with its full path instead of its ID
+    pub fn format_info_at(&self, path: &str) -> String {
         format!(
-            "#{}: {}{}{}",
-            self.id,
+            "{}#{}: {}{}{}{}{}{}",
+            if self.starred { "★ " } else { "" },
+            path,
             self.description,
+            self.format_attributes(),
             match &self.sub_list {
-                Some(list) => format!(" ({})", list.todo_len()),
+                Some(list) => {
+                    let (done, total) = list.progress();
+                    format!(" ({}/{})", done, total)
+                }
                 None => String::new(),
             },
-            match &self.deadline {
+            match self.deadline_label() {
                 Some(time) => format!(" | deadline: {}", time),
                 None => String::new(),
+            },
+            match &self.waiting_on {
+                Some(who) => format!(" | waiting on {}", who),
+                None => String::new(),
+            },
+            match &self.assignee {
+                Some(who) => format!(" | assigned to {}", who),
+                None => String::new(),
             }
         )
     }
 
+    /// Prints every field of the TodoItem, used by `show`
+    pub fn show(&self, path: &str) {
+        let status = if self.is_completed() {
+            "done"
+        } else if self.is_started() {
+            "in progress"
+        } else {
+            "pending"
+        };
+        println!("#{}: {}", path, self.description);
+        println!("  status: {}", status);
+        let fields = [
+            ("uuid", Some(self.uuid.clone())),
+            (
+                "priority",
+                self.priority.map(|priority| priority.to_string()),
+            ),
+            (
+                "tags",
+                Some(self.tags.join(", ")).filter(|tags| !tags.is_empty()),
+            ),
+            ("context", self.context.clone()),
+            ("project", self.project.clone()),
+            ("milestone", self.milestone.clone()),
+            ("url", self.url.clone()),
+            ("deadline", self.deadline_label()),
+            ("waiting on", self.waiting_on.clone()),
+            ("assignee", self.assignee.clone()),
+            (
+                "github issue",
+                self.github_issue.map(|number| format!("#{}", number)),
+            ),
+            ("note", self.markdown_file.clone()),
+            ("calendar", self.calendar_uid.clone()),
+            ("repeat", self.repeat.clone()),
+            (
+                "remind",
+                Some(self.remind.join(", ")).filter(|remind| !remind.is_empty()),
+            ),
+            ("created", self.created_at.clone()),
+            ("modified", self.modified_at.clone()),
+            ("completed", self.completed_at.clone()),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                println!("  {}: {}", name, value);
+            }
+        }
+        if self.starred {
+            println!("  starred");
+        }
+        if self.someday {
+            println!("  someday");
+        }
+        if !self.checklist.is_empty() {
+            println!("  checklist:");
+            for (index, entry) in self.checklist.iter().enumerate() {
+                let mark = if entry.done { "x" } else { " " };
+                println!("    {} [{}] {}", index, mark, entry.text);
+            }
+        }
+        if !self.comments.is_empty() {
+            println!("  comments:");
+            for comment in &self.comments {
+                println!("    {}: {}", comment.at, comment.text);
+            }
+        }
+        if !self.attachments.is_empty() {
+            println!("  attachments:");
+            for (index, attachment) in self.attachments.iter().enumerate() {
+                println!("    {} {}", index, attachment);
+            }
+        }
+        if let Some(sub_list) = &self.sub_list {
+            println!(
+                "  subitems: {} ({} pending)",
+                sub_list.items.len(),
+                sub_list.todo_len()
+            );
+        }
+    }
+
     /// Displays the TodoItem with proper formatting and indentation
     ///
     /// # Arguments
     /// * `depth` - Indentation depth for nested items
-    pub fn display(&self, depth: usize) {
-        let status = if self.completed { " | ✓" } else { "" };
-        println!("{}{}{}", "  ".repeat(depth), self.format_info(), status);
+    /// * `inherited` - Deadline of the closest item it belongs to, shown
+    ///   instead of a missing one when `options.inherit_deadlines` is set
+    /// * `options` - Which subtasks to show and how
+    pub fn display(&self, depth: usize, inherited: Option<&str>, options: &ListOptions) {
+        let status = if self.is_completed() {
+            " | ✓"
+        } else if self.is_started() {
+            " | ▶"
+        } else {
+            ""
+        };
+        let progress = match &self.sub_list {
+            Some(sub_list) if options.progress && !sub_list.items.is_empty() => {
+                let (done, total) = sub_list.progress();
+                format!(" {}", progress_bar(done, total, 10))
+            }
+            _ => String::new(),
+        };
+        let effective = self.deadline.as_deref().or(inherited);
+        let inherited = match inherited.and_then(parse_timestamp) {
+            Some(time) if self.deadline.is_none() && !self.is_completed() => {
+                format!(" | deadline: {} (inherited)", time.format("%Y-%m-%d %H:%M"))
+            }
+            _ => String::new(),
+        };
+        println!(
+            "{}{}{}{}{}",
+            "  ".repeat(depth),
+            self.format_info(),
+            inherited,
+            progress,
+            status
+        );
+        if options.depth.is_some_and(|max| depth >= max) {
+            return;
+        }
         if let Some(sub_list) = &self.sub_list {
-            let items = sub_list.list_items();
-            for item in items {
-                item.display(depth + 1);
+            let inherited = effective.filter(|_| options.inherit_deadlines);
+            for item in sub_list.list_items(options) {
+                item.display(depth + 1, inherited, options);
             }
         };
     }
@@ -269,22 +1552,356 @@ impl fmt::Display for TodoItem {
 #[cfg(test)]
 mod todo_list_tests {
     use super::*;
-    use crate::utils::{expand_path, parse_deadline, save_todo_list};
+    use crate::config::DatesConfig;
+    use crate::utils::{load_todo_list, parse_deadline, save_todo_list};
+    use proptest::prelude::*;
 
     #[test]
     fn create() -> Result<()> {
         let mut list = TodoList::new();
         list.add_item("test1".to_string(), None, None)?;
         list.add_item("test2".to_string(), None, None)?;
-        let time = parse_deadline(Some("today".to_string()))?;
+        let time = parse_deadline(Some("today".to_string()), &DatesConfig::default())?;
         list.add_item("test3".to_string(), Some(time), None)?;
         list.add_item("test4".to_string(), None, None)?;
-        // list.add_item("test5".to_string(), None, Some(0))?;
-        let path_str = "~/.todo".to_string();
-        let path = expand_path(&path_str)?;
+        list.add_item("test5".to_string(), None, Some(&"0".to_string()))?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("todo.json");
+
+        save_todo_list(&path, &list, &Default::default())?;
+        let loaded = load_todo_list(&path)?;
+        assert_eq!(loaded.items.len(), 4);
+        assert_eq!(loaded.find_by_path("0:0").unwrap().description, "test5");
+        Ok(())
+    }
+
+    #[test]
+    fn restore_under_recycled_id() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("parent".to_string(), None, None)?;
+        list.add_item("child".to_string(), None, Some(&"0".to_string()))?;
+        let child = list.trash_item("0:0")?.id;
+        list.trash_item("0")?;
+        // the new item takes the ID the parent had
+        list.add_item("stranger".to_string(), None, None)?;
+        assert_eq!(list.restore_item(child)?, "1");
+        assert!(list.items[0].sub_list.is_none());
+
+        let mut list = TodoList::new();
+        list.add_item("parent".to_string(), None, None)?;
+        list.add_item("child".to_string(), None, Some(&"0".to_string()))?;
+        let child = list.trash_item("0:0")?.id;
+        assert_eq!(list.restore_item(child)?, "0:0");
+        Ok(())
+    }
+
+    proptest! {
+        /// Acquires IDs, or releases one in use when `release` is set, checking
+        /// the pool against the IDs actually handed out after every step
+        #[test]
+        fn id_pool_invariants(
+            monotonic in any::<bool>(),
+            steps in prop::collection::vec((any::<bool>(), any::<prop::sample::Index>()), 0..100),
+        ) {
+            let mut pool = IdPool::default();
+            pool.set_monotonic(monotonic);
+            let mut in_use = BTreeSet::new();
+            let mut handed_out = BTreeSet::new();
+            for (release, index) in steps {
+                if release && !in_use.is_empty() {
+                    let id = *in_use.iter().nth(index.index(in_use.len())).unwrap();
+                    pool.release_id(id).unwrap();
+                    in_use.remove(&id);
+                    prop_assert!(pool.release_id(id).is_err());
+                } else {
+                    let id = pool.acquire_id();
+                    prop_assert!(in_use.insert(id), "ID {} handed out twice", id);
+                    prop_assert!(!monotonic || handed_out.insert(id), "ID {} reused", id);
+                    handed_out.insert(id);
+                }
+                prop_assert_eq!(pool.inconsistencies(&in_use), Vec::<String>::new());
+                prop_assert_eq!(pool.rebuild(&in_use).inconsistencies(&in_use), Vec::<String>::new());
+            }
+        }
+    }
+
+    #[test]
+    fn duplicate() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("checklist".to_string(), None, None)?;
+        list.add_item("step".to_string(), None, Some(&"0".to_string()))?;
+        list.complete_item(&"0:0".to_string())?;
+
+        let path = list.duplicate_item(&"0".to_string(), None, true)?;
+        assert_eq!(path, "1");
+        let copy = list.parse_path(&path)?;
+        let sub_list = copy.sub_list.as_ref().unwrap();
+        assert_eq!(sub_list.items[0].id, 0);
+        assert!(!sub_list.items[0].is_completed());
+
+        let path = list.duplicate_item(&"0:0".to_string(), Some(&"1".to_string()), false)?;
+        assert_eq!(path, "1:1");
+        Ok(())
+    }
+
+    #[test]
+    fn renumber() -> Result<()> {
+        let mut list = TodoList::new();
+        for description in ["a", "b", "c"] {
+            list.add_item(description.to_string(), None, None)?;
+        }
+        list.add_item("child".to_string(), None, Some(&"2".to_string()))?;
+        list.remove_item("0")?;
+
+        let mapping = list.renumber(true);
+        assert_eq!(
+            mapping,
+            vec![
+                ("1".to_string(), "0".to_string()),
+                ("2".to_string(), "1".to_string()),
+                ("2:0".to_string(), "1:0".to_string()),
+            ]
+        );
+        assert_eq!(list.add_item("d".to_string(), None, None)?.id, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn retag() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("a".to_string(), None, None)?.tags = vec!["wrk".to_string()];
+        list.add_item("b".to_string(), None, Some(&"0".to_string()))?
+            .tags = vec!["wrk".to_string(), "work".to_string()];
+
+        assert_eq!(list.retag("wrk", Some("work")), 2);
+        assert_eq!(list.tag_counts().get("work"), Some(&2));
+        assert_eq!(list.parse_path(&"0:0".to_string())?.tags, vec!["work"]);
+        assert_eq!(list.retag("work", None), 2);
+        assert!(list.tag_counts().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn links() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("a".to_string(), None, None)?;
+        list.add_item("b".to_string(), None, Some(&"0".to_string()))?;
+        list.add_item("c".to_string(), None, None)?;
+        list.link_items(&"0:0".to_string(), &"1".to_string(), true)?;
+        list.link_items(&"0:0".to_string(), &"1".to_string(), true)?;
+
+        let uuid = list.parse_path(&"0:0".to_string())?.related[0].clone();
+        let (path, item) = list.find_by_uuid(&uuid).unwrap();
+        assert_eq!((path.as_str(), item.related.len()), ("1", 1));
+
+        list.link_items(&"1".to_string(), &"0:0".to_string(), false)?;
+        assert!(list.parse_path(&"0:0".to_string())?.related.is_empty());
+        assert!(list
+            .link_items(&"1".to_string(), &"1".to_string(), true)
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn dirty() -> Result<()> {
+        let mut list = TodoList::new();
+        assert!(list.is_dirty());
+        list.add_item("a".to_string(), None, None)?;
+        list.mark_clean();
+        assert!(!list.is_dirty());
+        assert_eq!(list.retag("missing", None), 0);
+        assert!(!list.is_dirty());
+        list.complete_item(&"0".to_string())?;
+        assert!(list.is_dirty());
+        Ok(())
+    }
+
+    #[test]
+    fn diagnose() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("a".to_string(), None, None)?;
+        list.add_item("b".to_string(), None, None)?;
+        list.add_item("c".to_string(), None, Some(&"1".to_string()))?;
+        list.items[1].id = 0;
+        list.items[0].deadline = Some("someday".to_string());
+        list.items[0].sub_list = Some(TodoList::new());
+        list.items[0]
+            .sub_list
+            .as_mut()
+            .unwrap()
+            .id_pool
+            .acquire_id();
+
+        assert_eq!(list.diagnose(false).len(), 4);
+        list.diagnose(true);
+        assert!(list.diagnose(false).is_empty());
+        assert_eq!(list.items[1].id, 1);
+        assert!(list.parse_path(&"1:0".to_string()).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn recursive() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("a".to_string(), None, None)?;
+        list.add_item("b".to_string(), None, Some(&"0".to_string()))?;
+        list.add_item("c".to_string(), None, Some(&"0:0".to_string()))?;
+        list.add_item("d".to_string(), None, None)?;
+        let order: Vec<(Vec<usize>, &str)> = list
+            .iter()
+            .map(|(path, item)| (path, item.description.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                (vec![0], "a"),
+                (vec![0, 0], "b"),
+                (vec![0, 0, 0], "c"),
+                (vec![1], "d")
+            ]
+        );
+        assert_eq!(list.pending_count(), 4);
+        let c = list.find_by_path(&normalize_path("0.0:0")).unwrap();
+        assert_eq!(c.description, "c");
+        assert_eq!(list.find_by_uuid(&c.uuid).unwrap().0, "0:0:0");
+        assert!(list.find_by_path("0:1").is_none() && list.find_by_path("").is_none());
+        assert_eq!(list.complete_item_recursive(&"0".to_string())?, (2, None));
+        assert_eq!(list.pending_count(), 1);
+        Ok(())
+    }
 
-        save_todo_list(&path, &list)?;
+    #[test]
+    fn dotted_paths() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("a".to_string(), None, None)?;
+        list.add_item("b".to_string(), None, Some(&"0".to_string()))?;
+        list.add_item("c".to_string(), None, Some(&normalize_path("0.0")))?;
+        assert_eq!(list.parse_path(&"0:0:0".to_string())?.description, "c");
+        assert_eq!(list.find_matches("0.0.")[0].0, "0:0:0");
+        // the guard sees the same separators as the path it protects
+        assert!(list
+            .move_item(&normalize_path("0"), Some(&normalize_path("0.0")))
+            .is_err());
+        assert_eq!(list.remove_item(&normalize_path("0.0.0"))?.description, "c");
+        assert!(list.parse_path(&normalize_path("0..0")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn priority_inheritance() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("launch".to_string(), None, None)?.priority = Some(Priority::High);
+        list.add_item("slides".to_string(), None, Some(&"0".to_string()))?;
+        list.add_item("draft".to_string(), None, Some(&"0:0".to_string()))?;
+        list.add_item("snacks".to_string(), None, Some(&"0".to_string()))?
+            .priority = Some(Priority::Low);
+        assert_eq!(list.effective_priority(&[0, 0, 0]), Some(Priority::High));
+        assert_eq!(list.effective_priority(&[0, 1]), Some(Priority::Low));
+        assert_eq!(list.effective_priority(&[1]), None);
+
+        let now = Local::now();
+        let draft = list.parse_path(&"0:0:0".to_string())?;
+        assert!(draft.urgency(now, Some(Priority::High)) > draft.urgency(now, None));
+        Ok(())
+    }
+
+    #[test]
+    fn late_subtasks() -> Result<()> {
+        let now = Local::now();
+        let day = chrono::Duration::days(1);
+        let mut list = TodoList::new();
+        list.add_item("trip".to_string(), Some(now + day), None)?;
+        list.add_item("pack".to_string(), None, Some(&"0".to_string()))?;
+        list.add_item(
+            "socks".to_string(),
+            Some(now + day * 2),
+            Some(&"0:0".to_string()),
+        )?;
+        list.add_item(
+            "done".to_string(),
+            Some(now + day * 3),
+            Some(&"0".to_string()),
+        )?;
+        list.complete_item(&"0:1".to_string())?;
+        assert_eq!(
+            list.late_subtasks(),
+            [("0:0:0".to_string(), "0".to_string())]
+        );
+        // deadlines the user chose are only reported, never repaired
+        let deadline = list.parse_path(&"0:0:0".to_string())?.deadline.clone();
+        assert!(list.diagnose(true).is_empty());
+        assert_eq!(list.parse_path(&"0:0:0".to_string())?.deadline, deadline);
+        Ok(())
+    }
+
+    #[test]
+    fn monotonic_ids() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("a".to_string(), None, None)?;
+        list.add_item("b".to_string(), None, None)?;
+        list.remove_item("0")?;
+        list.set_monotonic_ids(true);
+        assert_eq!(list.add_item("c".to_string(), None, None)?.id, 2);
+        list.remove_item("2")?;
+        assert_eq!(list.add_item("d".to_string(), None, None)?.id, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn list_options() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("plan trip".to_string(), None, None)?;
+        list.add_item("book hotel".to_string(), None, Some(&"0".to_string()))?;
+        list.add_item("water plants".to_string(), None, None)?;
+        list.complete_item(&"1".to_string())?;
+        let listed = |options: &ListOptions| -> Vec<String> {
+            list.list_items(options)
+                .iter()
+                .map(|item| item.description.clone())
+                .collect()
+        };
+        assert_eq!(listed(&ListOptions::default()), ["plan trip"]);
+        let all = ListOptions {
+            include_completed: true,
+            sort: Some(SortKey::Alpha),
+            reverse: true,
+            ..Default::default()
+        };
+        assert_eq!(listed(&all), ["water plants", "plan trip"]);
+        // a parent is listed for a matching subtask
+        let hotel = ListOptions {
+            filter: Some(Filter::parse("hotel", &DatesConfig::default())?),
+            ..Default::default()
+        };
+        assert_eq!(listed(&hotel), ["plan trip"]);
+        let done = ListOptions {
+            filter: Some(Filter::parse("done", &DatesConfig::default())?),
+            ..Default::default()
+        };
+        assert_eq!(listed(&done), ["water plants"]);
+        Ok(())
+    }
+
+    #[test]
+    fn take_completed() -> Result<()> {
+        let mut list = TodoList::new();
+        list.add_item("parent".to_string(), None, None)?;
+        list.add_item("open".to_string(), None, Some(&"0".to_string()))?;
+        list.add_item("finished".to_string(), None, Some(&"0".to_string()))?;
+        list.add_item("old".to_string(), None, None)?;
+        for path in ["0", "0:1", "1"] {
+            list.complete_item(&path.to_string())?;
+        }
+        let cutoff = Local::now() + chrono::Duration::minutes(1);
 
+        let taken: Vec<String> = list
+            .take_completed_before(cutoff)
+            .into_iter()
+            .map(|item| item.description)
+            .collect();
+        // the parent stays with its pending subtask, its finished one goes
+        assert_eq!(taken, ["finished", "old"]);
+        assert!(list.find_by_path("0:0").is_some());
         Ok(())
     }
 }
@@ -0,0 +1,492 @@
+use crate::config::DatesConfig;
+use crate::exchange::{parse_due, Node};
+use crate::todo_list::{Comment, Priority, TodoItem, TodoList};
+use crate::utils::{parse_deadline, parse_timestamp};
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{fs, path::Path};
+
+/// Project that collects the top-level items without sub items on export
+const INBOX: &str = "Inbox";
+
+/// Deepest indent Todoist supports
+const MAX_INDENT: usize = 4;
+
+/// Splits the `@label` words off the content of a task
+fn split_labels(content: &str) -> (String, Vec<String>) {
+    let (labels, words): (Vec<&str>, Vec<&str>) = content
+        .split_whitespace()
+        .partition(|word| word.len() > 1 && word.starts_with('@'));
+    let labels = labels.iter().map(|label| label[1..].to_string()).collect();
+    (words.join(" "), labels)
+}
+
+/// Builds an item from the fields Todoist tasks have in both formats
+fn task(
+    content: &str,
+    description: &str,
+    priority: Option<Priority>,
+    due: Option<&str>,
+    dates: &DatesConfig,
+) -> TodoItem {
+    let (content, tags) = split_labels(content);
+    let mut item = TodoItem::new(content);
+    item.tags = tags;
+    item.priority = priority;
+    if !description.is_empty() {
+        item.comments.push(Comment {
+            at: Local::now().to_string(),
+            text: description.to_string(),
+        });
+    }
+    if let Some(due) = due.filter(|due| !due.is_empty()) {
+        match parse_due(due).or_else(|| parse_deadline(Some(due.to_string()), dates).ok()) {
+            Some(deadline) => item.set_deadline(Some(deadline)),
+            None => eprintln!("Ignoring the due date '{}' of '{}'", due, item.description),
+        }
+    }
+    item
+}
+
+/// Imports a project exported as CSV, named after the file
+///
+/// Sections become items of the project, tasks go below the section before them
+/// at their indent, and notes become comments of the task before them
+pub fn import_csv(path: &Path, dates: &DatesConfig) -> Result<TodoItem> {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or(INBOX.to_string());
+    let mut project = Node::new(TodoItem::new(name));
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let (kind, content) = column("TYPE")
+        .zip(column("CONTENT"))
+        .with_context(|| format!("{} is no Todoist CSV file", path.display()))?;
+    let (description, priority, indent, responsible, date) = (
+        column("DESCRIPTION"),
+        column("PRIORITY"),
+        column("INDENT"),
+        column("RESPONSIBLE"),
+        column("DATE"),
+    );
+
+    let mut in_section = false;
+    for record in reader.records() {
+        let record = record?;
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|index| record.get(index))
+                .unwrap_or_default()
+        };
+        match field(Some(kind)) {
+            "section" => {
+                project
+                    .children
+                    .push(Node::new(TodoItem::new(field(Some(content)).to_string())));
+                in_section = true;
+            }
+            "task" => {
+                // 1 is the highest priority, 4 none
+                let priority = match field(priority) {
+                    "1" => Some(Priority::High),
+                    "2" => Some(Priority::Medium),
+                    "3" => Some(Priority::Low),
+                    _ => None,
+                };
+                let mut item = task(
+                    field(Some(content)),
+                    field(description),
+                    priority,
+                    Some(field(date)),
+                    dates,
+                );
+                item.assignee = Some(field(responsible).to_string()).filter(|who| !who.is_empty());
+                let depth = field(indent).parse::<usize>().unwrap_or(1).max(1) - 1;
+                project.insert(depth + usize::from(in_section), Node::new(item));
+            }
+            "note" => {
+                let mut last = &mut project;
+                while let Some(child) = last.children.last_mut() {
+                    last = child;
+                }
+                last.item.comments.push(Comment {
+                    at: Local::now().to_string(),
+                    text: field(Some(content)).to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(project.into_item())
+}
+
+/// A Todoist backup as JSON, in the shape of the Sync or REST API
+#[derive(Deserialize)]
+struct Backup {
+    #[serde(default)]
+    projects: Vec<Project>,
+    #[serde(default)]
+    sections: Vec<Section>,
+    #[serde(default, alias = "tasks")]
+    items: Vec<Task>,
+}
+
+#[derive(Deserialize)]
+struct Project {
+    id: String,
+    name: String,
+    #[serde(default)]
+    parent_id: Option<String>,
+    #[serde(default, alias = "order")]
+    child_order: i64,
+}
+
+#[derive(Deserialize)]
+struct Section {
+    id: String,
+    project_id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Task {
+    id: String,
+    content: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    project_id: Option<String>,
+    #[serde(default)]
+    section_id: Option<String>,
+    #[serde(default)]
+    parent_id: Option<String>,
+    /// 4 is the highest priority, 1 none
+    #[serde(default)]
+    priority: u8,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    due: Option<Due>,
+    #[serde(default, alias = "is_completed")]
+    checked: bool,
+    #[serde(default)]
+    completed_at: Option<String>,
+    #[serde(default, alias = "order")]
+    child_order: i64,
+}
+
+#[derive(Deserialize)]
+struct Due {
+    date: String,
+}
+
+/// Imports the projects of a JSON backup, each becoming a top-level item
+pub fn import_json(path: &Path, dates: &DatesConfig) -> Result<Vec<TodoItem>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let backup: Backup = serde_json::from_str(&content)
+        .with_context(|| format!("{} is no Todoist JSON backup", path.display()))?;
+
+    // tasks without a section come before the sections, like Todoist shows them
+    let mut projects = backup.projects;
+    projects.sort_by_key(|project| project.child_order);
+    let mut tasks = backup.items;
+    tasks.sort_by_key(|task| task.child_order);
+    let mut entries = Vec::new();
+    for project in projects {
+        let item = TodoItem::new(project.name);
+        entries.push((project.id, project.parent_id, item));
+    }
+    for task in tasks {
+        let priority = match task.priority {
+            4 => Some(Priority::High),
+            3 => Some(Priority::Medium),
+            2 => Some(Priority::Low),
+            _ => None,
+        };
+        let due = task.due.as_ref().map(|due| due.date.as_str());
+        let mut item = self::task(&task.content, &task.description, priority, due, dates);
+        item.tags.extend(task.labels);
+        if task.checked {
+            item.complete();
+            if let Some(time) = task.completed_at.as_deref().and_then(parse_due) {
+                item.completed_at = Some(time.to_string());
+            }
+        }
+        let parent = task.parent_id.or(task.section_id).or(task.project_id);
+        entries.push((task.id, parent, item));
+    }
+    for section in backup.sections {
+        let item = TodoItem::new(section.name);
+        entries.push((section.id, Some(section.project_id), item));
+    }
+    Ok(children(&entries, None))
+}
+
+/// An imported item with its Todoist ID and the ID of its parent
+type Entry = (String, Option<String>, TodoItem);
+
+/// Returns the entries below `parent` with their sub items, or the top-level
+/// ones, whose parent is missing, for None
+fn children(entries: &[Entry], parent: Option<&str>) -> Vec<TodoItem> {
+    let is_child = |entry_parent: &Option<String>| match (parent, entry_parent) {
+        (Some(parent), Some(entry_parent)) => parent == entry_parent,
+        (None, None) => true,
+        (None, Some(entry_parent)) => !entries.iter().any(|(id, _, _)| id == entry_parent),
+        (Some(_), None) => false,
+    };
+    entries
+        .iter()
+        .filter(|(_, entry_parent, _)| is_child(entry_parent))
+        .map(|(id, _, item)| {
+            let mut item = item.clone();
+            let sub_items = children(entries, Some(id));
+            if !sub_items.is_empty() {
+                let mut sub_list = TodoList::new();
+                for sub_item in sub_items {
+                    sub_list.push_item(sub_item);
+                }
+                item.sub_list = Some(sub_list);
+            }
+            item
+        })
+        .collect()
+}
+
+/// Imports a Todoist file, a project as CSV or a whole backup as JSON
+pub fn import(path: &Path, dates: &DatesConfig) -> Result<Vec<TodoItem>> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => import_json(path, dates),
+        _ => Ok(vec![import_csv(path, dates)?]),
+    }
+}
+
+/// Returns the projects to export, top-level items with sub items, and the
+/// remaining top-level items collected in an inbox project
+fn projects(todo_list: &TodoList) -> Vec<(String, Vec<&TodoItem>)> {
+    let mut projects = Vec::new();
+    let mut inbox = Vec::new();
+    for item in &todo_list.items {
+        match &item.sub_list {
+            Some(sub_list) if !sub_list.items.is_empty() => {
+                projects.push((item.description.clone(), sub_list.items.iter().collect()))
+            }
+            _ => inbox.push(item),
+        }
+    }
+    if !inbox.is_empty() {
+        projects.insert(0, (INBOX.to_string(), inbox));
+    }
+    projects
+}
+
+/// Returns the content of a task, the description followed by its tags as labels
+fn content(item: &TodoItem) -> String {
+    let mut content = item.description.clone();
+    for tag in &item.tags {
+        content.push_str(&format!(" @{}", tag));
+    }
+    content
+}
+
+/// Writes the pending items of a project as a Todoist CSV template
+fn write_csv(writer: &mut csv::Writer<fs::File>, items: &[&TodoItem], indent: usize) -> Result<()> {
+    for item in items.iter().filter(|item| !item.is_completed()) {
+        let priority = match item.priority {
+            Some(Priority::High) => "1",
+            Some(Priority::Medium) => "2",
+            Some(Priority::Low) => "3",
+            None => "4",
+        };
+        let date = item
+            .deadline_time()
+            .map(|deadline| deadline.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let indent_field = indent.min(MAX_INDENT).to_string();
+        writer.write_record([
+            "task",
+            &content(item),
+            "",
+            priority,
+            &indent_field,
+            "",
+            item.assignee.as_deref().unwrap_or_default(),
+            &date,
+            "en",
+            "",
+        ])?;
+        for comment in &item.comments {
+            writer.write_record(["note", &comment.text, "", "", "", "", "", "", "", ""])?;
+        }
+        if let Some(sub_list) = &item.sub_list {
+            let children: Vec<&TodoItem> = sub_list.items.iter().collect();
+            write_csv(writer, &children, indent + 1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes one CSV file per project into `dir`, the layout of a Todoist backup
+pub fn export_csv(todo_list: &TodoList, dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    for (name, items) in projects(todo_list) {
+        let file_name = name.replace(['/', '\\'], "-");
+        let mut writer = csv::Writer::from_path(dir.join(format!("{}.csv", file_name)))?;
+        writer.write_record([
+            "TYPE",
+            "CONTENT",
+            "DESCRIPTION",
+            "PRIORITY",
+            "INDENT",
+            "AUTHOR",
+            "RESPONSIBLE",
+            "DATE",
+            "DATE_LANG",
+            "TIMEZONE",
+        ])?;
+        write_csv(&mut writer, &items, 1)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Adds the tasks of a project to a JSON backup, depth-first
+fn add_tasks(tasks: &mut Vec<Value>, items: &[&TodoItem], project: &str, parent: Option<&str>) {
+    for (order, item) in items.iter().enumerate() {
+        let priority = match item.priority {
+            Some(Priority::High) => 4,
+            Some(Priority::Medium) => 3,
+            Some(Priority::Low) => 2,
+            None => 1,
+        };
+        let due = item.deadline_time().map(|deadline| {
+            json!({ "date": deadline.naive_local().format("%Y-%m-%dT%H:%M:%S").to_string() })
+        });
+        let completed_at = item
+            .completed_at
+            .as_deref()
+            .and_then(parse_timestamp)
+            .filter(|_| item.is_completed())
+            .map(|time| time.to_rfc3339());
+        tasks.push(json!({
+            "id": item.uuid,
+            "project_id": project,
+            "parent_id": parent,
+            "content": item.description,
+            "priority": priority,
+            "labels": item.tags,
+            "due": due,
+            "checked": item.is_completed(),
+            "completed_at": completed_at,
+            "child_order": order + 1,
+        }));
+        if let Some(sub_list) = &item.sub_list {
+            let children: Vec<&TodoItem> = sub_list.items.iter().collect();
+            add_tasks(tasks, &children, project, Some(&item.uuid));
+        }
+    }
+}
+
+/// Renders the list as a JSON backup with projects and items
+pub fn export_json(todo_list: &TodoList) -> Value {
+    let mut projects = Vec::new();
+    let mut tasks = Vec::new();
+    for (order, (name, items)) in self::projects(todo_list).into_iter().enumerate() {
+        let id = format!("project-{}", order + 1);
+        add_tasks(&mut tasks, &items, &id, None);
+        projects.push(json!({ "id": id, "name": name, "child_order": order + 1 }));
+    }
+    json!({ "projects": projects, "sections": [], "items": tasks })
+}
+
+#[cfg(test)]
+mod todoist_tests {
+    use super::*;
+
+    #[test]
+    fn csv_roundtrip() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        let path = dir.join("Work.csv");
+        fs::write(
+            &path,
+            "TYPE,CONTENT,DESCRIPTION,PRIORITY,INDENT,AUTHOR,RESPONSIBLE,DATE,DATE_LANG,TIMEZONE\n\
+             task,Plan @office,,1,1,,,2024-05-01,en,\n\
+             task,Draft,,4,2,,,,en,\n\
+             note,see the wiki,,,,,,,,\n\
+             ,,,,,,,,,\n\
+             section,Later,,,,,,,,\n\
+             task,Review,,4,1,,,,en,\n",
+        )?;
+        let project = import_csv(&path, &DatesConfig::default())?;
+        assert_eq!(project.description, "Work");
+        let items = &project.sub_list.as_ref().unwrap().items;
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].description, "Plan");
+        assert_eq!(items[0].tags, ["office"]);
+        assert_eq!(items[0].priority, Some(Priority::High));
+        assert!(items[0].deadline.is_some());
+        let draft = &items[0].sub_list.as_ref().unwrap().items[0];
+        assert_eq!(draft.comments[0].text, "see the wiki");
+        assert_eq!(items[1].description, "Later");
+        assert_eq!(
+            items[1].sub_list.as_ref().unwrap().items[0].description,
+            "Review"
+        );
+
+        let mut list = TodoList::new();
+        list.push_item(project);
+        let out = dir.join("out");
+        export_csv(&list, &out)?;
+        let exported = fs::read_to_string(out.join("Work.csv"))?;
+        assert!(exported.contains("task,Plan @office,,1,1,,,2024-05-01 23:59,en,"));
+        assert!(exported.contains("task,Review,,4,2,"));
+        Ok(())
+    }
+
+    #[test]
+    fn json_backup() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("todoist.json");
+        fs::write(
+            &path,
+            json!({
+                "projects": [{ "id": "p", "name": "Home" }],
+                "sections": [{ "id": "s", "project_id": "p", "name": "Garden" }],
+                "items": [
+                    { "id": "b", "content": "Buy seeds", "project_id": "p", "section_id": "s", "parent_id": "a" },
+                    { "id": "a", "content": "Plant", "project_id": "p", "section_id": "s", "priority": 4, "labels": ["spring"] },
+                    { "id": "c", "content": "Done", "project_id": "p", "checked": true }
+                ]
+            })
+            .to_string(),
+        )?;
+        let items = import(&path, &DatesConfig::default())?;
+        assert_eq!(items.len(), 1);
+        let home = items[0].sub_list.as_ref().unwrap();
+        assert!(home.items[0].is_completed());
+        assert_eq!(home.items[1].description, "Garden");
+        let plant = &home.items[1].sub_list.as_ref().unwrap().items[0];
+        assert_eq!(plant.tags, ["spring"]);
+        assert_eq!(
+            plant.sub_list.as_ref().unwrap().items[0].description,
+            "Buy seeds"
+        );
+
+        let mut list = TodoList::new();
+        list.push_item(items.into_iter().next().unwrap());
+        let backup = export_json(&list);
+        assert_eq!(backup["projects"][0]["name"], "Home");
+        assert_eq!(backup["items"][2]["content"], "Plant");
+        assert_eq!(backup["items"][2]["priority"], 4);
+        assert_eq!(backup["items"][3]["parent_id"], backup["items"][2]["id"]);
+        Ok(())
+    }
+}
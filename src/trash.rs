@@ -0,0 +1,83 @@
+use crate::errors;
+use crate::todo_list::{IdPool, TodoItem};
+use crate::utils::parse_timestamp;
+use chrono::{DateTime, Local};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A removed item kept for restoring, together with where it came from
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TrashEntry {
+    pub id: usize,
+    /// Path of the item before it was removed
+    pub original_path: String,
+    /// uuid of the item's parent, as IDs in the path may be taken by other items since
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_uuid: Option<String>,
+    pub removed_at: String,
+    pub item: TodoItem,
+}
+
+/// Removed items, stored alongside the root todo list
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+pub struct Trash {
+    #[serde(default)]
+    pub entries: Vec<TrashEntry>,
+    #[serde(default)]
+    id_pool: IdPool,
+}
+
+impl Trash {
+    /// Returns true if the trash holds no items
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Puts a removed item into the trash
+    pub fn push(
+        &mut self,
+        original_path: String,
+        parent_uuid: Option<String>,
+        item: TodoItem,
+    ) -> &TrashEntry {
+        let id = self.id_pool.acquire_id();
+        self.entries.push(TrashEntry {
+            id,
+            original_path,
+            parent_uuid,
+            removed_at: Local::now().to_string(),
+            item,
+        });
+        &self.entries[self.entries.len() - 1]
+    }
+
+    /// Takes an entry out of the trash by its trash ID
+    pub fn take(&mut self, id: usize) -> anyhow::Result<TrashEntry> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or_else(|| errors::not_found(format!("Trash entry with ID {} not found", id)))?;
+        self.id_pool.release_id(id)?;
+        Ok(self.entries.remove(index))
+    }
+
+    /// Permanently deletes entries removed before `before`, or all entries if None
+    ///
+    /// Returns the number of deleted entries
+    pub fn empty(&mut self, before: Option<DateTime<Local>>) -> usize {
+        let (expired, kept): (Vec<_>, Vec<_>) =
+            self.entries.drain(..).partition(|entry| match before {
+                Some(before) => {
+                    parse_timestamp(&entry.removed_at).is_none_or(|removed| removed < before)
+                }
+                None => true,
+            });
+        self.entries = kept;
+        for entry in &expired {
+            // entries are always registered in the pool, releasing cannot fail
+            let _ = self.id_pool.release_id(entry.id);
+        }
+        expired.len()
+    }
+}
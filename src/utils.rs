@@ -1,38 +1,365 @@
-use crate::todo_list::TodoList;
+use crate::config::{DatesConfig, StorageConfig, StorageFormat, SyntaxConfig};
+use crate::errors;
+use crate::journal;
+use crate::todo_list::{normalize_path, Priority, TodoItem, TodoList};
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local, NaiveDateTime, ParseResult};
+use chrono::{
+    DateTime, Datelike, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, Weekday,
+};
+use dialoguer::{Confirm, FuzzySelect, MultiSelect};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::Serialize;
+use serde_json::{Map, Value};
 use std::{
+    collections::{hash_map::RandomState, HashSet},
     env,
-    fs::{File, OpenOptions},
-    io::{BufReader, BufWriter},
-    path::PathBuf,
+    fs::{self, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{self, BufRead, BufWriter, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
 };
 
-/// Loads a TodoList from a JSON file, or creates a new one if the file doesn't exist
-pub fn load_todo_list(file_path: &PathBuf) -> Result<TodoList> {
+/// Current version of the on-disk format, stored in the root list
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Migrations upgrading a file of version `index` to version `index + 1`
+const MIGRATIONS: [fn(&mut Value); SCHEMA_VERSION as usize] = [migrate_status, migrate_deadlines];
+
+/// Reads a todo file as JSON or MessagePack, telling them apart by the first byte
+///
+/// Gzip-compressed files are decompressed first, whatever their name
+pub fn read_value(file_path: &Path) -> Result<Value> {
+    let mut content = fs::read(file_path)?;
+    if content.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(content.as_slice()).read_to_end(&mut decompressed)?;
+        content = decompressed;
+    }
+    let is_json = content
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .is_none_or(|byte| *byte == b'{');
+    if is_json {
+        Ok(serde_json::from_slice(&content)?)
+    } else {
+        Ok(rmp_serde::from_slice(&content)?)
+    }
+}
+
+/// Loads a TodoList from a JSON or MessagePack file, or creates a new one if the file doesn't exist
+///
+/// Files written by older versions are migrated to the current format, and changes
+/// recorded in `<file>.journal` are applied on top
+pub fn load_todo_list(file_path: &Path) -> Result<TodoList> {
     if file_path.exists() {
-        let file = File::open(file_path)?;
-        let reader = BufReader::new(file);
-        let todo_list = serde_json::from_reader(reader)?;
+        let mut value = read_value(file_path)?;
+        let version = migrate(&mut value)?;
+        journal::replay(file_path, &mut value)?;
+        let mut todo_list: TodoList = serde_json::from_value(value)?;
+        todo_list.mark_clean();
+        todo_list.version = version;
         Ok(todo_list)
     } else {
-        Ok(TodoList::new())
+        let mut todo_list = TodoList::new();
+        todo_list.version = SCHEMA_VERSION;
+        Ok(todo_list)
+    }
+}
+
+/// Reads a TodoList like `load_todo_list` without writing anything, an unreadable
+/// journal tail is left out instead of cut off
+pub fn read_todo_list(file_path: &Path) -> Result<TodoList> {
+    if !file_path.exists() {
+        return Ok(TodoList::new());
+    }
+    let mut value = read_value(file_path)?;
+    migrate(&mut value)?;
+    for entry in journal::read_journal(file_path)? {
+        journal::apply(&mut value, &entry.patch);
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Loads a TodoList like `load_todo_list`, offering to recover when the file is corrupt
+///
+/// The corrupt file is first copied aside, then the user may restore the backup
+/// kept by `save_todo_list`, or continue with the items that could still be read.
+/// Without a terminal to ask on, loading fails and the file is left untouched
+pub fn load_or_recover(file_path: &PathBuf, storage: &StorageConfig) -> Result<TodoList> {
+    let err = match load_todo_list(file_path) {
+        Ok(todo_list) => return Ok(todo_list),
+        Err(err)
+            if err.downcast_ref::<serde_json::Error>().is_none()
+                && err.downcast_ref::<rmp_serde::decode::Error>().is_none() =>
+        {
+            return Err(err)
+        }
+        Err(err) => err,
+    };
+    let aside = sibling_path(
+        file_path,
+        &format!(".corrupt-{}", Local::now().format("%Y%m%d%H%M%S")),
+    );
+    fs::copy(file_path, &aside)?;
+    eprintln!("Failed to load {}: {}", file_path.display(), err);
+    eprintln!("Kept a copy of the corrupt file at {}", aside.display());
+    if !io::stdin().is_terminal() {
+        return Err(err.context("Todo file is corrupt, run td in a terminal to recover"));
+    }
+
+    let backup = backup_path(file_path);
+    if let Some(todo_list) = backup
+        .exists()
+        .then(|| load_todo_list(&backup).ok())
+        .flatten()
+    {
+        let restore = Confirm::new()
+            .with_prompt(format!(
+                "Restore the backup {} ({} items)?",
+                backup.display(),
+                todo_list.items.len()
+            ))
+            .default(true)
+            .interact()?;
+        if restore {
+            fs::copy(&backup, file_path)?;
+            return Ok(todo_list);
+        }
+    }
+    if let Some(mut todo_list) = salvage_todo_list(file_path) {
+        let salvage = Confirm::new()
+            .with_prompt(format!(
+                "Continue with the {} top level items that could be read?",
+                todo_list.items.len()
+            ))
+            .default(false)
+            .interact()?;
+        if salvage {
+            write_todo_list(file_path, &todo_list, storage)?;
+            todo_list.mark_clean();
+            return Ok(todo_list);
+        }
+    }
+    Err(err.context("Could not recover the todo file"))
+}
+
+/// Reads the items of a damaged file one by one, skipping those that don't parse
+///
+/// ID pools are rebuilt from the salvaged items. Returns None if nothing was readable
+fn salvage_todo_list(file_path: &Path) -> Option<TodoList> {
+    let mut value = read_value(file_path).ok()?;
+    migrate(&mut value).ok()?;
+    let items: Vec<TodoItem> = value
+        .get_mut("items")?
+        .as_array_mut()?
+        .drain(..)
+        .filter_map(|item| serde_json::from_value(item).ok())
+        .collect();
+    if items.is_empty() {
+        return None;
+    }
+    let mut todo_list = TodoList::new();
+    todo_list.version = SCHEMA_VERSION;
+    todo_list.items = items;
+    todo_list.diagnose(true);
+    Some(todo_list)
+}
+
+/// Upgrades a todo file in place to the current format, returning the current version
+///
+/// The list is left marked as saved with its old version, so a migrated file is
+/// rewritten by the next command that saves
+pub fn migrate(root: &mut Value) -> Result<u32> {
+    let version = root.get("version").and_then(Value::as_u64).unwrap_or(0) as usize;
+    if version > SCHEMA_VERSION as usize {
+        return Err(anyhow::anyhow!(
+            "Todo file has version {}, this td only supports up to {}",
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+    for migration in &MIGRATIONS[version..] {
+        migration(root);
     }
+    Ok(SCHEMA_VERSION)
 }
 
-/// Saves a TodoList to a JSON file with pretty formatting
-pub fn save_todo_list(file_path: &PathBuf, todo_list: &TodoList) -> Result<()> {
+/// Calls `f` on every item of a serialized list, including sub lists and trashed items
+fn for_each_item(list: &mut Value, f: &mut dyn FnMut(&mut Map<String, Value>)) {
+    let Some(list) = list.as_object_mut() else {
+        return;
+    };
+    let mut items: Vec<&mut Value> = Vec::new();
+    for (key, value) in list.iter_mut() {
+        match (key.as_str(), value) {
+            ("items", Value::Array(list_items)) => items.extend(list_items.iter_mut()),
+            ("trash", trash) => {
+                if let Some(entries) = trash.get_mut("entries").and_then(Value::as_array_mut) {
+                    items.extend(entries.iter_mut().filter_map(|entry| entry.get_mut("item")));
+                }
+            }
+            _ => {}
+        }
+    }
+    for item in items {
+        let Some(item) = item.as_object_mut() else {
+            continue;
+        };
+        f(item);
+        if let Some(sub_list) = item.get_mut("sub_list") {
+            for_each_item(sub_list, f);
+        }
+    }
+}
+
+/// Version 0 to 1: the `completed` and `started` flags become a single `status`
+fn migrate_status(root: &mut Value) {
+    for_each_item(root, &mut |item| {
+        let flag = |item: &mut Map<String, Value>, key| {
+            item.remove(key).and_then(|flag| flag.as_bool()) == Some(true)
+        };
+        let completed = flag(item, "completed");
+        let started = flag(item, "started");
+        let status = match (completed, started) {
+            (true, _) => "done",
+            (false, true) => "started",
+            (false, false) => "todo",
+        };
+        item.entry("status").or_insert(status.into());
+    });
+}
+
+/// Version 1 to 2: deadlines are stored as RFC 3339
+///
+/// Only the format changes, every deadline keeps its instant. Times typed with
+/// `-d` used to be read as UTC, but nothing in the file tells them apart from
+/// correct ones that importers and repeats stored, so they are left as they are
+fn migrate_deadlines(root: &mut Value) {
+    let migrate = |deadline: &mut Value| {
+        if let Some(time) = deadline
+            .as_str()
+            .and_then(|time| DateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.f %:z").ok())
+        {
+            *deadline = format_deadline(time.with_timezone(&Local)).into();
+        }
+    };
+    for_each_item(root, &mut |item| {
+        if let Some(deadline) = item.get_mut("deadline") {
+            migrate(deadline);
+        }
+    });
+    let milestones = root
+        .get_mut("milestones")
+        .and_then(|milestones| milestones.get_mut("items"))
+        .and_then(Value::as_array_mut);
+    for milestone in milestones.into_iter().flatten() {
+        if let Some(deadline) = milestone.get_mut("deadline") {
+            migrate(deadline);
+        }
+    }
+}
+
+/// Saves a TodoList in the format `storage` picks for the file, JSON being
+/// pretty-printed unless `storage.compact` is set
+///
+/// The previous content of the file is kept as `<file>.bak`, a journal next to it
+/// is removed since the file now holds its changes
+pub fn save_todo_list(
+    file_path: &PathBuf,
+    todo_list: &TodoList,
+    storage: &StorageConfig,
+) -> Result<()> {
+    if file_path.exists() {
+        fs::copy(file_path, backup_path(file_path))?;
+    }
+    write_todo_list(file_path, todo_list, storage)?;
+    let journal = journal::journal_path(file_path);
+    if journal.exists() {
+        fs::remove_file(journal)?;
+    }
+    Ok(())
+}
+
+/// Writes a TodoList without keeping a backup
+fn write_todo_list(file_path: &Path, todo_list: &TodoList, storage: &StorageConfig) -> Result<()> {
     let file = OpenOptions::new()
         .write(true)
         .create(true)
         .truncate(true)
         .open(file_path)?;
 
-    let writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, todo_list)?;
+    let mut content = Vec::new();
+    match storage.format_for(file_path) {
+        StorageFormat::Msgpack => {
+            todo_list.serialize(&mut rmp_serde::Serializer::new(&mut content).with_struct_map())?
+        }
+        StorageFormat::Json if storage.compact => serde_json::to_writer(&mut content, todo_list)?,
+        StorageFormat::Json => serde_json::to_writer_pretty(&mut content, todo_list)?,
+    }
+
+    let mut writer = BufWriter::new(file);
+    if is_gzip_path(file_path) {
+        let mut encoder = GzEncoder::new(&mut writer, Compression::default());
+        encoder.write_all(&content)?;
+        encoder.finish()?;
+    } else {
+        writer.write_all(&content)?;
+    }
+    writer.flush()?;
     Ok(())
 }
 
+/// Returns the todo file path with `suffix` appended to its name
+pub fn sibling_path(file_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = file_path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Returns the path of the backup kept next to a todo file
+pub fn backup_path(file_path: &Path) -> PathBuf {
+    sibling_path(file_path, ".bak")
+}
+
+/// Returns true if files at this path are written gzip-compressed
+pub fn is_gzip_path(file_path: &Path) -> bool {
+    file_path
+        .extension()
+        .is_some_and(|extension| extension == "gz")
+}
+
+/// Returns the path of the archive file kept next to a todo file
+///
+/// The archive of a `.gz` file is compressed too, e.g. `todo.json.archive.gz`
+pub fn archive_path(file_path: &Path) -> PathBuf {
+    if is_gzip_path(file_path) {
+        sibling_path(&file_path.with_extension(""), ".archive.gz")
+    } else {
+        sibling_path(file_path, ".archive")
+    }
+}
+
+/// Appends items to the archive todo list next to `file_path`
+///
+/// The archive is a regular todo file, it can be browsed with `td -f <file>.archive list -a`
+pub fn archive_items(
+    file_path: &Path,
+    items: Vec<TodoItem>,
+    storage: &StorageConfig,
+) -> Result<()> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    let path = archive_path(file_path);
+    let mut archive = load_todo_list(&path)?;
+    for item in items {
+        archive.push_item(item);
+    }
+    save_todo_list(&path, &archive, storage)
+}
+
 /// Expands a path string, replacing '~' with the user's home directory
 pub fn expand_path(path: &String) -> Result<PathBuf> {
     if path.starts_with('~') {
@@ -50,116 +377,1072 @@ pub fn expand_path(path: &String) -> Result<PathBuf> {
     }
 }
 
-/// Parses relative time expressions like "today", "tomorrow", "nextweek", or "+2days"
-fn parse_relative_time(time_str: &str) -> Option<DateTime<Local>> {
-    let now = Local::now();
-
-    match time_str.to_lowercase().as_str() {
-        "today" => Some(
-            now.date_naive()
-                .and_hms_opt(23, 59, 59)?
-                .and_local_timezone(Local)
-                .unwrap(),
-        ),
-        "tomorrow" => Some(
-            (now + chrono::Duration::days(1))
-                .date_naive()
-                .and_hms_opt(23, 59, 59)?
-                .and_local_timezone(Local)
-                .unwrap(),
-        ),
-        "nextweek" => Some(
-            (now + chrono::Duration::weeks(1))
-                .date_naive()
-                .and_hms_opt(23, 59, 59)?
-                .and_local_timezone(Local)
-                .unwrap(),
-        ),
-        _ => {
-            if let Some(duration_str) = time_str.strip_prefix('+') {
-                parse_duration_offset(duration_str, now)
-            } else {
-                None
+/// Names of the todo files looked for in a directory and its parents
+const LOCAL_FILES: [&str; 2] = [".todo", "todo.json"];
+
+/// Keys a serialized root `TodoList` may have
+const LIST_KEYS: [&str; 6] = [
+    "version",
+    "items",
+    "id_pool",
+    "habits",
+    "trash",
+    "milestones",
+];
+
+/// Returns the nearest `.todo` or `todo.json` in `dir` or one of its parents,
+/// like git finds `.git`
+///
+/// `todo.json` is a common name, so one that holds anything but a td list is
+/// an error rather than being taken over
+pub fn find_local_file(dir: &Path) -> Result<Option<PathBuf>> {
+    let Some(path) = dir
+        .ancestors()
+        .flat_map(|dir| LOCAL_FILES.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+    else {
+        return Ok(None);
+    };
+    if path.ends_with(".todo") || is_td_file(&path) {
+        return Ok(Some(path));
+    }
+    Err(errors::refused(format!(
+        "{} is not a td list, pass -f or --global to pick the list to use",
+        path.display()
+    )))
+}
+
+/// Returns true if the file holds an object with only the keys of a td list
+fn is_td_file(path: &Path) -> bool {
+    read_value(path).is_ok_and(|value| {
+        value
+            .as_object()
+            .is_some_and(|root| root.keys().all(|key| LIST_KEYS.contains(&key.as_str())))
+    })
+}
+
+/// Matches a path against a pattern where `*` stands for any characters and
+/// `?` for one, neither crossing a `/`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[char], text: &[char]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some(('*', rest)) => (0..=text.len())
+                .take_while(|skip| !text[..*skip].contains(&'/'))
+                .any(|skip| matches(rest, &text[skip..])),
+            Some(('?', rest)) => text
+                .split_first()
+                .is_some_and(|(c, text)| *c != '/' && matches(rest, text)),
+            Some((c, rest)) => text
+                .split_first()
+                .is_some_and(|(first, text)| first == c && matches(rest, text)),
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&pattern, &text)
+}
+
+/// Formats an ID path as a colon-separated string, e.g. "0:1:2"
+pub fn format_path(path: &[usize]) -> String {
+    path.iter()
+        .map(|index| index.to_string())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Parses a timestamp stored by `DateTime<Local>::to_string`, or an RFC 3339
+/// one like deadlines are stored as
+pub fn parse_timestamp(time: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.f %:z")
+        .or_else(|_| DateTime::parse_from_rfc3339(time))
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
+
+/// Formats a deadline as it is stored, RFC 3339 to the second with the offset
+/// of the local time zone, like `2024-05-01T18:00:00+02:00`
+pub fn format_deadline(time: DateTime<Local>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+/// Resolves a user supplied target to an item path
+///
+/// The target may be an exact path like `2:1` or `2.1`, a unique path prefix, or a unique description
+/// substring. When several items match and stdin is a terminal, the user is asked
+/// to choose one
+pub fn resolve_path(todo_list: &mut TodoList, target: &str) -> Result<String> {
+    let path = normalize_path(target);
+    if todo_list.find_by_path(&path).is_some() {
+        return Ok(path);
+    }
+
+    let matches = todo_list.find_matches(target);
+    match matches.len() {
+        0 => Err(errors::not_found(format!(
+            "No todo item matches '{}'",
+            target
+        ))),
+        1 => Ok(matches[0].0.clone()),
+        _ if io::stdin().is_terminal() => {
+            for (number, (path, item)) in matches.iter().enumerate() {
+                println!("{}) #{}: {}", number + 1, path, item.description);
             }
+            print!("Multiple items match '{}', choose one: ", target);
+            io::stdout().flush()?;
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line)?;
+            line.trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| matches.get(number.checked_sub(1)?))
+                .map(|(path, _)| path.clone())
+                .ok_or_else(|| anyhow::anyhow!("No item selected"))
+        }
+        _ => Err(errors::invalid(format!(
+            "'{}' is ambiguous, it matches: {}",
+            target,
+            matches
+                .iter()
+                .map(|(path, item)| format!("#{} ({})", path, item.description))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// Shows a checkbox list of pending items and returns the paths of the selected ones
+pub fn select_paths(todo_list: &TodoList, prompt: &str) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    let mut labels = Vec::new();
+    todo_list.walk(&mut |path, item| {
+        if !item.is_completed() {
+            labels.push(format!(
+                "{}#{}: {}",
+                "  ".repeat(path.len() - 1),
+                format_path(path),
+                item.description
+            ));
+            paths.push(format_path(path));
         }
+    });
+    if paths.is_empty() {
+        return Err(anyhow::anyhow!("No pending todo items to choose from"));
     }
+
+    let selected = MultiSelect::new()
+        .with_prompt(prompt)
+        .items(&labels)
+        .interact()?;
+    Ok(selected
+        .into_iter()
+        .map(|index| paths[index].clone())
+        .collect())
+}
+
+/// Runs a fuzzy finder over all items and returns the path of the chosen one
+///
+/// The finder is drawn on stderr so the result can be captured from stdout
+pub fn fuzzy_select_path(todo_list: &TodoList) -> Result<String> {
+    let mut paths = Vec::new();
+    let mut labels = Vec::new();
+    todo_list.walk(&mut |path, item| {
+        let status = if item.is_completed() { " ✓" } else { "" };
+        labels.push(format!(
+            "#{}: {}{}",
+            format_path(path),
+            item.description,
+            status
+        ));
+        paths.push(format_path(path));
+    });
+    if paths.is_empty() {
+        return Err(anyhow::anyhow!("No todo items to choose from"));
+    }
+
+    let selected = FuzzySelect::new()
+        .with_prompt("Item")
+        .items(&labels)
+        .interact_opt()?
+        .ok_or_else(|| anyhow::anyhow!("No item selected"))?;
+    Ok(paths[selected].clone())
+}
+
+/// Returns a random float in [0, 1) seeded from the std hasher's random keys
+pub fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    hasher.write_u128(nanos);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Lowercases a description and reduces it to its alphanumeric words
+fn normalize_description(description: &str) -> Vec<String> {
+    description
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Returns true if two descriptions likely describe the same task
+///
+/// Descriptions match when their normalized words are equal, or when they share
+/// at least three quarters of their distinct words
+pub fn similar_descriptions(a: &str, b: &str) -> bool {
+    let (a, b) = (normalize_description(a), normalize_description(b));
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    if a == b {
+        return true;
+    }
+    let a: HashSet<&String> = a.iter().collect();
+    let b: HashSet<&String> = b.iter().collect();
+    let shared = a.intersection(&b).count();
+    let total = a.union(&b).count();
+    shared * 4 >= total * 3
+}
+
+/// Returns true if the text looks like a web link
+pub fn is_url(text: &str) -> bool {
+    text.starts_with("https://") || text.starts_with("http://")
+}
+
+/// Turns an attachment argument into the reference stored on an item
+///
+/// Urls are kept as is. Files must exist and are stored relative to
+/// `attachment_dir` when inside it, absolute otherwise
+pub fn attachment_reference(target: &str, attachment_dir: Option<&String>) -> Result<String> {
+    if is_url(target) {
+        return Ok(target.to_string());
+    }
+    let path = expand_path(&target.to_string())?
+        .canonicalize()
+        .with_context(|| format!("Cannot attach '{}'", target))?;
+    if let Some(dir) = attachment_dir {
+        let dir = expand_path(dir)?.canonicalize()?;
+        if let Ok(relative) = path.strip_prefix(&dir) {
+            return Ok(relative.display().to_string());
+        }
+    }
+    Ok(path.display().to_string())
+}
+
+/// Resolves a stored attachment reference back to something `open_url` can launch
+pub fn resolve_attachment(reference: &str, attachment_dir: Option<&String>) -> Result<String> {
+    match attachment_dir {
+        Some(dir) if !is_url(reference) && Path::new(reference).is_relative() => {
+            Ok(expand_path(dir)?.join(reference).display().to_string())
+        }
+        _ => Ok(reference.to_string()),
+    }
+}
+
+/// Opens a url or file with $BROWSER, or the platform's default opener
+pub fn open_url(url: &str) -> Result<()> {
+    let opener = match env::var("BROWSER") {
+        Ok(browser) if !browser.is_empty() => browser,
+        _ if cfg!(target_os = "macos") => "open".to_string(),
+        _ if cfg!(windows) => "explorer".to_string(),
+        _ => "xdg-open".to_string(),
+    };
+    let status = Command::new(&opener)
+        .arg(url)
+        .status()
+        .with_context(|| format!("Failed to run '{}'", opener))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("'{}' exited with {}", opener, status));
+    }
+    Ok(())
+}
+
+/// Renders a `[####------] 40%` style bar, `width` being the count of cells
+pub fn progress_bar(done: usize, total: usize, width: usize) -> String {
+    let filled = (done * width).checked_div(total).unwrap_or(0);
+    let percent = (done * 100).checked_div(total).unwrap_or(0);
+    format!(
+        "[{}{}] {}%",
+        "#".repeat(filled),
+        "-".repeat(width - filled),
+        percent
+    )
+}
+
+/// Attributes extracted from a description written in the inline add syntax
+#[derive(Debug, Default)]
+pub struct InlineAttributes {
+    pub description: String,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+    pub context: Option<String>,
+    pub project: Option<String>,
+    pub deadline: Option<DateTime<Local>>,
+}
+
+/// Parses priority, tag, context, project and deadline tokens out of a description
+///
+/// e.g. "Ship release !high #work @office +launch due:tomorrow", with the token prefixes
+/// taken from the config. The remaining words form the description
+pub fn parse_inline(
+    description: &str,
+    syntax: &SyntaxConfig,
+    dates: &DatesConfig,
+) -> Result<InlineAttributes> {
+    let mut attributes = InlineAttributes::default();
+    let mut words = Vec::new();
+    for word in description.split_whitespace() {
+        let token = |prefix: &str| {
+            word.strip_prefix(prefix)
+                .filter(|rest| !prefix.is_empty() && !rest.is_empty())
+        };
+        if let Some(priority) = token(&syntax.priority) {
+            attributes.priority = Some(priority.parse()?);
+        } else if let Some(tag) = token(&syntax.tag) {
+            attributes.tags.push(tag.to_string());
+        } else if let Some(context) = token(&syntax.context) {
+            attributes.context = Some(context.to_string());
+        } else if let Some(project) = token(&syntax.project)
+            // keep offsets like "+2d" for deadline detection
+            .filter(|project| !project.starts_with(|c: char| c.is_ascii_digit()))
+        {
+            attributes.project = Some(project.to_string());
+        } else if let Some(deadline) = token(&syntax.deadline) {
+            attributes.deadline = Some(parse_deadline(Some(deadline.to_string()), dates)?);
+        } else {
+            words.push(word);
+        }
+    }
+    attributes.description = words.join(" ");
+    Ok(attributes)
+}
+
+/// A deadline phrase detected inside a description
+#[derive(Debug)]
+pub struct DetectedDeadline {
+    pub deadline: DateTime<Local>,
+    /// The matched words, including a leading connector like "by"
+    pub phrase: String,
+    /// The description with the phrase removed
+    pub stripped: String,
+}
+
+/// Detects a natural-language deadline phrase in a description
+///
+/// Recognises "today", "tomorrow", "nextweek", dates like "2024-06-01 [18:00]",
+/// durations like "+2d" and "in 3 days", optionally preceded by a connector
+/// such as "by", "on" or "before". Weekday abbreviations like "sun" also name
+/// other things, so they only count after "by", "on" or "due". The last phrase
+/// found wins
+pub fn detect_deadline(description: &str, dates: &DatesConfig) -> Option<DetectedDeadline> {
+    const CONNECTORS: [&str; 6] = ["by", "on", "due", "before", "until", "at"];
+    const WEEKDAY_CONNECTORS: [&str; 3] = ["by", "on", "due"];
+    let words: Vec<&str> = description.split_whitespace().collect();
+    let is_abbreviation = |word: &str| word.len() == 3 && word.parse::<Weekday>().is_ok();
+
+    let parse_phrase = |phrase: &[&str]| -> Option<DateTime<Local>> {
+        let candidate = match phrase {
+            ["in", amount, unit] if amount.parse::<u32>().is_ok() => {
+                format!("+{}{}", amount, unit.trim_end_matches('s'))
+            }
+            [word]
+                if word.starts_with('+')
+                    && !word[1..].starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                return None
+            }
+            _ => phrase.join(" "),
+        };
+        parse_deadline(Some(candidate), dates).ok()
+    };
+
+    for start in (0..words.len()).rev() {
+        for len in (1..=3).rev() {
+            let Some(phrase) = words.get(start..start + len) else {
+                continue;
+            };
+            let Some(deadline) = parse_phrase(phrase) else {
+                continue;
+            };
+            let connector = start
+                .checked_sub(1)
+                .map(|before| words[before].to_lowercase());
+            if is_abbreviation(phrase[0])
+                && !connector
+                    .as_deref()
+                    .is_some_and(|connector| WEEKDAY_CONNECTORS.contains(&connector))
+            {
+                continue;
+            }
+            let from = match connector {
+                Some(connector) if CONNECTORS.contains(&connector.as_str()) => start - 1,
+                _ => start,
+            };
+            let stripped = words[..from]
+                .iter()
+                .chain(&words[start + len..])
+                .copied()
+                .collect::<Vec<_>>()
+                .join(" ");
+            return Some(DetectedDeadline {
+                deadline,
+                phrase: words[from..start + len].join(" "),
+                stripped,
+            });
+        }
+    }
+    None
+}
+
+/// Returns true if the day is neither on a weekend nor one of the holidays,
+/// given as `2024-12-24` or as `12-25` for every year
+fn is_business_day(date: NaiveDate, holidays: &[String]) -> bool {
+    let (day, yearly) = (
+        date.format("%Y-%m-%d").to_string(),
+        date.format("%m-%d").to_string(),
+    );
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+        && !holidays
+            .iter()
+            .any(|holiday| *holiday == day || *holiday == yearly)
+}
+
+/// Moves `days` business days past `time`, keeping the time of day
+fn add_business_days(time: DateTime<Local>, days: u32, holidays: &[String]) -> DateTime<Local> {
+    let mut date = time.date_naive();
+    let mut left = days;
+    while left > 0 {
+        date = date.succ_opt().unwrap_or(date);
+        if is_business_day(date, holidays) {
+            left -= 1;
+        }
+    }
+    date.and_time(time.time())
+        .and_local_timezone(Local)
+        .earliest()
+        .unwrap_or(time + chrono::Duration::days((date - time.date_naive()).num_days()))
+}
+
+/// Returns the coming `weekday`, today if it is one, or with `next` the one in
+/// the week after this, weeks beginning on `week_start`
+fn weekday_date(today: NaiveDate, weekday: Weekday, next: bool, week_start: Weekday) -> NaiveDate {
+    if !next {
+        let ahead = weekday.days_since(today.weekday());
+        return today + chrono::Duration::days(ahead.into());
+    }
+    let next_week = today - chrono::Duration::days(today.weekday().days_since(week_start).into())
+        + chrono::Duration::weeks(1);
+    next_week + chrono::Duration::days(weekday.days_since(week_start).into())
+}
+
+/// Parses a time of day like "9am", "9:30pm" or "14:30"
+pub fn parse_time_of_day(text: &str) -> Option<NaiveTime> {
+    let text = text.trim().to_lowercase();
+    let (clock, offset) = match (text.strip_suffix("am"), text.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(0)),
+        (_, Some(clock)) => (clock, Some(12)),
+        _ => (text.as_str(), None),
+    };
+    let (hour, minute) = clock.trim().split_once(':').unwrap_or((clock.trim(), "0"));
+    let (hour, minute): (u32, u32) = (hour.parse().ok()?, minute.parse().ok()?);
+    let hour = match offset {
+        // 12am is midnight and 12pm noon
+        Some(offset) if (1..=12).contains(&hour) => hour % 12 + offset,
+        Some(_) => return None,
+        // a bare hour like "9" is no time, it could be anything
+        None if !clock.contains(':') => return None,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Returns the deadline of a day given without time, at the `due_time` of the
+/// `[dates]` config or else its end
+fn at_due_time(date: NaiveDate, dates: &DatesConfig) -> Option<DateTime<Local>> {
+    let time = dates
+        .due_time
+        .as_deref()
+        .and_then(parse_time_of_day)
+        .or_else(|| NaiveTime::from_hms_opt(23, 59, 59))?;
+    date.and_time(time).and_local_timezone(Local).earliest()
+}
+
+/// Returns the day a relative date like "tomorrow", "friday", "sat" or
+/// "next monday" names
+fn parse_relative_day(day: &str, now: DateTime<Local>, dates: &DatesConfig) -> Option<NaiveDate> {
+    let today = now.date_naive();
+    match day {
+        "today" => Some(today),
+        "tomorrow" => today.succ_opt(),
+        "nextweek" => Some(today + chrono::Duration::weeks(1)),
+        "next business day" | "nextbusinessday" => {
+            Some(add_business_days(now, 1, &dates.holidays).date_naive())
+        }
+        day => {
+            // "fri", "friday" or "next friday"
+            let (name, next) = match day.strip_prefix("next ") {
+                Some(name) => (name.trim(), true),
+                None => (day, false),
+            };
+            let week_start = dates
+                .week_start
+                .as_deref()
+                .and_then(|day| day.parse().ok())
+                .unwrap_or(Weekday::Mon);
+            Some(weekday_date(today, name.parse().ok()?, next, week_start))
+        }
+    }
+}
+
+/// Parses relative time expressions like "today", "tomorrow", "nextweek", "friday"
+/// or "+2days", days being followed by an optional time like "9am" or "14:30"
+fn parse_relative_time(
+    time_str: &str,
+    now: DateTime<Local>,
+    dates: &DatesConfig,
+) -> Option<DateTime<Local>> {
+    let lower = time_str.trim().to_lowercase();
+    if let Some(date) = parse_relative_day(&lower, now, dates) {
+        return at_due_time(date, dates);
+    }
+    if let Some((day, time)) = lower.rsplit_once(' ') {
+        if let (Some(date), Some(time)) = (
+            parse_relative_day(day.trim(), now, dates),
+            parse_time_of_day(time),
+        ) {
+            return date.and_time(time).and_local_timezone(Local).earliest();
+        }
+    }
+    let duration_str = lower.strip_prefix('+')?;
+    parse_duration_offset(duration_str, now, dates)
 }
 
 /// Parses duration offset strings like "2d", "3h", "30m" and adds them to a base time
+///
+/// Months and years like "1mo" or "1y" are calendar arithmetic, the day being
+/// clamped to the end of shorter months, and business days like "3bd" skip
+/// weekends and the holidays of the config. They are added before the rest,
+/// so "1mo 2d" is two days after the same day next month
 fn parse_duration_offset(
     duration_str: &str,
     base_time: DateTime<Local>,
+    dates: &DatesConfig,
 ) -> Option<DateTime<Local>> {
-    let parts: Vec<&str> = duration_str.split_whitespace().collect();
-    let mut duration = chrono::Duration::zero();
+    let mut months = 0;
+    let mut business_days = 0;
+    let mut rest = Vec::new();
+    for part in duration_str.split_whitespace() {
+        match split_unit(part)? {
+            (amount, "mo" | "month" | "months") => months += amount,
+            (amount, "y" | "year" | "years") => months += amount * 12,
+            (amount, "bd") => business_days += amount,
+            _ => rest.push(part),
+        }
+    }
+    if rest.is_empty() && months == 0 && business_days == 0 {
+        return None;
+    }
+    let time = base_time.checked_add_months(Months::new(months))?;
+    let time = add_business_days(time, business_days, &dates.holidays);
+    parse_duration(&rest.join(" ")).map(|duration| time + duration)
+}
+
+/// Splits a part of a duration like "12h" into its amount and unit
+fn split_unit(part: &str) -> Option<(u32, &str)> {
+    let index = part.find(|c: char| !c.is_ascii_digit())?;
+    Some((part[..index].parse().ok()?, &part[index..]))
+}
 
-    for part in parts {
-        if part.ends_with("d") || part.ends_with("days") {
-            let days = part
-                .trim_end_matches("d")
-                .trim_end_matches("day")
-                .trim_end_matches("days")
-                .parse()
-                .ok()?;
-            duration += chrono::Duration::days(days);
-        } else if part.ends_with("h") || part.ends_with("hours") {
-            let hours = part
-                .trim_end_matches("h")
-                .trim_end_matches("hour")
-                .trim_end_matches("hours")
-                .parse()
-                .ok()?;
-            duration += chrono::Duration::hours(hours);
-        } else if part.ends_with("m") || part.ends_with("min") || part.ends_with("minutes") {
-            let minutes = part
-                .trim_end_matches("m")
-                .trim_end_matches("min")
-                .trim_end_matches("minute")
-                .trim_end_matches("minutes")
-                .parse()
-                .ok()?;
-            duration += chrono::Duration::minutes(minutes);
-        }
-    }
-
-    Some(base_time + duration)
+/// Parses duration strings like "2d", "3h", "30m", "2w" or "1d 12h", None if
+/// a part has no known unit
+pub fn parse_duration(duration_str: &str) -> Option<chrono::Duration> {
+    let mut duration = chrono::Duration::zero();
+    for part in duration_str.split_whitespace() {
+        let (amount, unit) = split_unit(part)?;
+        let amount = i64::from(amount);
+        duration += match unit {
+            "w" | "week" | "weeks" => chrono::Duration::weeks(amount),
+            "d" | "day" | "days" => chrono::Duration::days(amount),
+            "h" | "hour" | "hours" => chrono::Duration::hours(amount),
+            "m" | "min" | "minute" | "minutes" => chrono::Duration::minutes(amount),
+            _ => return None,
+        };
+    }
+    Some(duration)
 }
 
 /// Parses deadline strings in various formats:
 /// - "YYYY-MM-DD HH:MM" - Absolute date and time
-/// - "YYYY-MM-DD" - Absolute date (defaults to 23:59:59)
-/// - "today", "tomorrow", "nextweek" - Relative dates
-/// - "+2d", "+3h", "+30m" - Relative durations
-pub fn parse_deadline(deadline: Option<String>) -> Result<DateTime<Local>> {
+/// - "YYYY-MM-DD" - Absolute date (at the configured `due_time`, else 23:59:59)
+/// - "today", "tomorrow", "nextweek", "friday", "sat", "next monday",
+///   "next business day" - Relative dates, at
+///   the `due_time` too unless followed by a time like "9am" or "14:30"
+/// - "+2d", "+3h", "+30m", "+2w", "+1mo", "+1y", "+3bd" - Relative durations, "bd"
+///   counting business days
+///
+/// Holidays, the due time of days and the day weeks start on come from `dates`
+pub fn parse_deadline(deadline: Option<String>, dates: &DatesConfig) -> Result<DateTime<Local>> {
     if let Some(deadline_str) = deadline {
         // 尝试解析完整日期时间格式: YYYY-MM-DD HH:MM
-        if let ParseResult::Ok(datetime) =
-            NaiveDateTime::parse_from_str(&deadline_str, "%Y-%m-%d %H:%M")
+        // the time is local, the earlier one when clocks are turned back
+        if let Some(datetime) = NaiveDateTime::parse_from_str(&deadline_str, "%Y-%m-%d %H:%M")
+            .ok()
+            .and_then(|datetime| datetime.and_local_timezone(Local).earliest())
         {
-            // return Ok(DateTime::from_naive_utc_and_offset(datetime, Local));
-            return Ok(DateTime::from_naive_utc_and_offset(
-                datetime,
-                *Local::now().offset(),
-            ));
+            return Ok(datetime);
         }
 
-        // 尝试解析日期格式: YYYY-MM-DD (默认为当天23:59)
-        if let ParseResult::Ok(datetime) = NaiveDateTime::parse_from_str(&deadline_str, "%Y-%m-%d")
+        // 尝试解析日期格式: YYYY-MM-DD (默认为 due_time 或当天23:59:59)
+        if let Some(deadline) = NaiveDate::parse_from_str(&deadline_str, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| at_due_time(date, dates))
         {
-            // let datetime = date.and_hms_opt(23, 59, 0).context("Invalid time")?;
-            return Ok(DateTime::from_naive_utc_and_offset(
-                datetime,
-                *Local::now().offset(),
-            ));
+            return Ok(deadline);
         }
 
         // 尝试解析相对时间
-        if let Some(relative_time) = parse_relative_time(&deadline_str) {
+        if let Some(relative_time) = parse_relative_time(&deadline_str, Local::now(), dates) {
             return Ok(relative_time);
         }
     }
 
-    Err(anyhow::anyhow!(
-        "Invalid deadline format. Supported formats:\n  - Absolute: YYYY-MM-DD HH:MM or YYYY-MM-DD\n  - Relative: 'today', 'tomorrow', 'nextweek'\n  - Duration: '+2d', '+3h', '+30m'"
-    ))
+    Err(errors::invalid("Invalid deadline format. Supported formats:\n  - Absolute: YYYY-MM-DD HH:MM or YYYY-MM-DD\n  - Relative: 'today', 'tomorrow', 'nextweek', 'friday', 'sat', 'next monday',\n    'next business day',\n    optionally followed by a time like '9am' or '14:30'\n  - Duration: '+2d', '+3h', '+30m', '+2w', '+1mo', '+1y', '+3bd' (business days)"))
+}
+
+#[cfg(test)]
+mod utils_tests {
+    use super::*;
+    use crate::todo_list::Status;
+    use chrono::{Duration, TimeZone};
+    use proptest::prelude::*;
+
+    /// Returns a golden todo file from `tests/fixtures`, `vN` being written in
+    /// format version N. Keep them as they are and add one when the version changes
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name)
+    }
+
+    /// Builds a list from added and removed items, each step being a description,
+    /// whether it goes below an earlier item, which one, whether that item is
+    /// removed instead, and the status, priority, tags and deadline of the new item
+    fn arb_todo_list() -> impl Strategy<Value = TodoList> {
+        let status = prop_oneof![
+            Just(Status::Todo),
+            Just(Status::Started),
+            Just(Status::Done)
+        ];
+        let priority = prop_oneof![
+            Just(Priority::Low),
+            Just(Priority::Medium),
+            Just(Priority::High)
+        ];
+        let step = (
+            "\\PC{0,24}",
+            any::<bool>(),
+            any::<prop::sample::Index>(),
+            prop::bool::weighted(0.2),
+            status,
+            prop::option::of(priority),
+            prop::collection::vec("[a-z]{1,8}", 0..3),
+            prop::option::of(0i64..1_000_000),
+        );
+        prop::collection::vec(step, 0..40).prop_map(|steps| {
+            let start = Local.timestamp_opt(1_700_000_000, 0).unwrap();
+            let mut list = TodoList::new();
+            list.version = SCHEMA_VERSION;
+            let mut paths: Vec<String> = Vec::new();
+            for (description, nested, index, remove, status, priority, tags, minutes) in steps {
+                let picked = (!paths.is_empty()).then(|| paths[index.index(paths.len())].clone());
+                if let (true, Some(path)) = (remove, &picked) {
+                    list.remove_item(path).unwrap();
+                    let below = format!("{}:", path);
+                    paths.retain(|other| other != path && !other.starts_with(&below));
+                    continue;
+                }
+                let parent = picked.filter(|_| nested);
+                let deadline = minutes.map(|minutes| start + Duration::minutes(minutes));
+                let item = list
+                    .add_item(description, deadline, parent.as_ref())
+                    .unwrap();
+                item.status = status;
+                item.priority = priority;
+                item.tags = tags;
+                paths.push(match parent {
+                    Some(parent) => format!("{}:{}", parent, item.id),
+                    None => item.id.to_string(),
+                });
+            }
+            list
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip(list in arb_todo_list()) {
+            let temp = tempfile::tempdir().unwrap();
+            let dir = temp.path();
+            let expected = serde_json::to_value(&list).unwrap();
+            for (name, compact) in [
+                ("todo.json", false),
+                ("todo.json", true),
+                ("todo.mpk", false),
+                ("todo.json.gz", false),
+            ] {
+                let path = dir.join(name);
+                let storage = StorageConfig { compact, ..Default::default() };
+                write_todo_list(&path, &list, &storage).unwrap();
+                let loaded = load_todo_list(&path).unwrap();
+                prop_assert_eq!(&serde_json::to_value(&loaded).unwrap(), &expected);
+                prop_assert!(!loaded.is_dirty());
+            }
+        }
+    }
+
+    #[test]
+    fn golden_v0() -> Result<()> {
+        let mut list = load_todo_list(&fixture("v0.json"))?;
+        let plan = list.parse_path(&"0".to_string())?;
+        assert!(plan.is_started());
+        assert_eq!(
+            plan.deadline.as_deref(),
+            Some(
+                format_deadline(DateTime::parse_from_rfc3339("2024-05-01T18:00:00Z")?.into())
+                    .as_str()
+            )
+        );
+        assert!(list.parse_path(&"0:0".to_string())?.is_completed());
+        assert_eq!(list.trash.entries[0].item.status, Status::Done);
+        let uuids: HashSet<&str> = list.iter().map(|(_, item)| item.uuid.as_str()).collect();
+        assert_eq!(uuids.len(), 3);
+        assert!(list.diagnose(false).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn golden_v1() -> Result<()> {
+        let mut list = load_todo_list(&fixture("v1.json"))?;
+        assert_eq!(list.version, SCHEMA_VERSION);
+        let plan = list.parse_path(&"0".to_string())?;
+        assert!(plan.is_started() && plan.starred);
+        assert_eq!(plan.priority, Some(Priority::Medium));
+        assert_eq!(
+            plan.deadline_time().unwrap(),
+            DateTime::parse_from_rfc3339("2024-05-01T18:00:00+00:00")?
+        );
+        let hotel = list.parse_path(&"0:0".to_string())?;
+        assert_eq!(
+            hotel.deadline_time().unwrap(),
+            DateTime::parse_from_rfc3339("2024-04-20T23:59:59+02:00")?
+        );
+        let summer = list.milestones.items[0].deadline.as_deref().unwrap();
+        assert_eq!(
+            DateTime::parse_from_rfc3339(summer)?,
+            DateTime::parse_from_rfc3339("2024-06-21T12:00:00+00:00")?
+        );
+        // the recycled ID of the file is handed out again
+        assert_eq!(list.add_item("new".to_string(), None, None)?.id, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn golden_v2() -> Result<()> {
+        let list = load_todo_list(&fixture("v2.json"))?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("golden.json");
+        write_todo_list(&path, &list, &StorageConfig::default())?;
+        let written = fs::read_to_string(&path)?;
+        assert_eq!(written, fs::read_to_string(fixture("v2.json"))?);
+
+        let packed = load_todo_list(&fixture("v2.mpk.gz"))?;
+        assert_eq!(serde_json::to_value(&packed)?, serde_json::to_value(&list)?);
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_v0() -> Result<()> {
+        let mut value = serde_json::json!({
+            "items": [{
+                "id": 0, "description": "a", "completed": false, "started": true,
+                "sub_list": {"items": [{"id": 0, "description": "b", "completed": true}]}
+            }],
+            "trash": {"entries": [{
+                "id": 0, "original_path": "1", "removed_at": "",
+                "item": {"id": 1, "description": "c", "completed": false}
+            }]}
+        });
+        assert_eq!(migrate(&mut value)?, SCHEMA_VERSION);
+        let mut list: TodoList = serde_json::from_value(value)?;
+        assert!(list.parse_path(&"0".to_string())?.is_started());
+        assert!(list.parse_path(&"0:0".to_string())?.is_completed());
+        assert_eq!(list.trash.entries[0].item.status, Status::Todo);
+
+        let mut newer = serde_json::json!({ "version": SCHEMA_VERSION + 1 });
+        assert!(migrate(&mut newer).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_v1() -> Result<()> {
+        let mut value = serde_json::json!({
+            "version": 1,
+            "items": [
+                {"id": 0, "description": "typed", "deadline": "2024-05-01 09:00:00 +00:00"},
+                {"id": 1, "description": "imported", "deadline": "2024-05-01 23:59:59 +02:00"},
+                {"id": 2, "description": "local", "deadline": "2030-05-01 09:00:00 +02:00"}
+            ]
+        });
+        migrate(&mut value)?;
+        let list: TodoList = serde_json::from_value(value)?;
+        // deadlines keep their instant, whole minutes included
+        let expected = [
+            "2024-05-01T09:00:00+00:00",
+            "2024-05-01T23:59:59+02:00",
+            "2030-05-01T09:00:00+02:00",
+        ];
+        for (item, expected) in list.items.iter().zip(expected) {
+            assert_eq!(
+                item.deadline_time().unwrap(),
+                DateTime::parse_from_rfc3339(expected)?
+            );
+            assert!(item.deadline.as_ref().unwrap().contains('T'));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn salvage() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("salvage.json");
+        fs::write(
+            &path,
+            r#"{"items": [{"id": 3, "description": "ok"}, {"id": "bad"}], "id_pool": 7}"#,
+        )?;
+        assert!(load_todo_list(&path).is_err());
+        let mut list = salvage_todo_list(&path).unwrap();
+        assert_eq!(list.items.len(), 1);
+        assert!(list.diagnose(false).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn local_file() -> Result<()> {
+        let temp = tempfile::tempdir()?;
+        let dir = temp.path();
+        let nested = dir.join("src").join("bin");
+        fs::create_dir_all(&nested)?;
+        fs::write(dir.join("todo.json"), r#"{"version": 2, "items": []}"#)?;
+        assert_eq!(find_local_file(&nested)?, Some(dir.join("todo.json")));
+        fs::write(dir.join("src").join(".todo"), "{}")?;
+        assert_eq!(
+            find_local_file(&nested)?,
+            Some(dir.join("src").join(".todo"))
+        );
+        // another tool's todo.json is left alone
+        fs::remove_file(dir.join("src").join(".todo"))?;
+        fs::write(
+            dir.join("todo.json"),
+            r#"{"name": "demo app", "tasks": []}"#,
+        )?;
+        assert!(find_local_file(&nested).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("/home/me/work/*", "/home/me/work/td"));
+        assert!(!glob_match("/home/me/work/*", "/home/me/work/td/src"));
+        assert!(!glob_match("/home/me/work/*", "/home/me/personal"));
+        assert!(glob_match("/src/*-api", "/src/billing-api"));
+        assert!(glob_match("/home/?e", "/home/me"));
+    }
+
+    #[test]
+    fn gzip_paths() {
+        let storage = StorageConfig::default();
+        let path = Path::new("/tmp/todo.mpk.gz");
+        assert!(is_gzip_path(path));
+        assert_eq!(storage.format_for(path), StorageFormat::Msgpack);
+        assert_eq!(archive_path(path), Path::new("/tmp/todo.mpk.archive.gz"));
+        assert_eq!(
+            archive_path(Path::new("/tmp/.todo")),
+            Path::new("/tmp/.todo.archive")
+        );
+    }
+
+    #[test]
+    fn similar() {
+        assert!(similar_descriptions("Buy milk", "buy  milk!"));
+        assert!(similar_descriptions(
+            "call the plumber about the sink",
+            "call plumber about the sink"
+        ));
+        assert!(!similar_descriptions("buy milk", "buy oat milk"));
+        assert!(!similar_descriptions("!!", "??"));
+    }
+
+    #[test]
+    fn progress() {
+        assert_eq!(progress_bar(1, 4, 8), "[##------] 25%");
+        assert_eq!(progress_bar(0, 0, 4), "[----] 0%");
+    }
+
+    #[test]
+    fn inline_syntax() -> Result<()> {
+        let (syntax, dates) = (SyntaxConfig::default(), DatesConfig::default());
+        let attributes = parse_inline(
+            "Ship release !high #work @office +launch due:today #v1",
+            &syntax,
+            &dates,
+        )?;
+        assert_eq!(attributes.description, "Ship release");
+        assert_eq!(attributes.project.as_deref(), Some("launch"));
+        assert_eq!(attributes.priority, Some(Priority::High));
+        assert_eq!(attributes.tags, vec!["work", "v1"]);
+        assert_eq!(attributes.context.as_deref(), Some("office"));
+        assert!(attributes.deadline.is_some());
+
+        let attributes = parse_inline("fix # in parser", &syntax, &dates)?;
+        assert_eq!(attributes.description, "fix # in parser");
+        let attributes = parse_inline("pay rent +2d", &syntax, &dates)?;
+        assert_eq!(attributes.description, "pay rent +2d");
+        assert!(parse_inline("task !urgent", &syntax, &dates).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn durations() {
+        use chrono::Duration;
+        assert_eq!(
+            parse_duration("1d 12h"),
+            Some(Duration::days(1) + Duration::hours(12))
+        );
+        assert_eq!(parse_duration("2w"), Some(Duration::days(14)));
+        assert_eq!(parse_duration("2days 30min"), Some(Duration::minutes(2910)));
+        assert_eq!(parse_duration("90m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("1mo"), None);
+        assert_eq!(parse_duration("3x"), None);
+        assert_eq!(parse_duration("5"), None);
+        assert_eq!(parse_duration("d"), None);
+    }
+
+    #[test]
+    fn calendar_offsets() {
+        use chrono::TimeZone;
+        let at = |y, m, d| Local.with_ymd_and_hms(y, m, d, 9, 0, 0).unwrap();
+        let dates = DatesConfig::default();
+        let offset = |text, base| parse_duration_offset(text, base, &dates);
+        assert_eq!(offset("1mo", at(2024, 1, 31)), Some(at(2024, 2, 29)));
+        assert_eq!(offset("1mo", at(2023, 1, 31)), Some(at(2023, 2, 28)));
+        assert_eq!(offset("3months", at(2024, 11, 30)), Some(at(2025, 2, 28)));
+        assert_eq!(offset("1y", at(2024, 2, 29)), Some(at(2025, 2, 28)));
+        assert_eq!(offset("1mo 2d", at(2024, 1, 31)), Some(at(2024, 3, 2)));
+        assert_eq!(offset("2d 1mo", at(2024, 1, 31)), Some(at(2024, 3, 2)));
+        assert_eq!(offset("1y 1mo", at(2024, 1, 15)), Some(at(2025, 2, 15)));
+        assert_eq!(offset("2w", at(2024, 12, 25)), Some(at(2025, 1, 8)));
+        assert_eq!(
+            offset("1d 3h", at(2024, 5, 1)),
+            Some(at(2024, 5, 2) + chrono::Duration::hours(3))
+        );
+        assert_eq!(offset("1mo 3x", at(2024, 5, 1)), None);
+        assert_eq!(offset("", at(2024, 5, 1)), None);
+    }
+
+    #[test]
+    fn relative_times() {
+        use chrono::TimeZone;
+        // a Wednesday afternoon
+        let now = Local.with_ymd_and_hms(2024, 5, 1, 15, 0, 0).unwrap();
+        let at = |d, h, m| Local.with_ymd_and_hms(2024, 5, d, h, m, 0).unwrap();
+        let dates = DatesConfig::default();
+        let parse_relative_time = |text| parse_relative_time(text, now, &dates);
+        assert_eq!(parse_relative_time("tomorrow 9am"), Some(at(2, 9, 0)));
+        assert_eq!(parse_relative_time("Friday 9:30pm"), Some(at(3, 21, 30)));
+        assert_eq!(parse_relative_time("nextweek 14:30"), Some(at(8, 14, 30)));
+        assert_eq!(parse_relative_time("today 12am"), Some(at(1, 0, 0)));
+        assert_eq!(parse_relative_time("tomorrow 13pm"), None);
+        assert_eq!(parse_relative_time("tomorrow 9"), None);
+        assert_eq!(parse_relative_time("+2h"), Some(at(1, 17, 0)));
+    }
+
+    #[test]
+    fn weekdays() {
+        // a Wednesday
+        let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 5, d).unwrap();
+        let monday = Weekday::Mon;
+        assert_eq!(weekday_date(today, Weekday::Wed, false, monday), today);
+        assert_eq!(weekday_date(today, Weekday::Fri, false, monday), day(3));
+        assert_eq!(weekday_date(today, Weekday::Mon, false, monday), day(6));
+        assert_eq!(weekday_date(today, Weekday::Mon, true, monday), day(6));
+        assert_eq!(weekday_date(today, Weekday::Fri, true, monday), day(10));
+        // weeks beginning on Sunday end on Saturday
+        assert_eq!(
+            weekday_date(today, Weekday::Sat, true, Weekday::Sun),
+            day(11)
+        );
+        assert_eq!(
+            weekday_date(today, Weekday::Sun, true, Weekday::Sun),
+            day(5)
+        );
+        let now = today
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap();
+        let dates = DatesConfig::default();
+        assert_eq!(parse_relative_day("sat", now, &dates), Some(day(4)));
+        assert_eq!(parse_relative_day("next thu", now, &dates), Some(day(9)));
+        assert_eq!(parse_relative_day("next week", now, &dates), None);
+        let sunday = DatesConfig {
+            week_start: Some("sunday".to_string()),
+            ..DatesConfig::default()
+        };
+        assert_eq!(parse_relative_day("next sat", now, &sunday), Some(day(11)));
+    }
+
+    #[test]
+    fn business_days() {
+        use chrono::TimeZone;
+        // a Friday before a Monday holiday
+        let friday = Local.with_ymd_and_hms(2024, 12, 20, 9, 30, 0).unwrap();
+        let holidays = ["2024-12-23".to_string(), "12-25".to_string()];
+        let next = add_business_days(friday, 2, &holidays);
+        assert_eq!(
+            next,
+            Local.with_ymd_and_hms(2024, 12, 26, 9, 30, 0).unwrap()
+        );
+        assert_eq!(add_business_days(friday, 1, &[]).weekday(), Weekday::Mon);
+        assert!(!is_business_day(
+            NaiveDate::from_ymd_opt(2030, 12, 25).unwrap(),
+            &holidays
+        ));
+    }
+
+    #[test]
+    fn deadline_detection() {
+        let dates = DatesConfig::default();
+        let detected = detect_deadline("pay rent by tomorrow", &dates).unwrap();
+        assert_eq!(detected.phrase, "by tomorrow");
+        assert_eq!(detected.stripped, "pay rent");
+
+        let detected = detect_deadline("call bank in 3 days please", &dates).unwrap();
+        assert_eq!(detected.phrase, "in 3 days");
+        assert_eq!(detected.stripped, "call bank please");
+
+        let detected = detect_deadline("submit on 2024-06-01 18:00", &dates).unwrap();
+        assert_eq!(detected.stripped, "submit");
+
+        assert!(detect_deadline("buy milk", &dates).is_none());
+        assert!(detect_deadline("buy sun cream", &dates).is_none());
+        assert!(detect_deadline("ask about wed plans", &dates).is_none());
+        let detected = detect_deadline("water plants by sat", &dates).unwrap();
+        assert_eq!(detected.stripped, "water plants");
+        assert!(detect_deadline("water plants saturday", &dates).is_some());
+        assert!(detect_deadline("c++ homework", &dates).is_none());
+    }
 }
@@ -0,0 +1,361 @@
+use crate::todo_list::{Priority, TodoItem, TodoList};
+use crate::utils::format_path;
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use std::collections::BTreeMap;
+
+/// Prints a day-by-day agenda of pending items with deadlines
+///
+/// Overdue items are pinned at the top, followed by one section per day
+/// within the next `days` days, each sorted by deadline time
+pub fn print_agenda(todo_list: &TodoList, days: i64) {
+    let now = Local::now();
+    let last_day = (now + Duration::days(days.saturating_sub(1))).date_naive();
+    let mut overdue: Vec<(DateTime<Local>, String)> = Vec::new();
+    let mut by_day: BTreeMap<NaiveDate, Vec<(DateTime<Local>, String)>> = BTreeMap::new();
+
+    todo_list.walk(&mut |path, item| {
+        let Some(deadline) = item.deadline_time().filter(|_| !item.is_completed()) else {
+            return;
+        };
+        let line = format!("#{}: {}", format_path(path), item.description);
+        if deadline < now {
+            overdue.push((deadline, line));
+        } else if deadline.date_naive() <= last_day {
+            by_day
+                .entry(deadline.date_naive())
+                .or_default()
+                .push((deadline, line));
+        }
+    });
+
+    if overdue.is_empty() && by_day.is_empty() {
+        println!("Nothing scheduled in the next {} days.", days);
+        return;
+    }
+
+    if !overdue.is_empty() {
+        overdue.sort();
+        println!("Overdue:");
+        for (deadline, line) in overdue {
+            println!("  {} {}", deadline.format("%Y-%m-%d %H:%M"), line);
+        }
+    }
+
+    for (day, mut entries) in by_day {
+        entries.sort();
+        let label = match (day - now.date_naive()).num_days() {
+            0 => " (today)",
+            1 => " (tomorrow)",
+            _ => "",
+        };
+        println!("{}{}:", day.format("%a %Y-%m-%d"), label);
+        for (deadline, line) in entries {
+            println!("  {} {}", deadline.format("%H:%M"), line);
+        }
+    }
+}
+
+/// Prints the pending items of several lists together, each labeled with the
+/// name of its list, those due soonest first and those without deadline last,
+/// returning false if there were none
+pub fn print_all_lists(lists: &[(String, TodoList)], all: bool) -> bool {
+    let mut entries: Vec<(Option<DateTime<Local>>, &str, String, &TodoItem)> = Vec::new();
+    for (name, todo_list) in lists {
+        todo_list.walk(&mut |path, item| {
+            if all || !item.is_completed() {
+                entries.push((item.deadline_time(), name, format_path(path), item));
+            }
+        });
+    }
+    if entries.is_empty() {
+        println!("No todo items found.");
+        return false;
+    }
+    // a stable sort keeps the order of lists and items among equal deadlines
+    entries.sort_by_key(|(deadline, ..)| (deadline.is_none(), *deadline));
+    println!("All lists({}):", entries.len());
+    for (_, name, path, item) in entries {
+        println!("[{}] {}", name, item.format_info_at(&path));
+    }
+    true
+}
+
+/// Output format for reports
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Markdown,
+}
+
+/// Prints what was completed, added, and is still pending over the last `days` days
+pub fn print_report(todo_list: &TodoList, days: i64, format: ReportFormat) {
+    let now = Local::now();
+    let since = now - Duration::days(days);
+    let mut completed: Vec<&TodoItem> = Vec::new();
+    let mut added: Vec<&TodoItem> = Vec::new();
+    let mut pending: Vec<&TodoItem> = Vec::new();
+
+    todo_list.walk(&mut |_, item| {
+        if item.completed_time().is_some_and(|time| time >= since) {
+            completed.push(item);
+        }
+        if item.created_time().is_some_and(|time| time >= since) {
+            added.push(item);
+        }
+        if !item.is_completed() {
+            pending.push(item);
+        }
+    });
+
+    let title = format!(
+        "Report {} – {}",
+        since.format("%Y-%m-%d"),
+        now.format("%Y-%m-%d")
+    );
+    let sections = [
+        ("Completed", completed),
+        ("Added", added),
+        ("Pending", pending),
+    ];
+    match format {
+        ReportFormat::Text => {
+            println!("{}", title);
+            for (name, items) in sections {
+                println!("{} ({}):", name, items.len());
+                for item in items {
+                    println!("  {}", item.description);
+                }
+            }
+        }
+        ReportFormat::Markdown => {
+            println!("## {}", title);
+            for (name, items) in sections {
+                println!();
+                println!("### {} ({})", name, items.len());
+                println!();
+                for item in items {
+                    let mark = if item.is_completed() { "x" } else { " " };
+                    println!("- [{}] {}", mark, item.description);
+                }
+            }
+        }
+    }
+}
+
+/// Prints completions of the last `days` days grouped by day, oldest day first
+pub fn print_log(todo_list: &TodoList, days: i64) {
+    let now = Local::now();
+    let first_day = (now - Duration::days(days.saturating_sub(1))).date_naive();
+    let mut by_day: BTreeMap<NaiveDate, Vec<(DateTime<Local>, &str)>> = BTreeMap::new();
+
+    todo_list.walk(&mut |_, item| {
+        let Some(time) = item.completed_time().filter(|_| item.is_completed()) else {
+            return;
+        };
+        if time.date_naive() >= first_day {
+            by_day
+                .entry(time.date_naive())
+                .or_default()
+                .push((time, &item.description));
+        }
+    });
+
+    if by_day.is_empty() {
+        println!("Nothing completed in the last {} days.", days);
+        return;
+    }
+
+    for (day, mut entries) in by_day {
+        entries.sort();
+        let noun = if entries.len() == 1 { "item" } else { "items" };
+        let descriptions: Vec<&str> = entries.iter().map(|(_, desc)| *desc).collect();
+        println!(
+            "{}: {} {} — {}",
+            day.format("%a %Y-%m-%d"),
+            entries.len(),
+            noun,
+            descriptions.join(", ")
+        );
+    }
+}
+
+/// Prints every project with its open and total item counts and completion percentage
+pub fn print_projects(todo_list: &TodoList) {
+    let mut projects: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    todo_list.walk(&mut |_, item| {
+        if let Some(project) = &item.project {
+            let (open, total) = projects.entry(project).or_default();
+            *open += usize::from(!item.is_completed());
+            *total += 1;
+        }
+    });
+
+    if projects.is_empty() {
+        println!("No projects found.");
+        return;
+    }
+    for (project, (open, total)) in projects {
+        println!(
+            "+{}: {} open / {} total ({}% complete)",
+            project,
+            open,
+            total,
+            (total - open) * 100 / total
+        );
+    }
+}
+
+/// Kind of chart to plot
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ChartKind {
+    /// Pending item count at the end of each day
+    Burndown,
+    /// Items completed per day
+    Velocity,
+}
+
+/// Renders values as a unicode sparkline scaled between zero and the maximum
+fn sparkline(values: &[usize]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|&value| BARS[value * (BARS.len() - 1) / max])
+        .collect()
+}
+
+/// Prints a sparkline chart over the last `days` days derived from item timestamps
+///
+/// Items without a creation time are treated as always existing, completed items
+/// without a completion time as completed before the charted period
+pub fn print_chart(todo_list: &TodoList, kind: ChartKind, days: i64) {
+    let today = Local::now().date_naive();
+    let first_day = today - Duration::days(days.max(1) - 1);
+    let mut values = Vec::new();
+
+    for offset in 0..days.max(1) {
+        let day = first_day + Duration::days(offset);
+        let value = match kind {
+            ChartKind::Burndown => todo_list.count_where(|item| {
+                let created = item
+                    .created_time()
+                    .is_none_or(|time| time.date_naive() <= day);
+                let open = if item.is_completed() {
+                    item.completed_time()
+                        .is_some_and(|time| time.date_naive() > day)
+                } else {
+                    true
+                };
+                created && open
+            }),
+            ChartKind::Velocity => todo_list.count_where(|item| {
+                item.completed_time()
+                    .is_some_and(|time| time.date_naive() == day)
+            }),
+        };
+        values.push(value);
+    }
+
+    let title = match kind {
+        ChartKind::Burndown => "Pending items",
+        ChartKind::Velocity => "Completed per day",
+    };
+    println!(
+        "{} ({} – {}):",
+        title,
+        first_day.format("%Y-%m-%d"),
+        today.format("%Y-%m-%d")
+    );
+    println!("  {}", sparkline(&values));
+    println!(
+        "  min {}  max {}  now {}",
+        values.iter().min().unwrap_or(&0),
+        values.iter().max().unwrap_or(&0),
+        values.last().unwrap_or(&0)
+    );
+}
+
+/// Prints pending items in a 2×2 urgent/important grid, or as JSON
+///
+/// Items with at least medium priority are important, items due within
+/// `urgent_within` (or overdue) are urgent
+pub fn print_matrix(todo_list: &TodoList, urgent_within: Duration, json: bool) {
+    let now = Local::now();
+    // quadrants in reading order: do, schedule, delegate, eliminate
+    let mut quadrants: [Vec<(String, &TodoItem)>; 4] = Default::default();
+    todo_list.walk(&mut |path, item| {
+        if item.is_completed() || item.someday {
+            return;
+        }
+        let important = item
+            .priority
+            .is_some_and(|priority| priority >= Priority::Medium);
+        let urgent = item
+            .deadline_time()
+            .is_some_and(|deadline| deadline - now <= urgent_within);
+        let quadrant = match (urgent, important) {
+            (true, true) => 0,
+            (false, true) => 1,
+            (true, false) => 2,
+            (false, false) => 3,
+        };
+        quadrants[quadrant].push((format_path(path), item));
+    });
+    let names = ["do", "schedule", "delegate", "eliminate"];
+
+    if json {
+        let value: serde_json::Map<String, serde_json::Value> = names
+            .iter()
+            .zip(&quadrants)
+            .map(|(name, items)| {
+                let items = items
+                    .iter()
+                    .map(|(path, item)| {
+                        serde_json::json!({
+                            "path": path,
+                            "description": item.description,
+                            "priority": item.priority,
+                            "deadline": item.deadline,
+                        })
+                    })
+                    .collect();
+                (name.to_string(), serde_json::Value::Array(items))
+            })
+            .collect();
+        println!("{}", serde_json::Value::Object(value));
+        return;
+    }
+
+    let width = crossterm::terminal::size()
+        .map_or(80, |(width, _)| width as usize)
+        .clamp(40, 160)
+        / 2
+        - 2;
+    let titles = [
+        "DO (urgent, important)",
+        "SCHEDULE (important)",
+        "DELEGATE (urgent)",
+        "ELIMINATE",
+    ];
+    let cell = |text: &str| -> String {
+        let text: String = text.chars().take(width).collect();
+        format!("{:<width$}", text, width = width)
+    };
+    let border = format!("+{}+{}+", "-".repeat(width + 1), "-".repeat(width + 1));
+
+    println!("{}", border);
+    for row in [0, 2] {
+        println!("|{} |{} |", cell(titles[row]), cell(titles[row + 1]));
+        let lines = quadrants[row].len().max(quadrants[row + 1].len());
+        for line in 0..lines {
+            let entry = |quadrant: usize| {
+                quadrants[quadrant]
+                    .get(line)
+                    .map(|(path, item)| format!("#{} {}", path, item.description))
+                    .unwrap_or_default()
+            };
+            println!("|{} |{} |", cell(&entry(row)), cell(&entry(row + 1)));
+        }
+        println!("{}", border);
+    }
+}
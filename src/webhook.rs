@@ -0,0 +1,60 @@
+use crate::config::WebhookConfig;
+use crate::events::{self, Event, ItemEvent};
+use crate::todo_list::TodoList;
+use crate::utils::{parse_timestamp, sibling_path};
+use anyhow::Result;
+use chrono::Local;
+use serde_json::json;
+use std::{fs, path::Path, time::Duration};
+
+/// Posts every event to the webhooks subscribed to it
+///
+/// A webhook that can't be reached only produces a warning, it never fails the command
+pub fn fire(webhooks: &[WebhookConfig], file_path: &Path, events: &[ItemEvent]) {
+    if webhooks.is_empty() || events.is_empty() {
+        return;
+    }
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(5))
+        .build();
+    for event in events {
+        let payload = json!({
+            "event": event.event,
+            "file": file_path.display().to_string(),
+            "path": event.path,
+            "item": event.item,
+        });
+        for webhook in webhooks.iter().filter(|webhook| webhook.wants(event.event)) {
+            let request = agent
+                .post(&webhook.url)
+                .set("Content-Type", "application/json");
+            if let Err(error) = request.send_string(&payload.to_string()) {
+                eprintln!("Webhook {} failed: {}", webhook.url, error);
+            }
+        }
+    }
+}
+
+/// Fires `overdue` for items whose deadline passed since the last run
+///
+/// The time of the last check is kept in `<file>.webhooks`, the first run only
+/// records it so existing overdue items aren't all reported at once
+pub fn check_overdue(webhooks: &[WebhookConfig], file_path: &Path, list: &TodoList) -> Result<()> {
+    if !webhooks.iter().any(|webhook| webhook.wants(Event::Overdue)) {
+        return Ok(());
+    }
+    let state_path = sibling_path(file_path, ".webhooks");
+    let now = Local::now();
+    let last_check = fs::read_to_string(&state_path)
+        .ok()
+        .and_then(|content| parse_timestamp(content.trim()));
+    if let Some(last_check) = last_check {
+        fire(
+            webhooks,
+            file_path,
+            &events::newly_overdue(list, last_check, now),
+        );
+    }
+    fs::write(state_path, now.to_string())?;
+    Ok(())
+}